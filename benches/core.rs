@@ -0,0 +1,72 @@
+use chip8::Chip8;
+use chip8::state::Snapshot;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+
+const ROMS: [&str; 2] = ["IBM Logo.ch8", "test_opcode.ch8"];
+const FRAMES: usize = 60;
+const CYCLES_PER_FRAME: usize = 10;
+
+/** A single cycle of a 7XNN, the cheapest kind of instruction */
+fn cycle(c: &mut Criterion) {
+    let mut chip8 = Chip8::with_seed(0);
+    chip8.load_memory(0x200, &[0x70, 0x01]);
+    c.bench_function("cycle", |b| {
+        b.iter(|| {
+            chip8.pc = 0x200;
+            black_box(&mut chip8).cycle();
+        })
+    });
+}
+
+/** Draws an 8x15 sprite from the font at the top left corner */
+fn op_dxyn(c: &mut Criterion) {
+    let mut chip8 = Chip8::with_seed(0);
+    chip8.idx_reg = 0x50;
+    chip8.load_memory(0x200, &[0xD0, 0x1F]);
+    c.bench_function("op_dxyn", |b| {
+        b.iter(|| {
+            chip8.pc = 0x200;
+            black_box(&mut chip8).cycle();
+        })
+    });
+}
+
+fn save_state(c: &mut Criterion) {
+    let rom = std::fs::read("ROMs/test_opcode.ch8").expect("Unable to read ROM");
+    let mut chip8 = Chip8::with_seed(0);
+    chip8.load_memory(0x200, &rom);
+    for _ in 0..FRAMES {
+        chip8.run_frame(CYCLES_PER_FRAME);
+    }
+    let bytes = chip8.snapshot().to_bytes();
+
+    c.bench_function("save_state/to_bytes", |b| {
+        b.iter(|| black_box(&chip8).snapshot().to_bytes())
+    });
+    c.bench_function("save_state/from_bytes", |b| {
+        b.iter(|| Snapshot::from_bytes(black_box(&bytes)).unwrap())
+    });
+}
+
+/** Loads and runs a bundled ROM for a second of emulated time */
+fn full_rom(c: &mut Criterion) {
+    let mut group = c.benchmark_group("full_rom");
+    for name in ROMS {
+        let rom = std::fs::read(format!("ROMs/{name}")).expect("Unable to read ROM");
+        group.bench_with_input(BenchmarkId::from_parameter(name), &rom, |b, rom| {
+            b.iter(|| {
+                let mut chip8 = Chip8::with_seed(0);
+                chip8.load_memory(0x200, rom);
+                for _ in 0..FRAMES {
+                    chip8.run_frame(CYCLES_PER_FRAME);
+                }
+                chip8
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, cycle, op_dxyn, save_state, full_rom);
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+use chip8::Chip8;
+use chip8::blocks::BlockEngine;
+use chip8::engine::{Engine, Interpreter};
+use chip8::predecode::PredecodedEngine;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+
+/** Roughly a frame of a fast interpreter, enough to reach each ROM's main loop quickly */
+const CYCLES_PER_FRAME: usize = 1000;
+const ROMS: [&str; 2] = ["IBM Logo.ch8", "test_opcode.ch8"];
+
+/** Both ROMs settle into a single jump, so also measure a loop of mixed ALU ops */
+const ALU_LOOP: [u8; 20] = [
+    0x60, 0x01, 0x61, 0x02, 0x80, 0x14, 0x81, 0x05, 0x82, 0x03, 0x70, 0x03, 0x30, 0x00, 0x82, 0x06,
+    0xA2, 0x00, 0x12, 0x04,
+];
+
+fn machine(rom: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::with_seed(0);
+    chip8.load_memory(0x200, rom);
+    chip8
+}
+
+fn engines(c: &mut Criterion) {
+    let mut group = c.benchmark_group("engines");
+    let mut workloads: Vec<(&str, Vec<u8>)> = ROMS
+        .iter()
+        .map(|name| {
+            let rom = std::fs::read(format!("ROMs/{name}")).expect("Unable to read ROM");
+            (*name, rom)
+        })
+        .collect();
+    workloads.push(("alu loop", ALU_LOOP.to_vec()));
+
+    for (name, rom) in &workloads {
+        group.bench_with_input(BenchmarkId::new("interpreter", name), rom, |b, rom| {
+            let mut chip8 = machine(rom);
+            b.iter(|| Interpreter.run_frame(black_box(&mut chip8), CYCLES_PER_FRAME))
+        });
+        group.bench_with_input(BenchmarkId::new("predecoded", name), rom, |b, rom| {
+            let mut chip8 = machine(rom);
+            let mut engine = PredecodedEngine::new();
+            b.iter(|| engine.run_frame(black_box(&mut chip8), CYCLES_PER_FRAME))
+        });
+        group.bench_with_input(BenchmarkId::new("blocks", name), rom, |b, rom| {
+            let mut chip8 = machine(rom);
+            let mut engine = BlockEngine::new();
+            b.iter(|| engine.run_frame(black_box(&mut chip8), CYCLES_PER_FRAME))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, engines);
+criterion_main!(benches);
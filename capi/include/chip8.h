@@ -0,0 +1,152 @@
+#ifndef CHIP8_H
+#define CHIP8_H
+
+/* Generated by cbindgen from chip8-capi. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define CHIP8_WIDTH 64
+
+#define CHIP8_HEIGHT 32
+
+/**
+ * Largest ROM that fits in memory from 0x200
+ */
+#define CHIP8_MAX_ROM_SIZE 3584
+
+/**
+ * Result of every fallible call. Nothing panics across the C boundary
+ */
+typedef enum chip8_status {
+  CHIP8_STATUS_OK = 0,
+  CHIP8_STATUS_NULL_POINTER,
+  CHIP8_STATUS_INVALID_ARGUMENT,
+  CHIP8_STATUS_ROM_TOO_LARGE,
+  CHIP8_STATUS_UNSUPPORTED_OPCODE,
+  CHIP8_STATUS_STACK_UNDERFLOW,
+  CHIP8_STATUS_PC_OUT_OF_BOUNDS,
+  CHIP8_STATUS_MEMORY_OUT_OF_BOUNDS,
+  CHIP8_STATUS_BUFFER_TOO_SMALL,
+  CHIP8_STATUS_INVALID_STATE,
+  /**
+   * An unexpected internal error. The handle should be destroyed
+   */
+  CHIP8_STATUS_PANIC,
+  CHIP8_STATUS_STACK_OVERFLOW,
+  CHIP8_STATUS_MACHINE_CODE,
+} chip8_status;
+
+/**
+ * Opaque handle to an emulator, created by `chip8_create` and freed by `chip8_destroy`
+ */
+typedef struct chip8_t chip8_t;
+
+typedef struct chip8_registers {
+  uint16_t pc;
+  uint16_t i;
+  uint8_t v[16];
+  uint8_t delay_timer;
+  uint8_t sound_timer;
+} chip8_registers;
+
+/**
+ * Creates an emulator whose CXNN results are reproducible for the given seed
+ */
+struct chip8_t *chip8_create(uint64_t seed);
+
+/**
+ * Frees an emulator. Passing null does nothing.
+ *
+ * # Safety
+ * `handle` must come from `chip8_create` and not be used afterwards.
+ */
+void chip8_destroy(struct chip8_t *handle);
+
+/**
+ * Restarts the machine and loads a ROM at 0x200.
+ *
+ * # Safety
+ * `handle` must be valid and `data` must point to `len` readable bytes.
+ */
+enum chip8_status chip8_load_rom(struct chip8_t *handle, const uint8_t *data, size_t len);
+
+/**
+ * Runs a number of instructions without ticking the timers, stopping at the first error.
+ *
+ * # Safety
+ * `handle` must be valid.
+ */
+enum chip8_status chip8_run_cycles(struct chip8_t *handle, uint32_t cycles);
+
+/**
+ * Runs one 60Hz frame: a number of instructions followed by a timer tick.
+ *
+ * # Safety
+ * `handle` must be valid.
+ */
+enum chip8_status chip8_run_frame(struct chip8_t *handle, uint32_t cycles_per_frame);
+
+/**
+ * Presses or releases a key from 0x0 to 0xF. Only one key is held at a time.
+ *
+ * # Safety
+ * `handle` must be valid.
+ */
+enum chip8_status chip8_set_key(struct chip8_t *handle, uint8_t key, bool pressed);
+
+/**
+ * Copies the display into `out`, one byte per pixel row by row: 1 for lit and 0 otherwise.
+ *
+ * # Safety
+ * `handle` must be valid and `out` must point to `len` writable bytes.
+ */
+enum chip8_status chip8_get_framebuffer(const struct chip8_t *handle, uint8_t *out, size_t len);
+
+/**
+ * Reads the PC, I, V0 to VF and both timers.
+ *
+ * # Safety
+ * `handle` must be valid and `out` must point to a writable `chip8_registers`.
+ */
+enum chip8_status chip8_get_registers(const struct chip8_t *handle, struct chip8_registers *out);
+
+/**
+ * Overwrites the PC, I, V0 to VF and both timers, e.g. from a debugger.
+ *
+ * # Safety
+ * `handle` must be valid and `registers` must point to a readable `chip8_registers`.
+ */
+enum chip8_status chip8_set_registers(struct chip8_t *handle,
+                                      const struct chip8_registers *registers);
+
+/**
+ * Writes a save state into `out`. The size needed is always stored in `written`, so calling
+ * with a null `out` and zero `capacity` queries it and returns `CHIP8_STATUS_BUFFER_TOO_SMALL`.
+ *
+ * # Safety
+ * `handle` must be valid, `out` must point to `capacity` writable bytes and `written` must
+ * be writable or null.
+ */
+enum chip8_status chip8_save_state(const struct chip8_t *handle,
+                                   uint8_t *out,
+                                   size_t capacity,
+                                   size_t *written);
+
+/**
+ * Restores a save state written by `chip8_save_state`.
+ *
+ * # Safety
+ * `handle` must be valid and `data` must point to `len` readable bytes.
+ */
+enum chip8_status chip8_load_state(struct chip8_t *handle, const uint8_t *data, size_t len);
+
+/**
+ * A short English description of a status, valid for the life of the program
+ */
+const char *chip8_status_message(enum chip8_status status);
+
+#endif  /* CHIP8_H */
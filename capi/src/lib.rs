@@ -0,0 +1,327 @@
+use chip8::error::Chip8Error;
+use chip8::rom::ROM;
+use chip8::state::Snapshot;
+use chip8::{Chip8, HEIGHT, WIDTH};
+use std::ffi::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+pub const CHIP8_WIDTH: usize = 64;
+pub const CHIP8_HEIGHT: usize = 32;
+/// Largest ROM that fits in memory from 0x200
+pub const CHIP8_MAX_ROM_SIZE: usize = 3584;
+const _: () = assert!(CHIP8_WIDTH == WIDTH && CHIP8_HEIGHT == HEIGHT);
+
+/// Opaque handle to an emulator, created by `chip8_create` and freed by `chip8_destroy`
+pub struct Chip8Handle {
+    chip8: Chip8,
+    seed: u64,
+}
+
+/// Result of every fallible call. Nothing panics across the C boundary
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8Status {
+    Ok = 0,
+    NullPointer,
+    InvalidArgument,
+    RomTooLarge,
+    UnsupportedOpcode,
+    StackUnderflow,
+    PcOutOfBounds,
+    MemoryOutOfBounds,
+    BufferTooSmall,
+    InvalidState,
+    /// An unexpected internal error. The handle should be destroyed
+    Panic,
+    StackOverflow,
+    MachineCode,
+}
+impl From<Chip8Error> for Chip8Status {
+    fn from(error: Chip8Error) -> Self {
+        match error {
+            Chip8Error::UnsupportedOpcode(_) => Chip8Status::UnsupportedOpcode,
+            Chip8Error::StackUnderflow => Chip8Status::StackUnderflow,
+            Chip8Error::StackOverflow => Chip8Status::StackOverflow,
+            Chip8Error::PcOutOfBounds(_) => Chip8Status::PcOutOfBounds,
+            Chip8Error::MemoryOutOfBounds(_) => Chip8Status::MemoryOutOfBounds,
+            Chip8Error::MachineCode(_) => Chip8Status::MachineCode,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Chip8Registers {
+    pub pc: u16,
+    pub i: u16,
+    pub v: [u8; 16],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+}
+
+/// Runs a call, turning panics into `Chip8Status::Panic`
+fn guard(call: impl FnOnce() -> Result<(), Chip8Status>) -> Chip8Status {
+    match panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => Chip8Status::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => Chip8Status::Panic,
+    }
+}
+
+unsafe fn handle_mut<'a>(handle: *mut Chip8Handle) -> Result<&'a mut Chip8Handle, Chip8Status> {
+    unsafe { handle.as_mut() }.ok_or(Chip8Status::NullPointer)
+}
+
+unsafe fn handle_ref<'a>(handle: *const Chip8Handle) -> Result<&'a Chip8Handle, Chip8Status> {
+    unsafe { handle.as_ref() }.ok_or(Chip8Status::NullPointer)
+}
+
+/// Views a C buffer as a slice, allowing a null pointer only for an empty buffer
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Result<&'a [u8], Chip8Status> {
+    match (data.is_null(), len) {
+        (true, 0) => Ok(&[]),
+        (true, _) => Err(Chip8Status::NullPointer),
+        (false, _) => Ok(unsafe { slice::from_raw_parts(data, len) }),
+    }
+}
+
+/// Creates an emulator whose CXNN results are reproducible for the given seed
+#[unsafe(no_mangle)]
+pub extern "C" fn chip8_create(seed: u64) -> *mut Chip8Handle {
+    Box::into_raw(Box::new(Chip8Handle {
+        chip8: Chip8::with_seed(seed),
+        seed,
+    }))
+}
+
+/// Frees an emulator. Passing null does nothing.
+///
+/// # Safety
+/// `handle` must come from `chip8_create` and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_destroy(handle: *mut Chip8Handle) {
+    if !handle.is_null() {
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
+
+/// Restarts the machine and loads a ROM at 0x200.
+///
+/// # Safety
+/// `handle` must be valid and `data` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_load_rom(
+    handle: *mut Chip8Handle,
+    data: *const u8,
+    len: usize,
+) -> Chip8Status {
+    guard(|| {
+        let handle = unsafe { handle_mut(handle) }?;
+        let rom = unsafe { bytes(data, len) }?;
+        if rom.len() > CHIP8_MAX_ROM_SIZE {
+            return Err(Chip8Status::RomTooLarge);
+        }
+        let quirks = handle.chip8.quirks;
+        handle.chip8 = Chip8::with_seed(handle.seed);
+        handle.chip8.quirks = quirks;
+        handle.chip8.load_rom(ROM::from_bytes(rom));
+        Ok(())
+    })
+}
+
+/// Runs a number of instructions without ticking the timers, stopping at the first error.
+///
+/// # Safety
+/// `handle` must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_run_cycles(handle: *mut Chip8Handle, cycles: u32) -> Chip8Status {
+    guard(|| {
+        let handle = unsafe { handle_mut(handle) }?;
+        for _ in 0..cycles {
+            handle.chip8.try_cycle()?;
+        }
+        Ok(())
+    })
+}
+
+/// Runs one 60Hz frame: a number of instructions followed by a timer tick.
+///
+/// # Safety
+/// `handle` must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_run_frame(
+    handle: *mut Chip8Handle,
+    cycles_per_frame: u32,
+) -> Chip8Status {
+    guard(|| {
+        let handle = unsafe { handle_mut(handle) }?;
+        handle.chip8.try_run_frame(cycles_per_frame as usize)?;
+        Ok(())
+    })
+}
+
+/// Presses or releases a key from 0x0 to 0xF. Only one key is held at a time.
+///
+/// # Safety
+/// `handle` must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_set_key(
+    handle: *mut Chip8Handle,
+    key: u8,
+    pressed: bool,
+) -> Chip8Status {
+    guard(|| {
+        let handle = unsafe { handle_mut(handle) }?;
+        if key > 0xF {
+            return Err(Chip8Status::InvalidArgument);
+        }
+        if pressed {
+            handle.chip8.set_input_key(Some(key));
+        } else if handle.chip8.curr_input_key == Some(key) {
+            handle.chip8.set_input_key(None);
+        }
+        Ok(())
+    })
+}
+
+/// Copies the display into `out`, one byte per pixel row by row: 1 for lit and 0 otherwise.
+///
+/// # Safety
+/// `handle` must be valid and `out` must point to `len` writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_get_framebuffer(
+    handle: *const Chip8Handle,
+    out: *mut u8,
+    len: usize,
+) -> Chip8Status {
+    guard(|| {
+        let handle = unsafe { handle_ref(handle) }?;
+        if len < CHIP8_WIDTH * CHIP8_HEIGHT {
+            return Err(Chip8Status::BufferTooSmall);
+        }
+        if out.is_null() {
+            return Err(Chip8Status::NullPointer);
+        }
+        let out = unsafe { slice::from_raw_parts_mut(out, len) };
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                out[y * WIDTH + x] = handle.chip8.display.get_pixel(y, x) as u8;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Reads the PC, I, V0 to VF and both timers.
+///
+/// # Safety
+/// `handle` must be valid and `out` must point to a writable `chip8_registers`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_get_registers(
+    handle: *const Chip8Handle,
+    out: *mut Chip8Registers,
+) -> Chip8Status {
+    guard(|| {
+        let chip8 = &unsafe { handle_ref(handle) }?.chip8;
+        let out = unsafe { out.as_mut() }.ok_or(Chip8Status::NullPointer)?;
+        *out = Chip8Registers {
+            pc: chip8.pc,
+            i: chip8.idx_reg as u16,
+            v: chip8.var_reg,
+            delay_timer: chip8.delay_timer,
+            sound_timer: chip8.sound_timer,
+        };
+        Ok(())
+    })
+}
+
+/// Overwrites the PC, I, V0 to VF and both timers, e.g. from a debugger.
+///
+/// # Safety
+/// `handle` must be valid and `registers` must point to a readable `chip8_registers`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_set_registers(
+    handle: *mut Chip8Handle,
+    registers: *const Chip8Registers,
+) -> Chip8Status {
+    guard(|| {
+        let chip8 = &mut unsafe { handle_mut(handle) }?.chip8;
+        let registers = unsafe { registers.as_ref() }.ok_or(Chip8Status::NullPointer)?;
+        chip8.pc = registers.pc;
+        chip8.idx_reg = registers.i.into();
+        chip8.var_reg = registers.v;
+        chip8.delay_timer = registers.delay_timer;
+        chip8.sound_timer = registers.sound_timer;
+        Ok(())
+    })
+}
+
+/// Writes a save state into `out`. The size needed is always stored in `written`, so calling
+/// with a null `out` and zero `capacity` queries it and returns `CHIP8_STATUS_BUFFER_TOO_SMALL`.
+///
+/// # Safety
+/// `handle` must be valid, `out` must point to `capacity` writable bytes and `written` must
+/// be writable or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_save_state(
+    handle: *const Chip8Handle,
+    out: *mut u8,
+    capacity: usize,
+    written: *mut usize,
+) -> Chip8Status {
+    guard(|| {
+        let state = unsafe { handle_ref(handle) }?.chip8.snapshot().to_bytes();
+        if let Some(written) = unsafe { written.as_mut() } {
+            *written = state.len();
+        }
+        if capacity < state.len() {
+            return Err(Chip8Status::BufferTooSmall);
+        }
+        if out.is_null() {
+            return Err(Chip8Status::NullPointer);
+        }
+        unsafe { slice::from_raw_parts_mut(out, state.len()) }.copy_from_slice(&state);
+        Ok(())
+    })
+}
+
+/// Restores a save state written by `chip8_save_state`.
+///
+/// # Safety
+/// `handle` must be valid and `data` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chip8_load_state(
+    handle: *mut Chip8Handle,
+    data: *const u8,
+    len: usize,
+) -> Chip8Status {
+    guard(|| {
+        let handle = unsafe { handle_mut(handle) }?;
+        let state = unsafe { bytes(data, len) }?;
+        let snapshot = Snapshot::from_bytes(state).map_err(|_| Chip8Status::InvalidState)?;
+        handle.chip8.restore(&snapshot);
+        Ok(())
+    })
+}
+
+/// A short English description of a status, valid for the life of the program
+#[unsafe(no_mangle)]
+pub extern "C" fn chip8_status_message(status: Chip8Status) -> *const c_char {
+    let message = match status {
+        Chip8Status::Ok => c"ok",
+        Chip8Status::NullPointer => c"null pointer",
+        Chip8Status::InvalidArgument => c"invalid argument",
+        Chip8Status::RomTooLarge => c"ROM too large",
+        Chip8Status::UnsupportedOpcode => c"unsupported opcode",
+        Chip8Status::StackUnderflow => c"returned with an empty call stack",
+        Chip8Status::PcOutOfBounds => c"PC out of bounds",
+        Chip8Status::MemoryOutOfBounds => c"memory access out of bounds",
+        Chip8Status::BufferTooSmall => c"buffer too small",
+        Chip8Status::InvalidState => c"invalid save state",
+        Chip8Status::Panic => c"internal error",
+        Chip8Status::StackOverflow => c"called with a full call stack",
+        Chip8Status::MachineCode => c"unsupported machine code",
+    };
+    message.as_ptr()
+}
@@ -0,0 +1,55 @@
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    const CRATE_DIR: &str = env!("CARGO_MANIFEST_DIR");
+
+    #[test]
+    fn header_is_up_to_date() {
+        let config = cbindgen::Config::from_file(Path::new(CRATE_DIR).join("cbindgen.toml"))
+            .expect("Unable to read cbindgen.toml");
+        let mut generated = Vec::new();
+        cbindgen::generate_with_config(CRATE_DIR, config)
+            .expect("Unable to generate header")
+            .write(&mut generated);
+
+        let committed = std::fs::read(Path::new(CRATE_DIR).join("include/chip8.h")).unwrap();
+        assert!(
+            generated == committed,
+            "include/chip8.h is stale, regenerate it with cbindgen"
+        );
+    }
+
+    /** The directory cargo put this crate's shared library in, next to the test binary */
+    fn library_dir() -> PathBuf {
+        let exe = std::env::current_exe().unwrap();
+        exe.parent().unwrap().to_path_buf()
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn c_smoke_test() {
+        let lib_dir = library_dir();
+        let binary = lib_dir.join("chip8_smoke");
+        let compiled = Command::new("cc")
+            .arg(Path::new(CRATE_DIR).join("tests/smoke.c"))
+            .arg("-I")
+            .arg(Path::new(CRATE_DIR).join("include"))
+            .arg("-L")
+            .arg(&lib_dir)
+            .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+            .args(["-lchip8_capi", "-Wall", "-Werror", "-o"])
+            .arg(&binary)
+            .status()
+            .expect("Unable to run cc");
+        assert!(compiled.success());
+
+        let output = Command::new(&binary).output().unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "ok");
+    }
+}
@@ -0,0 +1,69 @@
+/* Drives the C API end to end: run a ROM, poke registers, save and restore state. */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "chip8.h"
+
+#define CHECK(expr, expected)                                                   \
+  do {                                                                          \
+    chip8_status status = (expr);                                               \
+    if (status != (expected)) {                                                 \
+      fprintf(stderr, "%s:%d: %s returned \"%s\"\n", __FILE__, __LINE__, #expr, \
+              chip8_status_message(status));                                   \
+      return 1;                                                                 \
+    }                                                                           \
+  } while (0)
+
+/* I = font "0", draw it at (V0, V1), then loop forever */
+static const uint8_t ROM[] = {0xA0, 0x50, 0xD0, 0x15, 0x12, 0x04};
+
+int main(void) {
+  chip8_t *chip8 = chip8_create(42);
+  uint8_t framebuffer[CHIP8_WIDTH * CHIP8_HEIGHT];
+  chip8_registers registers;
+
+  CHECK(chip8_load_rom(chip8, ROM, sizeof ROM), CHIP8_STATUS_OK);
+  CHECK(chip8_run_frame(chip8, 10), CHIP8_STATUS_OK);
+  CHECK(chip8_get_framebuffer(chip8, framebuffer, sizeof framebuffer), CHIP8_STATUS_OK);
+  if (framebuffer[0] != 1 || framebuffer[4] != 0) {
+    fprintf(stderr, "glyph not drawn at the top left\n");
+    return 1;
+  }
+
+  size_t size = 0;
+  CHECK(chip8_save_state(chip8, NULL, 0, &size), CHIP8_STATUS_BUFFER_TOO_SMALL);
+  uint8_t *state = malloc(size);
+  CHECK(chip8_save_state(chip8, state, size, &size), CHIP8_STATUS_OK);
+
+  CHECK(chip8_get_registers(chip8, &registers), CHIP8_STATUS_OK);
+  registers.v[0] = 8;
+  registers.pc = 0x202;
+  CHECK(chip8_set_registers(chip8, &registers), CHIP8_STATUS_OK);
+  CHECK(chip8_run_cycles(chip8, 1), CHIP8_STATUS_OK);
+  CHECK(chip8_get_framebuffer(chip8, framebuffer, sizeof framebuffer), CHIP8_STATUS_OK);
+  if (framebuffer[8] != 1) {
+    fprintf(stderr, "glyph not drawn at x = 8\n");
+    return 1;
+  }
+
+  CHECK(chip8_load_state(chip8, state, size), CHIP8_STATUS_OK);
+  CHECK(chip8_get_framebuffer(chip8, framebuffer, sizeof framebuffer), CHIP8_STATUS_OK);
+  if (framebuffer[8] != 0) {
+    fprintf(stderr, "state not restored\n");
+    return 1;
+  }
+  free(state);
+
+  CHECK(chip8_set_key(chip8, 0x10, true), CHIP8_STATUS_INVALID_ARGUMENT);
+  CHECK(chip8_load_state(chip8, ROM, sizeof ROM), CHIP8_STATUS_INVALID_STATE);
+  CHECK(chip8_run_frame(NULL, 10), CHIP8_STATUS_NULL_POINTER);
+
+  static const uint8_t BAD[] = {0xFF, 0xFF};
+  CHECK(chip8_load_rom(chip8, BAD, sizeof BAD), CHIP8_STATUS_OK);
+  CHECK(chip8_run_frame(chip8, 10), CHIP8_STATUS_UNSUPPORTED_OPCODE);
+
+  chip8_destroy(chip8);
+  puts("ok");
+  return 0;
+}
@@ -0,0 +1,511 @@
+pub mod ffi;
+
+use chip8::error::Chip8Error;
+use chip8::palette::Palette;
+use chip8::quirks::Quirks;
+use chip8::rom::ROM;
+use chip8::state::Snapshot;
+use chip8::timing::Timing;
+use chip8::{Chip8, HEIGHT, WIDTH};
+use ffi::*;
+use std::ffi::{CStr, c_char, c_uint, c_void};
+use std::ptr;
+use std::slice;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+pub const SAMPLE_RATE: u32 = 44100;
+/** Stereo sample frames produced by every `retro_run` */
+pub const SAMPLES_PER_FRAME: usize = SAMPLE_RATE as usize / 60;
+const BEEP_HZ: u32 = 440;
+const BEEP_VOLUME: i16 = 0x1000;
+/** Save states are padded to a fixed size, leaving room for this many return addresses */
+const MAX_SAVED_STACK: usize = 64;
+
+/** Chip8 keys for each RetroPad button. The d-pad and A cover the usual 2/4/6/8 and 5 layout */
+pub const KEYMAP: [(c_uint, u8); 16] = [
+    (RETRO_DEVICE_ID_JOYPAD_UP, 0x2),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, 0x8),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, 0x4),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, 0x6),
+    (RETRO_DEVICE_ID_JOYPAD_A, 0x5),
+    (RETRO_DEVICE_ID_JOYPAD_B, 0x0),
+    (RETRO_DEVICE_ID_JOYPAD_X, 0x1),
+    (RETRO_DEVICE_ID_JOYPAD_Y, 0x3),
+    (RETRO_DEVICE_ID_JOYPAD_L, 0x7),
+    (RETRO_DEVICE_ID_JOYPAD_R, 0x9),
+    (RETRO_DEVICE_ID_JOYPAD_L2, 0xA),
+    (RETRO_DEVICE_ID_JOYPAD_R2, 0xB),
+    (RETRO_DEVICE_ID_JOYPAD_L3, 0xC),
+    (RETRO_DEVICE_ID_JOYPAD_R3, 0xD),
+    (RETRO_DEVICE_ID_JOYPAD_SELECT, 0xE),
+    (RETRO_DEVICE_ID_JOYPAD_START, 0xF),
+];
+
+const QUIRKS_KEY: &CStr = c"chip8_quirks";
+const SPEED_KEY: &CStr = c"chip8_speed";
+const TIMING_KEY: &CStr = c"chip8_timing";
+
+struct Variables([Variable; 4]);
+/** Only holds pointers to static strings */
+unsafe impl Sync for Variables {}
+
+static VARIABLES: Variables = Variables([
+    Variable {
+        key: QUIRKS_KEY.as_ptr(),
+        value: c"Quirks; default|vip|schip|xochip".as_ptr(),
+    },
+    Variable {
+        key: SPEED_KEY.as_ptr(),
+        value: c"Instructions per frame; 10|15|20|30|50|100|200|500|1000|1|5".as_ptr(),
+    },
+    Variable {
+        key: TIMING_KEY.as_ptr(),
+        value: c"Timing; fast|vip".as_ptr(),
+    },
+    Variable {
+        key: ptr::null(),
+        value: ptr::null(),
+    },
+]);
+
+/** Callbacks handed over by the frontend */
+#[derive(Clone, Copy)]
+struct Frontend {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+static FRONTEND: Mutex<Frontend> = Mutex::new(Frontend {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+/** The loaded game */
+struct Core {
+    chip8: Chip8,
+    rom: Vec<u8>,
+    cycles_per_frame: usize,
+    /** Set after an error, after which the machine stays frozen until reset */
+    halted: bool,
+    video: Vec<u32>,
+    audio: Vec<i16>,
+    /** Position in the beep's square wave, carried across frames so it doesn't click */
+    phase: u32,
+}
+impl Core {
+    fn new(rom: &[u8]) -> Core {
+        let mut core = Core {
+            chip8: Chip8::new(),
+            rom: rom.to_vec(),
+            cycles_per_frame: 10,
+            halted: false,
+            video: vec![0; WIDTH * HEIGHT],
+            audio: vec![0; SAMPLES_PER_FRAME * 2],
+            phase: 0,
+        };
+        core.reset();
+        core
+    }
+
+    /** Restarts the ROM, keeping the options */
+    fn reset(&mut self) {
+        let (quirks, timing) = (self.chip8.quirks, self.chip8.timing);
+        self.chip8 = Chip8::new();
+        self.chip8.quirks = quirks;
+        self.chip8.timing = timing;
+        self.chip8.load_rom(ROM::from_bytes(&self.rom));
+        self.halted = false;
+    }
+
+    fn apply_options(&mut self, environment: EnvironmentFn) {
+        if let Some(quirks) = unsafe { variable(environment, QUIRKS_KEY) }
+            .as_deref()
+            .and_then(Quirks::preset)
+        {
+            self.chip8.quirks = quirks;
+        }
+        if let Some(speed) =
+            unsafe { variable(environment, SPEED_KEY) }.and_then(|speed| speed.parse().ok())
+        {
+            self.cycles_per_frame = speed;
+        }
+        if let Some(timing) = unsafe { variable(environment, TIMING_KEY) }
+            .as_deref()
+            .and_then(Timing::from_name)
+        {
+            self.chip8.timing = timing;
+        }
+    }
+
+    fn run_frame(&mut self) {
+        if self.halted {
+            return;
+        }
+        if let Err(error) = self.chip8.try_run_frame(self.cycles_per_frame) {
+            self.halt(error);
+        }
+    }
+
+    fn halt(&mut self, error: Chip8Error) {
+        eprintln!("chip8: {error} at {:#05x}, halting", self.chip8.pc);
+        self.halted = true;
+    }
+
+    fn render_video(&mut self) {
+        let colors = Palette::default()
+            .colors
+            .map(|[r, g, b]| u32::from_be_bytes([0, r, g, b]));
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                self.video[y * WIDTH + x] = colors[self.chip8.display.get_pixel(y, x) as usize];
+            }
+        }
+    }
+
+    /** Fills one frame of a square wave while the sound timer runs, silence otherwise */
+    fn render_audio(&mut self) {
+        let beeping = self.chip8.sound_timer > 0 && !self.halted;
+        for frame in self.audio.chunks_exact_mut(2) {
+            let sample = match (beeping, self.phase * BEEP_HZ * 2 / SAMPLE_RATE % 2) {
+                (false, _) => 0,
+                (true, 0) => BEEP_VOLUME,
+                (true, _) => -BEEP_VOLUME,
+            };
+            frame.fill(sample);
+            self.phase = if beeping {
+                (self.phase + 1) % SAMPLE_RATE
+            } else {
+                0
+            };
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn frontend() -> Frontend {
+    *lock(&FRONTEND)
+}
+
+/** Fixed size of a save state: a length prefix, the snapshot and padding for the call stack */
+fn serialize_size() -> usize {
+    4 + Chip8::with_seed(0).snapshot().to_bytes().len() + MAX_SAVED_STACK * 2
+}
+
+/** Reads the current value of a core option */
+unsafe fn variable(environment: EnvironmentFn, key: &CStr) -> Option<String> {
+    let mut variable = Variable {
+        key: key.as_ptr(),
+        value: ptr::null(),
+    };
+    let found = unsafe {
+        environment(
+            RETRO_ENVIRONMENT_GET_VARIABLE,
+            &mut variable as *mut Variable as *mut c_void,
+        )
+    };
+    if !found || variable.value.is_null() {
+        return None;
+    }
+    Some(
+        unsafe { CStr::from_ptr(variable.value) }
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+/** Declares the core options. Called before `retro_init` */
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_environment(environment: Option<EnvironmentFn>) {
+    lock(&FRONTEND).environment = environment;
+    if let Some(environment) = environment {
+        unsafe {
+            environment(
+                RETRO_ENVIRONMENT_SET_VARIABLES,
+                VARIABLES.0.as_ptr() as *mut c_void,
+            )
+        };
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_video_refresh(video_refresh: Option<VideoRefreshFn>) {
+    lock(&FRONTEND).video_refresh = video_refresh;
+}
+
+/** Unused: audio is always sent in batches */
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample(_audio_sample: Option<AudioSampleFn>) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample_batch(audio_sample_batch: Option<AudioSampleBatchFn>) {
+    lock(&FRONTEND).audio_sample_batch = audio_sample_batch;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_poll(input_poll: Option<InputPollFn>) {
+    lock(&FRONTEND).input_poll = input_poll;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_state(input_state: Option<InputStateFn>) {
+    lock(&FRONTEND).input_state = input_state;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_init() {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_deinit() {
+    *lock(&CORE) = None;
+}
+
+/**
+ * # Safety
+ * `info` must point to a writable `retro_system_info`.
+ */
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    unsafe {
+        info.write(SystemInfo {
+            library_name: c"RustyChip8".as_ptr(),
+            library_version: c"0.1.0".as_ptr(),
+            valid_extensions: c"ch8|c8".as_ptr(),
+            need_fullpath: false,
+            block_extract: false,
+        })
+    };
+}
+
+/**
+ * # Safety
+ * `info` must point to a writable `retro_system_av_info`.
+ */
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    unsafe {
+        info.write(SystemAvInfo {
+            geometry: GameGeometry {
+                base_width: WIDTH as c_uint,
+                base_height: HEIGHT as c_uint,
+                max_width: WIDTH as c_uint,
+                max_height: HEIGHT as c_uint,
+                aspect_ratio: WIDTH as f32 / HEIGHT as f32,
+            },
+            timing: SystemTiming {
+                fps: 60.0,
+                sample_rate: SAMPLE_RATE as f64,
+            },
+        })
+    };
+}
+
+/** Every port is a RetroPad */
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = lock(&CORE).as_mut() {
+        core.reset();
+    }
+}
+
+/** Runs one 60Hz frame, then hands over its video and audio */
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_run() {
+    let frontend = frontend();
+    let mut core = lock(&CORE);
+    let Some(core) = core.as_mut() else {
+        return;
+    };
+
+    if let Some(environment) = frontend.environment {
+        let mut updated = false;
+        let asked = unsafe {
+            environment(
+                RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE,
+                &mut updated as *mut bool as *mut c_void,
+            )
+        };
+        if asked && updated {
+            core.apply_options(environment);
+        }
+    }
+
+    if let Some(input_poll) = frontend.input_poll {
+        unsafe { input_poll() };
+    }
+    if let Some(input_state) = frontend.input_state {
+        let key = KEYMAP
+            .iter()
+            .find(|(id, _)| unsafe { input_state(0, RETRO_DEVICE_JOYPAD, 0, *id) } != 0)
+            .map(|&(_, key)| key);
+        core.chip8.set_input_key(key);
+    }
+
+    core.run_frame();
+
+    if let Some(video_refresh) = frontend.video_refresh {
+        core.render_video();
+        unsafe {
+            video_refresh(
+                core.video.as_ptr() as *const c_void,
+                WIDTH as c_uint,
+                HEIGHT as c_uint,
+                WIDTH * 4,
+            )
+        };
+    }
+    if let Some(audio_sample_batch) = frontend.audio_sample_batch {
+        core.render_audio();
+        unsafe { audio_sample_batch(core.audio.as_ptr(), SAMPLES_PER_FRAME) };
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_serialize_size() -> usize {
+    serialize_size()
+}
+
+/**
+ * # Safety
+ * `data` must point to `size` writable bytes.
+ */
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let core = lock(&CORE);
+    let Some(core) = core.as_ref() else {
+        return false;
+    };
+    let state = core.chip8.snapshot().to_bytes();
+    if data.is_null() || size < serialize_size() || state.len() + 4 > size {
+        return false;
+    }
+    let out = unsafe { slice::from_raw_parts_mut(data as *mut u8, size) };
+    out.fill(0);
+    out[..4].copy_from_slice(&(state.len() as u32).to_le_bytes());
+    out[4..4 + state.len()].copy_from_slice(&state);
+    true
+}
+
+/**
+ * # Safety
+ * `data` must point to `size` readable bytes.
+ */
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let mut core = lock(&CORE);
+    let Some(core) = core.as_mut() else {
+        return false;
+    };
+    if data.is_null() || size < 4 {
+        return false;
+    }
+    let bytes = unsafe { slice::from_raw_parts(data as *const u8, size) };
+    let len = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    let Some(state) = bytes[4..].get(..len) else {
+        return false;
+    };
+    match Snapshot::from_bytes(state) {
+        Ok(snapshot) => {
+            core.chip8.restore(&snapshot);
+            core.halted = false;
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/**
+ * Loads a ROM from memory, applying the current core options.
+ *
+ * # Safety
+ * `game` must be null or point to a valid `retro_game_info` whose `data` holds `size` bytes.
+ */
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    let Some(game) = (unsafe { game.as_ref() }) else {
+        return false;
+    };
+    if game.data.is_null() || game.size > 4096 - 0x200 {
+        return false;
+    }
+    let frontend = frontend();
+    if let Some(environment) = frontend.environment {
+        let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+        let accepted = unsafe {
+            environment(
+                RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+                &mut format as *mut _ as *mut c_void,
+            )
+        };
+        if !accepted {
+            return false;
+        }
+    }
+
+    let rom = unsafe { slice::from_raw_parts(game.data as *const u8, game.size) };
+    let mut core = Core::new(rom);
+    if let Some(environment) = frontend.environment {
+        core.apply_options(environment);
+    }
+    *lock(&CORE) = Some(core);
+    true
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const GameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_unload_game() {
+    *lock(&CORE) = None;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+/** Exposes RAM to the frontend, e.g. for cheats and achievements */
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_data(id: c_uint) -> *mut c_void {
+    match lock(&CORE).as_mut() {
+        Some(core) if id == RETRO_MEMORY_SYSTEM_RAM => core.chip8.ram.as_mut_ptr() as *mut c_void,
+        _ => ptr::null_mut(),
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_size(id: c_uint) -> usize {
+    match lock(&CORE).as_ref() {
+        Some(core) if id == RETRO_MEMORY_SYSTEM_RAM => core.chip8.ram.len(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,254 @@
+/** A minimal libretro frontend driving the core through its C entry points */
+mod tests {
+    use chip8_libretro::ffi::*;
+    use chip8_libretro::*;
+    use std::ffi::{CStr, CString, c_uint, c_void};
+    use std::sync::{Mutex, MutexGuard, PoisonError};
+
+    const IBM_LOGO: &[u8] = include_bytes!("../../ROMs/IBM Logo.ch8");
+
+    /** What the frontend has been told or will answer */
+    struct Frontend {
+        declared: Vec<(String, String)>,
+        options: Vec<(CString, CString)>,
+        options_updated: bool,
+        pixel_format: Option<i32>,
+        video: Vec<u32>,
+        audio: Vec<i16>,
+        frames: usize,
+        held: Option<c_uint>,
+    }
+
+    static FRONTEND: Mutex<Frontend> = Mutex::new(Frontend {
+        declared: Vec::new(),
+        options: Vec::new(),
+        options_updated: false,
+        pixel_format: None,
+        video: Vec::new(),
+        audio: Vec::new(),
+        frames: 0,
+        held: None,
+    });
+    /** The core is a singleton, so tests take turns */
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn frontend() -> MutexGuard<'static, Frontend> {
+        FRONTEND.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    unsafe extern "C" fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+        let mut frontend = frontend();
+        match cmd {
+            RETRO_ENVIRONMENT_SET_VARIABLES => {
+                let mut variable = data as *const Variable;
+                while let Some(declared) = unsafe { variable.as_ref() } {
+                    if declared.key.is_null() {
+                        break;
+                    }
+                    let text = |ptr| unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_owned();
+                    frontend
+                        .declared
+                        .push((text(declared.key), text(declared.value)));
+                    variable = unsafe { variable.add(1) };
+                }
+                true
+            }
+            RETRO_ENVIRONMENT_GET_VARIABLE => {
+                let variable = unsafe { &mut *(data as *mut Variable) };
+                let key = unsafe { CStr::from_ptr(variable.key) };
+                match frontend.options.iter().find(|(k, _)| k.as_c_str() == key) {
+                    Some((_, value)) => {
+                        variable.value = value.as_ptr();
+                        true
+                    }
+                    None => false,
+                }
+            }
+            RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE => {
+                unsafe { *(data as *mut bool) = std::mem::take(&mut frontend.options_updated) };
+                true
+            }
+            RETRO_ENVIRONMENT_SET_PIXEL_FORMAT => {
+                frontend.pixel_format = Some(unsafe { *(data as *const i32) });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    unsafe extern "C" fn video_refresh(
+        data: *const c_void,
+        width: c_uint,
+        height: c_uint,
+        pitch: usize,
+    ) {
+        assert_eq!(pitch, width as usize * 4);
+        let pixels =
+            unsafe { std::slice::from_raw_parts(data as *const u32, (width * height) as usize) };
+        let mut frontend = frontend();
+        frontend.video = pixels.to_vec();
+        frontend.frames += 1;
+    }
+
+    unsafe extern "C" fn audio_sample_batch(data: *const i16, frames: usize) -> usize {
+        let samples = unsafe { std::slice::from_raw_parts(data, frames * 2) };
+        frontend().audio.extend_from_slice(samples);
+        frames
+    }
+
+    unsafe extern "C" fn input_poll() {}
+
+    unsafe extern "C" fn input_state(
+        port: c_uint,
+        device: c_uint,
+        _index: c_uint,
+        id: c_uint,
+    ) -> i16 {
+        (port == 0 && device == RETRO_DEVICE_JOYPAD && frontend().held == Some(id)) as i16
+    }
+
+    /** Resets the frontend, hands the callbacks over and loads a ROM */
+    fn start(rom: &[u8], options: &[(&str, &str)]) -> MutexGuard<'static, ()> {
+        let serial = SERIAL.lock().unwrap_or_else(PoisonError::into_inner);
+        {
+            let mut frontend = frontend();
+            frontend.declared.clear();
+            frontend.options = options
+                .iter()
+                .map(|(k, v)| (CString::new(*k).unwrap(), CString::new(*v).unwrap()))
+                .collect();
+            frontend.options_updated = false;
+            frontend.pixel_format = None;
+            frontend.audio.clear();
+            frontend.frames = 0;
+            frontend.held = None;
+        }
+        retro_set_environment(Some(environment));
+        retro_set_video_refresh(Some(video_refresh));
+        retro_set_audio_sample_batch(Some(audio_sample_batch));
+        retro_set_input_poll(Some(input_poll));
+        retro_set_input_state(Some(input_state));
+        retro_init();
+        let game = GameInfo {
+            path: std::ptr::null(),
+            data: rom.as_ptr() as *const c_void,
+            size: rom.len(),
+            meta: std::ptr::null(),
+        };
+        assert!(unsafe { retro_load_game(&game) });
+        serial
+    }
+
+    fn lit_pixels() -> usize {
+        frontend()
+            .video
+            .iter()
+            .filter(|&&pixel| pixel == 0xFFFFFF)
+            .count()
+    }
+
+    #[test]
+    fn declares_options_and_geometry() {
+        let _serial = start(IBM_LOGO, &[]);
+        let keys: Vec<String> = frontend().declared.iter().map(|(k, _)| k.clone()).collect();
+        assert_eq!(keys, ["chip8_quirks", "chip8_speed", "chip8_timing"]);
+        assert_eq!(frontend().pixel_format, Some(RETRO_PIXEL_FORMAT_XRGB8888));
+
+        let mut info = std::mem::MaybeUninit::<SystemAvInfo>::uninit();
+        let info = unsafe {
+            retro_get_system_av_info(info.as_mut_ptr());
+            info.assume_init()
+        };
+        assert_eq!(
+            (info.geometry.base_width, info.geometry.base_height),
+            (64, 32)
+        );
+        assert_eq!(info.timing.fps, 60.0);
+        assert_eq!(retro_get_memory_size(RETRO_MEMORY_SYSTEM_RAM), 4096);
+        retro_deinit();
+    }
+
+    #[test]
+    fn runs_frames_with_video_and_silence() {
+        let _serial = start(IBM_LOGO, &[]);
+        for _ in 0..30 {
+            retro_run();
+        }
+        assert_eq!(frontend().frames, 30);
+        assert!(lit_pixels() > 0);
+        assert_eq!(frontend().audio.len(), 30 * SAMPLES_PER_FRAME * 2);
+        assert!(frontend().audio.iter().all(|&sample| sample == 0));
+        retro_deinit();
+    }
+
+    #[test]
+    fn start_button_presses_key_f_and_beeps() {
+        // wait for a key, then start the sound timer with the key's value
+        let _serial = start(&[0xF0, 0x0A, 0xF0, 0x18, 0x12, 0x04], &[]);
+        retro_run();
+        assert!(frontend().audio.iter().all(|&sample| sample == 0));
+
+        frontend().held = Some(RETRO_DEVICE_ID_JOYPAD_START);
+        retro_run();
+        frontend().held = None;
+        frontend().audio.clear();
+        for _ in 0..14 {
+            retro_run();
+        }
+        let beep = frontend().audio.clone();
+        assert!(beep.iter().any(|&sample| sample > 0));
+        assert!(beep.iter().any(|&sample| sample < 0));
+
+        frontend().audio.clear();
+        retro_run();
+        assert!(frontend().audio.iter().all(|&sample| sample == 0));
+        retro_deinit();
+    }
+
+    #[test]
+    fn save_states_restore_the_machine() {
+        // one instruction per frame draws the logo a piece at a time
+        let _serial = start(IBM_LOGO, &[("chip8_speed", "1")]);
+        for _ in 0..8 {
+            retro_run();
+        }
+        let mut state = vec![0xAA; retro_serialize_size()];
+        assert!(unsafe { retro_serialize(state.as_mut_ptr() as *mut c_void, state.len()) });
+        retro_run();
+        let next_frame = frontend().video.clone();
+        for _ in 0..20 {
+            retro_run();
+        }
+        assert_ne!(frontend().video, next_frame);
+
+        assert!(unsafe { retro_unserialize(state.as_ptr() as *const c_void, state.len()) });
+        retro_run();
+        assert_eq!(frontend().video, next_frame);
+        assert!(!unsafe { retro_unserialize(state.as_ptr() as *const c_void, 3) });
+        retro_deinit();
+    }
+
+    #[test]
+    fn option_updates_apply_without_restarting() {
+        let _serial = start(IBM_LOGO, &[("chip8_speed", "1")]);
+        retro_run();
+        let slow_pc = pc();
+        {
+            let mut frontend = frontend();
+            frontend.options = vec![(c"chip8_speed".to_owned(), c"100".to_owned())];
+            frontend.options_updated = true;
+        }
+        retro_run();
+        assert!(pc() > slow_pc + 2);
+        retro_deinit();
+    }
+
+    /** Reads the PC back out of a save state */
+    fn pc() -> u16 {
+        let mut state = vec![0; retro_serialize_size()];
+        assert!(unsafe { retro_serialize(state.as_mut_ptr() as *mut c_void, state.len()) });
+        let len = u32::from_le_bytes(state[..4].try_into().unwrap()) as usize;
+        let snapshot = chip8::state::Snapshot::from_bytes(&state[4..4 + len]).unwrap();
+        snapshot.pc
+    }
+}
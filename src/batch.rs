@@ -0,0 +1,160 @@
+use crate::Chip8;
+use crate::display::HEIGHT;
+use crate::error::Chip8Error;
+use crate::quirks::Quirks;
+use crate::rom::ROM;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+/** Results of one batch step, stored struct-of-arrays with one entry per instance */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchFrame {
+    pub framebuffers: Vec<[u64; HEIGHT]>,
+    pub pcs: Vec<u16>,
+}
+
+/** Running totals per instance, stored struct-of-arrays */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchStats {
+    /** Frames each instance has run */
+    pub frames: Vec<u64>,
+    /** Frames in which each instance executed 00E0 or DXYN */
+    pub draw_frames: Vec<u64>,
+    /** The error that stopped each instance, e.g. an unsupported opcode. Stopped instances don't run again */
+    pub errors: Vec<Option<Chip8Error>>,
+}
+
+/** Steps many independent Chip8 machines in parallel on a thread pool */
+pub struct Batch {
+    machines: Vec<Chip8>,
+    stats: BatchStats,
+    pool: Option<ThreadPool>,
+}
+impl Batch {
+    pub fn new(machines: Vec<Chip8>) -> Batch {
+        let count = machines.len();
+        Batch {
+            machines,
+            stats: BatchStats {
+                frames: vec![0; count],
+                draw_frames: vec![0; count],
+                errors: vec![None; count],
+            },
+            pool: None,
+        }
+    }
+
+    /**
+     * Creates `count` machines running the same ROM, seeded with `seed`, `seed + 1`, ... ROMs
+     * too big for memory are cut short
+     */
+    pub fn from_rom(rom: &[u8], count: usize, seed: u64, quirks: Quirks) -> Batch {
+        let machines = (0..count as u64)
+            .map(|i| {
+                let mut chip8 = Chip8::with_seed(seed.wrapping_add(i));
+                chip8.quirks = quirks;
+                chip8.load_rom(ROM::from_bytes(rom));
+                chip8
+            })
+            .collect();
+        Batch::new(machines)
+    }
+
+    /** Runs on a dedicated pool with the given number of threads instead of rayon's global pool */
+    pub fn with_threads(mut self, threads: usize) -> Batch {
+        self.pool = Some(
+            ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("Unable to build batch thread pool"),
+        );
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.machines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.machines.is_empty()
+    }
+
+    pub fn machines(&self) -> &[Chip8] {
+        &self.machines
+    }
+
+    pub fn machine_mut(&mut self, index: usize) -> &mut Chip8 {
+        &mut self.machines[index]
+    }
+
+    pub fn stats(&self) -> &BatchStats {
+        &self.stats
+    }
+
+    /** Runs one frame on every machine, each with its own input */
+    pub fn step(&mut self, inputs: &[Option<u8>], cycles_per_frame: usize) -> BatchFrame {
+        assert_eq!(
+            inputs.len(),
+            self.machines.len(),
+            "Batch step needs one input per machine"
+        );
+
+        let (machines, stats) = (&mut self.machines, &mut self.stats);
+        match &self.pool {
+            Some(pool) => pool.install(|| step_all(machines, stats, inputs, cycles_per_frame)),
+            None => step_all(machines, stats, inputs, cycles_per_frame),
+        }
+        self.frame()
+    }
+
+    /** Runs a number of frames with the same input held on every machine */
+    pub fn run(&mut self, frames: usize, cycles_per_frame: usize, input: Option<u8>) -> BatchFrame {
+        let inputs = vec![input; self.machines.len()];
+        for _ in 0..frames {
+            self.step(&inputs, cycles_per_frame);
+        }
+        self.frame()
+    }
+
+    /** Collects the current framebuffer and PC of every machine */
+    pub fn frame(&self) -> BatchFrame {
+        BatchFrame {
+            framebuffers: self
+                .machines
+                .iter()
+                .map(|chip8| chip8.display.as_bitmask_rows())
+                .collect(),
+            pcs: self.machines.iter().map(|chip8| chip8.pc).collect(),
+        }
+    }
+}
+
+/** Runs one frame on every machine that hasn't hit an error, in parallel on the current rayon pool */
+fn step_all(
+    machines: &mut [Chip8],
+    stats: &mut BatchStats,
+    inputs: &[Option<u8>],
+    cycles_per_frame: usize,
+) {
+    machines
+        .par_iter_mut()
+        .zip(stats.frames.par_iter_mut())
+        .zip(stats.draw_frames.par_iter_mut())
+        .zip(stats.errors.par_iter_mut())
+        .zip(inputs.par_iter())
+        .for_each(|((((chip8, frames), draw_frames), error), &input)| {
+            if error.is_some() {
+                return;
+            }
+            chip8.set_input_key(input);
+            if let Err(err) = chip8.try_run_frame(cycles_per_frame) {
+                *error = Some(err);
+                return;
+            }
+            *frames += 1;
+            if chip8.draw_flag {
+                *draw_frames += 1;
+                chip8.draw_flag = false;
+            }
+        });
+}
@@ -0,0 +1,196 @@
+use chip8::capture::{Capture, Recorder, RecordingFormat};
+use chip8::font::{Font, FontSet};
+use chip8::movie::Movie;
+use chip8::palette::{Palette, PaletteDatabase, Theme};
+use chip8::platform::Platform;
+use chip8::quirks::Quirks;
+use chip8::rng::Generator;
+use chip8::rom::ROM;
+use chip8::timing::Timing;
+use chip8::{Chip8, FONT_START};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "Usage: chip8-headless <rom> [--frames N] [--cycles N] [--scale N] \
+[--seed N] [--rng xorshift|vip] [--quirks NAME] [--timing fast|vip] [--platform NAME] [--font NAME | --font-file PATH] [--font-addr HEX] [--theme NAME | --palette HEX,HEX | --palette-db PATH] [--screenshot OUT.png] \
+[--record OUT.gif|OUT.png] [--play-movie MOVIE] [--ascii]";
+
+/** Runs a ROM without a window for a fixed number of 60Hz frames */
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut rom_path: Option<String> = None;
+    let mut frames: Option<usize> = None;
+    let mut cycles_per_frame: usize = 10;
+    let mut scale: usize = 10;
+    let mut palette: Option<Palette> = None;
+    let mut palette_db: Option<PaletteDatabase> = None;
+    let mut seed: Option<u64> = None;
+    let mut generator = Generator::default();
+    let mut quirks = Quirks::default();
+    let mut timing = Timing::default();
+    let mut platform = Platform::default();
+    let mut font = Font::default();
+    let mut font_addr = FONT_START as u16;
+    let mut movie_path: Option<PathBuf> = None;
+    let mut print_ascii = false;
+    let mut screenshot_path: Option<PathBuf> = None;
+    let mut record_path: Option<PathBuf> = None;
+
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--frames" => frames = Some(parse_number(args_iter.next())),
+            "--cycles" => cycles_per_frame = parse_number(args_iter.next()),
+            "--scale" => scale = parse_number(args_iter.next()),
+            "--theme" => {
+                let name = args_iter.next().unwrap_or_else(|| exit_with_usage());
+                palette = Some(
+                    Theme::from_name(name)
+                        .unwrap_or_else(|| exit_with_error(&format!("Unknown theme: {name}")))
+                        .palette(),
+                );
+            }
+            "--seed" => seed = Some(parse_number(args_iter.next()) as u64),
+            "--rng" => {
+                let name = args_iter.next().unwrap_or_else(|| exit_with_usage());
+                generator = Generator::from_name(name).unwrap_or_else(|| {
+                    exit_with_error(&format!("Unknown random generator: {name}"))
+                });
+            }
+            "--palette" => {
+                let hex = args_iter.next().unwrap_or_else(|| exit_with_usage());
+                palette = Some(Palette::from_hex(hex).unwrap_or_else(|err| exit_with_error(&err)));
+            }
+            "--palette-db" => {
+                let path = args_iter.next().unwrap_or_else(|| exit_with_usage());
+                palette_db = Some(
+                    PaletteDatabase::load(Path::new(path)).unwrap_or_else(|err| {
+                        exit_with_error(&format!("Unable to load palettes: {err}"))
+                    }),
+                );
+            }
+            "--quirks" => {
+                let name = args_iter.next().unwrap_or_else(|| exit_with_usage());
+                quirks = Quirks::preset(name)
+                    .unwrap_or_else(|| exit_with_error(&format!("Unknown quirks: {name}")));
+            }
+            "--timing" => {
+                let name = args_iter.next().unwrap_or_else(|| exit_with_usage());
+                timing = Timing::from_name(name)
+                    .unwrap_or_else(|| exit_with_error(&format!("Unknown timing: {name}")));
+            }
+            "--platform" => {
+                let name = args_iter.next().unwrap_or_else(|| exit_with_usage());
+                platform = Platform::from_name(name)
+                    .unwrap_or_else(|| exit_with_error(&format!("Unknown platform: {name}")));
+            }
+            "--font" => {
+                let name = args_iter.next().unwrap_or_else(|| exit_with_usage());
+                font = FontSet::from_name(name)
+                    .unwrap_or_else(|| exit_with_error(&format!("Unknown font set: {name}")))
+                    .font();
+            }
+            "--font-file" => {
+                let path = args_iter.next().unwrap_or_else(|| exit_with_usage());
+                font = Font::load(Path::new(path))
+                    .unwrap_or_else(|err| exit_with_error(&format!("Unable to load font: {err}")));
+            }
+            "--font-addr" => {
+                let hex = args_iter.next().unwrap_or_else(|| exit_with_usage());
+                font_addr = u16::from_str_radix(hex.trim_start_matches("0x"), 16)
+                    .unwrap_or_else(|_| exit_with_error(&format!("Invalid address: {hex}")));
+            }
+            "--play-movie" => movie_path = args_iter.next().map(PathBuf::from),
+            "--ascii" => print_ascii = true,
+            "--screenshot" => screenshot_path = args_iter.next().map(PathBuf::from),
+            "--record" => record_path = args_iter.next().map(PathBuf::from),
+            _ => rom_path = Some(arg.clone()),
+        }
+    }
+    let rom_path = rom_path.unwrap_or_else(|| exit_with_usage());
+    let rom = ROM::new(&rom_path);
+    // an explicit theme or palette wins over the database
+    let palette = palette
+        .or_else(|| palette_db?.lookup(&rom.sha1()))
+        .unwrap_or_default();
+
+    let capture = Capture {
+        rows: platform.display_height(),
+        ..Capture::new(palette, scale)
+    };
+    let mut recorder = record_path.as_ref().map(|path| {
+        let format = RecordingFormat::from_path(path)
+            .unwrap_or_else(|| exit_with_error("Recordings must end in .gif or .png"));
+        Recorder::new(capture, format)
+    });
+
+    let mut movie = movie_path.map(|path| {
+        Movie::load(&path)
+            .unwrap_or_else(|err| exit_with_error(&format!("Unable to load movie: {err}")))
+    });
+    let mut chip8 = match (&movie, seed) {
+        (Some(movie), _) => {
+            if movie.header.rom_sha1 != rom.sha1() {
+                eprintln!("Warning: movie was recorded with a different ROM");
+            }
+            movie
+                .create_machine()
+                .unwrap_or_else(|err| exit_with_error(&err))
+        }
+        (None, Some(seed)) => Chip8::with_seed(seed),
+        (None, None) => Chip8::new(),
+    };
+    if movie.is_none() {
+        chip8.set_generator(generator);
+        chip8.quirks = quirks;
+        chip8.timing = timing;
+        chip8.set_platform(platform);
+        chip8
+            .load_font(&font, font_addr)
+            .unwrap_or_else(|err| exit_with_error(&err));
+    }
+    chip8.load_rom(rom);
+
+    let frames = frames.unwrap_or_else(|| movie.as_ref().map(Movie::len).unwrap_or(600));
+    for _ in 0..frames {
+        match movie.as_mut() {
+            Some(movie) => movie.run_frame(&mut chip8, None),
+            None => chip8.run_frame(cycles_per_frame),
+        }
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.push_frame(&chip8.display);
+        }
+    }
+
+    if let Some(path) = screenshot_path {
+        capture
+            .save_png(&chip8.display, &path)
+            .unwrap_or_else(|err| exit_with_error(&format!("Unable to save screenshot: {err}")));
+        println!("Saved screenshot to {}", path.display());
+    }
+    if let (Some(recorder), Some(path)) = (recorder, record_path) {
+        recorder
+            .save(&path)
+            .unwrap_or_else(|err| exit_with_error(&format!("Unable to save recording: {err}")));
+        println!("Saved recording to {}", path.display());
+    }
+    if print_ascii {
+        print!("{}", chip8.display.to_ascii());
+    }
+    println!("Ran {frames} frames, PC at {:#06x}", chip8.pc);
+}
+
+fn parse_number(arg: Option<&String>) -> usize {
+    arg.and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| exit_with_usage())
+}
+
+fn exit_with_usage() -> ! {
+    exit_with_error(USAGE)
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{message}");
+    process::exit(1);
+}
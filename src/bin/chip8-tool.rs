@@ -0,0 +1,277 @@
+use chip8::cfg::{ControlFlowGraph, RegionKind};
+use chip8::engine::Engine;
+use chip8::font::{Font, FontSet};
+use chip8::lint::Linter;
+use chip8::palette::Palette;
+use chip8::quirks::Quirks;
+use chip8::rom::ROM;
+use chip8::sprites::{self, SpriteSheet, SpriteTracker};
+use chip8::{Chip8, FONT_START};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+const USAGE: &str = "Usage: chip8-tool <command> [options]
+
+Commands:
+  sprites <rom> [--frames N] [--cycles N] [--quirks NAME] [--png OUT.png] [--scale N] [--columns N]
+      Lists the sprites a ROM draws, found statically and by running it for N frames (default 0)
+  font [rom] [--frames N] [--cycles N] [--quirks NAME] [--font NAME] [--address HEX] [--png OUT.png] [--scale N]
+      Previews the font in memory, or a font set loaded at the address, after optionally running a ROM
+  cfg <rom> [--dot OUT.dot]
+      Lists subroutines, indirect jumps and code that never runs, and exports the control-flow graph
+  lint <rom> [--frames N] [--cycles N] [--quirks NAME]
+      Flags platform-specific behaviour, also while running for N frames (default 0), and recommends quirks";
+
+/** Reverse-engineering tools for ROMs */
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("sprites") => sprites_command(&args[1..]),
+        Some("font") => font_command(&args[1..]),
+        Some("cfg") => cfg_command(&args[1..]),
+        Some("lint") => lint_command(&args[1..]),
+        _ => exit_with_usage(),
+    }
+}
+
+/** Options shared by the commands */
+struct RunOptions {
+    rom_path: Option<String>,
+    frames: usize,
+    cycles_per_frame: usize,
+    quirks: Quirks,
+    png_path: Option<PathBuf>,
+    dot_path: Option<PathBuf>,
+    scale: usize,
+    columns: usize,
+    address: usize,
+    font: Option<Font>,
+}
+impl RunOptions {
+    fn parse(args: &[String]) -> RunOptions {
+        let mut options = RunOptions {
+            rom_path: None,
+            frames: 0,
+            cycles_per_frame: 10,
+            quirks: Quirks::default(),
+            png_path: None,
+            dot_path: None,
+            scale: 4,
+            columns: 16,
+            address: FONT_START,
+            font: None,
+        };
+        let mut args_iter = args.iter();
+        while let Some(arg) = args_iter.next() {
+            match arg.as_str() {
+                "--frames" => options.frames = parse_number(args_iter.next()),
+                "--cycles" => options.cycles_per_frame = parse_number(args_iter.next()),
+                "--scale" => options.scale = parse_number(args_iter.next()),
+                "--columns" => options.columns = parse_number(args_iter.next()),
+                "--quirks" => {
+                    let name = args_iter.next().unwrap_or_else(|| exit_with_usage());
+                    options.quirks = Quirks::preset(name)
+                        .unwrap_or_else(|| exit_with_error(&format!("Unknown quirks: {name}")));
+                }
+                "--font" => {
+                    let name = args_iter.next().unwrap_or_else(|| exit_with_usage());
+                    let set = FontSet::from_name(name)
+                        .unwrap_or_else(|| exit_with_error(&format!("Unknown font set: {name}")));
+                    options.font = Some(set.font());
+                }
+                "--address" => {
+                    let hex = args_iter.next().unwrap_or_else(|| exit_with_usage());
+                    options.address = usize::from_str_radix(hex.trim_start_matches("0x"), 16)
+                        .ok()
+                        .filter(|address| *address < 4096)
+                        .unwrap_or_else(|| exit_with_error(&format!("Invalid address: {hex}")));
+                }
+                "--png" => options.png_path = args_iter.next().map(PathBuf::from),
+                "--dot" => options.dot_path = args_iter.next().map(PathBuf::from),
+                _ if arg.starts_with("--") => exit_with_usage(),
+                _ => options.rom_path = Some(arg.clone()),
+            }
+        }
+        options
+    }
+
+    /** Loads the ROM, if any, and runs it while recording the sprites it draws. Returns the ROM's bytes */
+    fn run(&self, tracker: &mut SpriteTracker) -> (Chip8, Vec<u8>) {
+        let mut chip8 = Chip8::new();
+        chip8.quirks = self.quirks;
+        if let Some(font) = &self.font {
+            chip8
+                .load_font(font, self.address as u16)
+                .unwrap_or_else(|err| exit_with_error(&err));
+        }
+        let Some(path) = &self.rom_path else {
+            return (chip8, Vec::new());
+        };
+        let rom = ROM::new(path);
+        let data = rom.data[..rom.size].to_vec();
+        chip8.load_rom(rom);
+        for _ in 0..self.frames {
+            tracker.run_frame(&mut chip8, self.cycles_per_frame);
+        }
+        (chip8, data)
+    }
+
+    fn save(&self, sheet: &SpriteSheet) {
+        if let Some(path) = &self.png_path {
+            sheet
+                .save_png(path, &Palette::default(), self.scale)
+                .unwrap_or_else(|err| exit_with_error(&format!("Unable to save sheet: {err}")));
+            println!("Saved sprite sheet to {}", path.display());
+        }
+    }
+}
+
+fn sprites_command(args: &[String]) {
+    let options = RunOptions::parse(args);
+    if options.rom_path.is_none() {
+        exit_with_usage();
+    }
+    let mut tracker = SpriteTracker::new();
+    let (chip8, rom) = options.run(&mut tracker);
+    let found_statically = sprites::scan(&rom);
+    let found_running = tracker.sprites();
+    tracker.extend(found_statically.iter().copied());
+
+    let sprites = tracker.sprites();
+    for sprite in &sprites {
+        let source = match (
+            found_statically.contains(sprite),
+            found_running.contains(sprite),
+        ) {
+            (true, true) => "static, drawn",
+            (true, false) => "static",
+            _ => "drawn",
+        };
+        println!("{:#05x} {:>2} rows ({source})", sprite.addr, sprite.height);
+    }
+    println!("{} sprites", sprites.len());
+    options.save(&SpriteSheet::from_sprites(
+        &chip8.ram,
+        &sprites,
+        options.columns,
+    ));
+}
+
+fn font_command(args: &[String]) {
+    let options = RunOptions::parse(args);
+    let (chip8, _) = options.run(&mut SpriteTracker::new());
+    let sheet = SpriteSheet::font(&chip8.ram, options.address);
+    // print the glyphs side by side, one text row per sprite row
+    for row in 0..5 {
+        let line: Vec<String> = sheet
+            .entries
+            .iter()
+            .map(|glyph| {
+                (0..4)
+                    .map(|bit| match glyph.rows[row] & (0x80 >> bit) {
+                        0 => '.',
+                        _ => '#',
+                    })
+                    .collect()
+            })
+            .collect();
+        println!("{}", line.join(" "));
+    }
+    options.save(&sheet);
+}
+
+fn cfg_command(args: &[String]) {
+    let options = RunOptions::parse(args);
+    if options.rom_path.is_none() {
+        exit_with_usage();
+    }
+    let (_, rom) = options.run(&mut SpriteTracker::new());
+    let graph = ControlFlowGraph::build(&rom);
+    let list = |addrs: Vec<u16>| match addrs.is_empty() {
+        true => "none".to_owned(),
+        false => addrs
+            .iter()
+            .map(|addr| format!("{addr:#05x}"))
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+
+    println!("{} blocks", graph.blocks.len());
+    println!(
+        "Subroutines: {}",
+        list(graph.subroutines.iter().copied().collect())
+    );
+    println!(
+        "Indirect jumps (BNNN): {}",
+        list(graph.indirect_jumps.clone())
+    );
+    println!(
+        "Machine code calls (0NNN): {}",
+        list(graph.machine_calls.clone())
+    );
+    for region in graph.regions() {
+        let kind = match region.kind {
+            RegionKind::Data => "data",
+            RegionKind::Unreachable => "unreachable",
+        };
+        println!(
+            "{:#05x}..{:#05x} {kind} ({} bytes)",
+            region.range.start,
+            region.range.end,
+            region.range.len()
+        );
+    }
+    if let Some(path) = &options.dot_path {
+        fs::write(path, graph.to_dot())
+            .unwrap_or_else(|err| exit_with_error(&format!("Unable to save graph: {err}")));
+        println!("Saved graph to {}", path.display());
+    }
+}
+
+fn lint_command(args: &[String]) {
+    let options = RunOptions::parse(args);
+    let Some(path) = &options.rom_path else {
+        exit_with_usage();
+    };
+    let rom = ROM::new(path);
+    let mut linter = Linter::new();
+    linter.check_rom(&rom.data[..rom.size]);
+
+    let mut chip8 = Chip8::new();
+    chip8.quirks = options.quirks;
+    chip8.load_rom(rom);
+    if let Err(err) = linter.run(&mut chip8, options.frames, options.cycles_per_frame) {
+        println!("Stopped running at {:#05x}: {err}", chip8.pc);
+    }
+
+    let lints = linter.lints();
+    for lint in &lints {
+        println!("{lint}");
+    }
+    println!("{} lints", lints.len());
+    let recommendation = linter.recommend();
+    match recommendation.reasons.is_empty() {
+        true => println!("Recommended quirks: {}", recommendation.preset),
+        false => println!(
+            "Recommended quirks: {} ({})",
+            recommendation.preset,
+            recommendation.reasons.join(", ")
+        ),
+    }
+}
+
+fn parse_number(arg: Option<&String>) -> usize {
+    arg.and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| exit_with_usage())
+}
+
+fn exit_with_usage() -> ! {
+    exit_with_error(USAGE)
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{message}");
+    process::exit(1);
+}
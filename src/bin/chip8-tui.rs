@@ -0,0 +1,363 @@
+use chip8::display::Display;
+use chip8::error::Chip8Error;
+use chip8::font::{Font, FontSet};
+use chip8::palette::{Palette, PaletteDatabase, Rgb, Theme};
+use chip8::platform::Platform;
+use chip8::quirks::Quirks;
+use chip8::rng::Generator;
+use chip8::rom::ROM;
+use chip8::timing::Timing;
+use chip8::{Chip8, FONT_START};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::env;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "Usage: chip8-tui <rom> [--cycles N] [--braille] [--seed N] [--rng xorshift|vip] [--quirks NAME] [--timing fast|vip] [--platform NAME] [--font NAME | --font-file PATH] [--font-addr HEX] [--theme NAME | --palette HEX,HEX | --palette-db PATH]";
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/** Terminals only report key presses, so a key is held down for this many frames after each press */
+const KEY_HOLD_FRAMES: u8 = 6;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Glyphs {
+    /** Two pixels per character cell using ▀ ▄ █ */
+    HalfBlock,
+    /** Eight pixels per character cell using Unicode braille patterns */
+    Braille,
+}
+
+/** Runs a ROM inside the terminal, e.g. over SSH where no window can be opened */
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut rom_path: Option<String> = None;
+    let mut cycles_per_frame: usize = 10;
+    let mut glyphs = Glyphs::HalfBlock;
+    let mut palette: Option<Palette> = None;
+    let mut palette_db: Option<PaletteDatabase> = None;
+    let mut seed: Option<u64> = None;
+    let mut generator = Generator::default();
+    let mut quirks = Quirks::default();
+    let mut timing = Timing::default();
+    let mut platform = Platform::default();
+    let mut font = Font::default();
+    let mut font_addr = FONT_START as u16;
+
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--cycles" => {
+                cycles_per_frame = args_iter
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or_else(|| exit_with_error(USAGE));
+            }
+            "--braille" => glyphs = Glyphs::Braille,
+            "--theme" => {
+                let name = args_iter.next().unwrap_or_else(|| exit_with_error(USAGE));
+                palette = Some(
+                    Theme::from_name(name)
+                        .unwrap_or_else(|| exit_with_error(&format!("Unknown theme: {name}")))
+                        .palette(),
+                );
+            }
+            "--seed" => {
+                seed = args_iter
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .or_else(|| exit_with_error(USAGE));
+            }
+            "--rng" => {
+                let name = args_iter.next().unwrap_or_else(|| exit_with_error(USAGE));
+                generator = Generator::from_name(name).unwrap_or_else(|| {
+                    exit_with_error(&format!("Unknown random generator: {name}"))
+                });
+            }
+            "--quirks" => {
+                let name = args_iter.next().unwrap_or_else(|| exit_with_error(USAGE));
+                quirks = Quirks::preset(name)
+                    .unwrap_or_else(|| exit_with_error(&format!("Unknown quirks: {name}")));
+            }
+            "--timing" => {
+                let name = args_iter.next().unwrap_or_else(|| exit_with_error(USAGE));
+                timing = Timing::from_name(name)
+                    .unwrap_or_else(|| exit_with_error(&format!("Unknown timing: {name}")));
+            }
+            "--platform" => {
+                let name = args_iter.next().unwrap_or_else(|| exit_with_error(USAGE));
+                platform = Platform::from_name(name)
+                    .unwrap_or_else(|| exit_with_error(&format!("Unknown platform: {name}")));
+            }
+            "--font" => {
+                let name = args_iter.next().unwrap_or_else(|| exit_with_error(USAGE));
+                font = FontSet::from_name(name)
+                    .unwrap_or_else(|| exit_with_error(&format!("Unknown font set: {name}")))
+                    .font();
+            }
+            "--font-file" => {
+                let path = args_iter.next().unwrap_or_else(|| exit_with_error(USAGE));
+                font = Font::load(Path::new(path))
+                    .unwrap_or_else(|err| exit_with_error(&format!("Unable to load font: {err}")));
+            }
+            "--font-addr" => {
+                let hex = args_iter.next().unwrap_or_else(|| exit_with_error(USAGE));
+                font_addr = u16::from_str_radix(hex.trim_start_matches("0x"), 16)
+                    .unwrap_or_else(|_| exit_with_error(&format!("Invalid address: {hex}")));
+            }
+            "--palette" => {
+                let hex = args_iter.next().unwrap_or_else(|| exit_with_error(USAGE));
+                palette = Some(Palette::from_hex(hex).unwrap_or_else(|err| exit_with_error(&err)));
+            }
+            "--palette-db" => {
+                let path = args_iter.next().unwrap_or_else(|| exit_with_error(USAGE));
+                palette_db = Some(
+                    PaletteDatabase::load(Path::new(path)).unwrap_or_else(|err| {
+                        exit_with_error(&format!("Unable to load palettes: {err}"))
+                    }),
+                );
+            }
+            _ => rom_path = Some(arg.clone()),
+        }
+    }
+    let rom_path = rom_path.unwrap_or_else(|| exit_with_error(USAGE));
+    let rom = ROM::new(&rom_path);
+    // an explicit theme or palette wins over the database
+    let palette = palette
+        .or_else(|| palette_db?.lookup(&rom.sha1()))
+        .unwrap_or_default();
+
+    let mut chip8 = match seed {
+        Some(seed) => Chip8::with_seed(seed),
+        None => Chip8::new(),
+    };
+    chip8.set_generator(generator);
+    chip8.quirks = quirks;
+    chip8.timing = timing;
+    chip8.set_platform(platform);
+    chip8
+        .load_font(&font, font_addr)
+        .unwrap_or_else(|err| exit_with_error(&err));
+    chip8.load_rom(rom);
+
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode().expect("Unable to enable raw terminal mode");
+    let guard = TerminalGuard;
+    execute!(stdout, EnterAlternateScreen, Hide, Clear(ClearType::All))
+        .expect("Unable to set up terminal");
+
+    let result = run(&mut chip8, &mut stdout, cycles_per_frame, glyphs, palette);
+
+    // exiting skips destructors, so restore the terminal first
+    drop(guard);
+    match result {
+        Err(err) => exit_with_error(&format!("Terminal error: {err}")),
+        Ok(Err(err)) => exit_with_error(&format!("{err} at {:#05x}", chip8.pc)),
+        Ok(Ok(())) => println!("Goodbye"),
+    }
+}
+
+/** Leaves raw mode and the alternate screen when dropped, including while a panic unwinds */
+struct TerminalGuard;
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        execute!(io::stdout(), ResetColor, Show, LeaveAlternateScreen).ok();
+        terminal::disable_raw_mode().ok();
+    }
+}
+
+/** Runs until Esc, or until the program hits an error, which is returned rather than panicking */
+fn run(
+    chip8: &mut Chip8,
+    stdout: &mut io::Stdout,
+    cycles_per_frame: usize,
+    glyphs: Glyphs,
+    palette: Palette,
+) -> io::Result<Result<(), Chip8Error>> {
+    let mut held_key: Option<(u8, u8)> = None;
+    let mut was_beeping = false;
+    let mut drawn_frame_id: Option<u64> = None;
+    let mut next_frame = Instant::now();
+
+    loop {
+        // input
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key_event) = event::read()? {
+                if key_event.kind == KeyEventKind::Release {
+                    continue;
+                }
+                match key_event.code {
+                    KeyCode::Esc => return Ok(Ok(())),
+                    KeyCode::Char(c) => {
+                        if let Some(key) = map_key(c.to_ascii_lowercase()) {
+                            held_key = Some((key, KEY_HOLD_FRAMES));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        chip8.set_input_key(held_key.map(|(key, _)| key));
+        held_key = held_key.and_then(|(key, frames)| (frames > 1).then(|| (key, frames - 1)));
+
+        // execute
+        if let Err(err) = chip8.try_run_frame(cycles_per_frame) {
+            return Ok(Err(err));
+        }
+
+        // sound the bell once each time the sound timer starts
+        let beeping = chip8.sound_timer > 0;
+        if beeping && !was_beeping {
+            queue!(stdout, Print('\u{7}'))?;
+        }
+        was_beeping = beeping;
+
+        // draw
+        if drawn_frame_id != Some(chip8.display.frame_id()) {
+            draw_display(stdout, &chip8.display, glyphs, palette)?;
+            drawn_frame_id = Some(chip8.display.frame_id());
+        }
+        draw_registers(stdout, chip8, glyphs)?;
+        stdout.flush()?;
+
+        next_frame += FRAME_TIME;
+        let now = Instant::now();
+        if next_frame > now {
+            std::thread::sleep(next_frame - now);
+        } else {
+            next_frame = now;
+        }
+    }
+}
+
+fn draw_display(
+    stdout: &mut io::Stdout,
+    display: &Display,
+    glyphs: Glyphs,
+    palette: Palette,
+) -> io::Result<()> {
+    queue!(
+        stdout,
+        SetForegroundColor(to_color(palette.foreground())),
+        SetBackgroundColor(to_color(palette.background()))
+    )?;
+    let (cell_width, cell_height) = cell_size(glyphs);
+    for cell_y in 0..display.height() / cell_height {
+        let mut line = String::with_capacity(chip8::WIDTH);
+        for cell_x in 0..chip8::WIDTH / cell_width {
+            let (x, y) = (cell_x * cell_width, cell_y * cell_height);
+            line.push(match glyphs {
+                Glyphs::HalfBlock => half_block(display, x, y),
+                Glyphs::Braille => braille(display, x, y),
+            });
+        }
+        queue!(stdout, MoveTo(0, cell_y as u16), Print(line))?;
+    }
+    queue!(stdout, ResetColor)
+}
+
+fn draw_registers(stdout: &mut io::Stdout, chip8: &Chip8, glyphs: Glyphs) -> io::Result<()> {
+    let (cell_width, _) = cell_size(glyphs);
+    let column = (chip8::WIDTH / cell_width) as u16 + 2;
+    let mut lines = vec![
+        format!("PC {:#06x}  I {:#06x}", chip8.pc, chip8.idx_reg),
+        format!("DT {:3}     ST {:3}", chip8.delay_timer, chip8.sound_timer),
+        format!(
+            "SP {:2}/{:<2}   KEY {}",
+            chip8.call_stack.len(),
+            chip8.quirks.stack_depth,
+            key_label(chip8.curr_input_key)
+        ),
+    ];
+    for (index, values) in chip8.var_reg.chunks(4).enumerate() {
+        let registers: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| format!("V{:X} {value:02x}", index * 4 + i))
+            .collect();
+        lines.push(registers.join(" "));
+    }
+    for (row, line) in lines.iter().enumerate() {
+        queue!(
+            stdout,
+            MoveTo(column, row as u16),
+            Print(format!("{line:<24}"))
+        )?;
+    }
+    Ok(())
+}
+
+fn cell_size(glyphs: Glyphs) -> (usize, usize) {
+    match glyphs {
+        Glyphs::HalfBlock => (1, 2),
+        Glyphs::Braille => (2, 4),
+    }
+}
+
+fn half_block(display: &Display, x: usize, y: usize) -> char {
+    match (display.get_pixel(y, x), display.get_pixel(y + 1, x)) {
+        (true, true) => '█',
+        (true, false) => '▀',
+        (false, true) => '▄',
+        (false, false) => ' ',
+    }
+}
+
+fn braille(display: &Display, x: usize, y: usize) -> char {
+    // Dot bit for each (column, row) of a braille cell, see the Unicode braille pattern block
+    const DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+    let mut pattern = 0;
+    for (col, dots) in DOTS.iter().enumerate() {
+        for (row, dot) in dots.iter().enumerate() {
+            if display.get_pixel(y + row, x + col) {
+                pattern |= dot;
+            }
+        }
+    }
+    char::from_u32(0x2800 + pattern).unwrap_or(' ')
+}
+
+/** Same QWERTY layout as the windowed frontend */
+fn map_key(c: char) -> Option<u8> {
+    match c {
+        '1' => Some(0x1),
+        '2' => Some(0x2),
+        '3' => Some(0x3),
+        '4' => Some(0xC),
+        'q' => Some(0x4),
+        'w' => Some(0x5),
+        'e' => Some(0x6),
+        'r' => Some(0xD),
+        'a' => Some(0x7),
+        's' => Some(0x8),
+        'd' => Some(0x9),
+        'f' => Some(0xE),
+        'z' => Some(0xA),
+        'x' => Some(0x0),
+        'c' => Some(0xB),
+        'v' => Some(0xF),
+        _ => None,
+    }
+}
+
+fn key_label(key: Option<u8>) -> String {
+    key.map(|key| format!("{key:X}"))
+        .unwrap_or_else(|| "-".to_owned())
+}
+
+fn to_color(rgb: Rgb) -> Color {
+    Color::Rgb {
+        r: rgb[0],
+        g: rgb[1],
+        b: rgb[2],
+    }
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{message}");
+    process::exit(1);
+}
@@ -0,0 +1,139 @@
+use crate::Chip8;
+use crate::engine::{self, Engine};
+use crate::predecode::Op;
+use crate::timing::Timing;
+
+/** Longest block compiled, which also bounds how far back invalidation has to look */
+const MAX_BLOCK_LEN: usize = 32;
+
+/** Whether the op may move the PC anywhere but the next instruction, or write over code */
+fn ends_block(op: Op) -> bool {
+    matches!(
+        op,
+        Op::Return
+            | Op::Jump(_)
+            | Op::Call(_)
+            | Op::JumpOffset(_)
+            | Op::SkipEqImm(..)
+            | Op::SkipNeImm(..)
+            | Op::SkipEq(..)
+            | Op::SkipNe(..)
+            | Op::SkipKey(_)
+            | Op::SkipNotKey(_)
+            | Op::WaitKey(_)
+            | Op::Bcd(_)
+            | Op::Store(_)
+            | Op::SkipKey2(_)
+            | Op::SkipNotKey2(_)
+            | Op::Stop
+            | Op::WaitDelay
+            | Op::Skip
+            | Op::SkipGreater(..)
+            | Op::StoreRange(..)
+            // the second word of 01NN NNNN isn't an instruction
+            | Op::LongIndex(_)
+            | Op::BranchBack(_)
+            | Op::BranchForward(_)
+            | Op::SkipBytes(_)
+            | Op::DelayWait(_)
+            | Op::MachineCall(_)
+            | Op::Unsupported(_)
+    )
+}
+
+/**
+ * Compiles straight-line runs of instructions into cached blocks of decoded ops and runs
+ * them back to back. Blocks end at any branch, skip or RAM write, and writes from FX55 or
+ * FX33 drop every block they touch, so self-modifying code behaves exactly like `Chip8::cycle`.
+ */
+pub struct BlockEngine {
+    /**
+     * The block starting at each RAM address, compiled the first time it is reached. Covers
+     * the first 4KB and grows when MEGA-CHIP runs code past it
+     */
+    blocks: Vec<Option<Box<[Op]>>>,
+}
+impl Default for BlockEngine {
+    fn default() -> Self {
+        BlockEngine::new()
+    }
+}
+impl BlockEngine {
+    pub fn new() -> BlockEngine {
+        BlockEngine {
+            blocks: vec![None; 4096],
+        }
+    }
+
+    /** Drops cached blocks overlapping the given byte range of RAM */
+    pub fn invalidate(&mut self, start: usize, end: usize) {
+        let first = start.saturating_sub(MAX_BLOCK_LEN * 2 - 1);
+        let last = end.min(self.blocks.len());
+        for address in first..last {
+            let overlaps = self.blocks[address]
+                .as_ref()
+                .is_some_and(|block| address + block.len() * 2 > start);
+            if overlaps {
+                self.blocks[address] = None;
+            }
+        }
+    }
+
+    fn compile(chip8: &Chip8, start: usize) -> Box<[Op]> {
+        let mut ops = Vec::new();
+        let mut pc = start;
+        loop {
+            let instruction = (chip8.ram[pc] as u16) << 8 | chip8.ram[pc + 1] as u16;
+            let op = Op::decode_for(instruction, chip8.platform);
+            ops.push(op);
+            pc += 2;
+            if ends_block(op) || ops.len() == MAX_BLOCK_LEN || pc + 1 >= chip8.ram.len() {
+                break;
+            }
+        }
+        ops.into_boxed_slice()
+    }
+
+    /** Runs at most `budget` instructions of the block at the PC, returning how many ran */
+    fn run_block(&mut self, chip8: &mut Chip8, budget: usize) -> usize {
+        let start = chip8.pc as usize;
+        if start >= self.blocks.len() {
+            self.blocks.resize(start + 1, None);
+        }
+        let block = self.blocks[start].get_or_insert_with(|| BlockEngine::compile(chip8, start));
+        let count = block.len().min(budget);
+
+        // only the last op of a block can write to RAM
+        let mut write_range = None;
+        for &op in &block[..count] {
+            write_range = op.write_range(chip8);
+            chip8.count_instruction();
+            chip8.pc += 2;
+            op.execute(chip8);
+        }
+        if let Some((start, end)) = write_range {
+            self.invalidate(start, end);
+        }
+        count
+    }
+}
+impl Engine for BlockEngine {
+    fn step(&mut self, chip8: &mut Chip8) {
+        self.run_block(chip8, 1);
+    }
+
+    fn run_frame(&mut self, chip8: &mut Chip8, cycles_per_frame: usize) {
+        if chip8.timing == Timing::Vip {
+            return engine::run_vip_frame(self, chip8);
+        }
+        let mut remaining = cycles_per_frame;
+        while remaining > 0 {
+            remaining -= self.run_block(chip8, remaining);
+        }
+        chip8.decrement_timers(1);
+    }
+
+    fn invalidate_all(&mut self) {
+        self.blocks.fill(None);
+    }
+}
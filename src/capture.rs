@@ -0,0 +1,237 @@
+use crate::display::{Display, HEIGHT, WIDTH};
+use crate::megachip::{MEGA_HEIGHT, MEGA_WIDTH};
+use crate::palette::{Palette, Rgb};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+/** Frames per second the recorder captures at, matching the 60Hz timers */
+pub const FRAME_RATE: u32 = 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordingFormat {
+    Gif,
+    Apng,
+}
+impl RecordingFormat {
+    /** Picks a format from a file extension, ".gif" or ".png"/".apng" */
+    pub fn from_path(path: &Path) -> Option<RecordingFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gif" => Some(RecordingFormat::Gif),
+            "png" | "apng" => Some(RecordingFormat::Apng),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Encodes displays as images the way the frontends draw them: through the palette and any
+ * CHIP-8X colours, with MEGA-CHIP's colour screen centred at 4:3
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capture {
+    pub palette: Palette,
+    pub scale: usize,
+    /** Rows in the image before scaling: 32, or 64 for HiRes CHIP-8 */
+    pub rows: usize,
+}
+impl Capture {
+    pub fn new(palette: Palette, scale: usize) -> Capture {
+        Capture {
+            palette,
+            scale: scale.max(1),
+            rows: HEIGHT,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        WIDTH * self.scale
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows * self.scale
+    }
+
+    /** Saves the current display as a PNG screenshot */
+    pub fn save_png(&self, display: &Display, path: &Path) -> io::Result<()> {
+        let frame = self.frame(display);
+        let colors = self.indexed_colors([&frame]);
+        let mut writer = self
+            .png_encoder(path, colors.as_deref())?
+            .write_header()
+            .map_err(io::Error::other)?;
+        writer
+            .write_image_data(&png_pixels(&frame, colors.as_deref()))
+            .map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+
+    /** Writes indexed pixels given the colours from `indexed_colors`, and RGB pixels otherwise */
+    fn png_encoder(
+        &self,
+        path: &Path,
+        colors: Option<&[Rgb]>,
+    ) -> io::Result<png::Encoder<'static, BufWriter<File>>> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width() as u32, self.height() as u32);
+        encoder.set_depth(png::BitDepth::Eight);
+        match colors {
+            Some(colors) => {
+                encoder.set_color(png::ColorType::Indexed);
+                encoder.set_palette(colors.concat());
+            }
+            None => encoder.set_color(png::ColorType::Rgb),
+        }
+        Ok(encoder)
+    }
+
+    /**
+     * A palette covering every colour in the frames, starting with `palette`'s colours, or
+     * None if they need more than 256
+     */
+    fn indexed_colors<'a>(&self, frames: impl IntoIterator<Item = &'a Frame>) -> Option<Vec<Rgb>> {
+        let mut colors = self.palette.colors.to_vec();
+        for frame in frames {
+            for pixel in frame.pixels.chunks_exact(3) {
+                if !colors.iter().any(|color| color == pixel) {
+                    if colors.len() == 256 {
+                        return None;
+                    }
+                    colors.push(pixel.try_into().unwrap());
+                }
+            }
+        }
+        Some(colors)
+    }
+
+    /**
+     * Renders the display at the capture's size. Taller displays get shorter pixels and
+     * MEGA-CHIP's screen is drawn as large as fits, centred on the background, as in the window
+     */
+    fn frame(&self, display: &Display) -> Frame {
+        let (width, height) = (self.width(), self.height());
+        let background = self.palette.background();
+        let mut pixels: Vec<u8> = std::iter::repeat_n(background, width * height)
+            .flatten()
+            .collect();
+        let rgba = display.to_rgba(&self.palette);
+        let (source_width, source_height, dest_width) = match display.mega_mode() {
+            Some(_) => (MEGA_WIDTH, MEGA_HEIGHT, height * MEGA_WIDTH / MEGA_HEIGHT),
+            None => (WIDTH, display.height(), width),
+        };
+        let left = width.saturating_sub(dest_width) / 2;
+        for y in 0..height {
+            let source_y = y * source_height / height;
+            for x in left..(left + dest_width).min(width) {
+                let source = (source_y * source_width + (x - left) * source_width / dest_width) * 4;
+                let out = (y * width + x) * 3;
+                pixels[out..out + 3].copy_from_slice(&rgba[source..source + 3]);
+            }
+        }
+        Frame { pixels, ticks: 1 }
+    }
+}
+
+/** One captured screen as RGB, held for however many 60Hz ticks it stayed on screen */
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Frame {
+    pixels: Vec<u8>,
+    ticks: u32,
+}
+
+/** Image data for the PNG encoder: palette indices with `colors`, RGB without */
+fn png_pixels(frame: &Frame, colors: Option<&[Rgb]>) -> Vec<u8> {
+    match colors {
+        Some(colors) => frame
+            .pixels
+            .chunks_exact(3)
+            .map(|pixel| colors.iter().position(|color| color == pixel).unwrap() as u8)
+            .collect(),
+        None => frame.pixels.clone(),
+    }
+}
+
+/** Records an animated GIF or APNG clip, one frame per 60Hz tick */
+pub struct Recorder {
+    capture: Capture,
+    format: RecordingFormat,
+    frames: Vec<Frame>,
+}
+impl Recorder {
+    pub fn new(capture: Capture, format: RecordingFormat) -> Recorder {
+        Recorder {
+            capture,
+            format,
+            frames: Vec::new(),
+        }
+    }
+
+    /** Captures the display for one tick. Unchanged frames just extend the previous frame */
+    pub fn push_frame(&mut self, display: &Display) {
+        let frame = self.capture.frame(display);
+        match self.frames.last_mut() {
+            Some(last) if last.pixels == frame.pixels => last.ticks += 1,
+            _ => self.frames.push(frame),
+        }
+    }
+
+    /** Number of 60Hz ticks recorded so far */
+    pub fn ticks(&self) -> u32 {
+        self.frames.iter().map(|frame| frame.ticks).sum()
+    }
+
+    /** Encodes the recorded clip and writes it to the given path */
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if self.frames.is_empty() {
+            return Err(io::Error::other("No frames have been recorded"));
+        }
+        match self.format {
+            RecordingFormat::Gif => self.save_gif(path),
+            RecordingFormat::Apng => self.save_apng(path),
+        }
+    }
+
+    fn save_gif(&self, path: &Path) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let width = self.capture.width() as u16;
+        let height = self.capture.height() as u16;
+        let mut encoder = gif::Encoder::new(file, width, height, &[]).map_err(io::Error::other)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(io::Error::other)?;
+
+        // GIF delays are in hundredths of a second, so round the running total to keep 60Hz on average
+        let mut elapsed_ticks = 0;
+        let mut elapsed_centis = 0;
+        for frame in &self.frames {
+            elapsed_ticks += frame.ticks;
+            let end_centis = (elapsed_ticks * 100 + FRAME_RATE / 2) / FRAME_RATE;
+            // exact when a frame has at most 256 colours, which only blended MEGA-CHIP frames exceed
+            let mut gif_frame = gif::Frame::from_rgb_speed(width, height, &frame.pixels, 10);
+            gif_frame.delay = (end_centis - elapsed_centis) as u16;
+            elapsed_centis = end_centis;
+            encoder.write_frame(&gif_frame).map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    fn save_apng(&self, path: &Path) -> io::Result<()> {
+        // every frame shares one palette, so the clip is indexed only if all of them fit in it
+        let colors = self.capture.indexed_colors(&self.frames);
+        let mut encoder = self.capture.png_encoder(path, colors.as_deref())?;
+        encoder
+            .set_animated(self.frames.len() as u32, 0)
+            .map_err(io::Error::other)?;
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        for frame in &self.frames {
+            writer
+                .set_frame_delay(frame.ticks.min(u16::MAX as u32) as u16, FRAME_RATE as u16)
+                .map_err(io::Error::other)?;
+            writer
+                .write_image_data(&png_pixels(frame, colors.as_deref()))
+                .map_err(io::Error::other)?;
+        }
+        writer.finish().map_err(io::Error::other)
+    }
+}
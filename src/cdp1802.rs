@@ -0,0 +1,361 @@
+use crate::Chip8;
+use crate::display::HEIGHT;
+use crate::quirks::VIP_STACK_TOP;
+use std::fmt;
+
+/** Where the VIP interpreter keeps V0 to VF, which machine code reaches through R6 and R7 */
+pub const VARIABLES_ADDR: usize = 0xEF0;
+/** Where the VIP keeps the 64x32 display, 8 bytes per row with the leftmost pixel in bit 7 */
+pub const DISPLAY_ADDR: usize = 0xF00;
+/** Instructions a routine may run before it's assumed never to return */
+pub const MAX_INSTRUCTIONS: usize = 1_000_000;
+
+/** Why a machine code routine couldn't be run */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MachineCodeError {
+    /** An 1802 instruction this CPU doesn't emulate, such as IDL or I/O other than the keypad */
+    Unsupported { addr: u16, opcode: u8 },
+    /** The routine starting here ran for `MAX_INSTRUCTIONS` without returning with SEP R4 */
+    NoReturn(u16),
+}
+impl fmt::Display for MachineCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MachineCodeError::Unsupported { addr, opcode } => {
+                write!(
+                    f,
+                    "unsupported 1802 instruction {opcode:02X} at {addr:#06x}"
+                )
+            }
+            MachineCodeError::NoReturn(addr) => {
+                write!(f, "routine at {addr:#06x} never returned with SEP R4")
+            }
+        }
+    }
+}
+
+/**
+ * A minimal RCA CDP1802, enough to run the machine code subroutines VIP programs call with
+ * 0NNN. Memory is the Chip8's RAM, mirrored every 4KB like on a VIP with 4KB fitted
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cdp1802 {
+    /** The 16 scratchpad registers R0 to RF */
+    pub r: [u16; 16],
+    /** The accumulator */
+    pub d: u8,
+    /** Carry, or no borrow after a subtraction */
+    pub df: bool,
+    /** Which register is the program counter */
+    pub p: u8,
+    /** Which register addresses memory for ALU and stack instructions */
+    pub x: u8,
+    /** X and P saved by MARK or an interrupt */
+    pub t: u8,
+    pub ie: bool,
+    /** The Q output, which drives the VIP's speaker */
+    pub q: bool,
+    /** The key selected by OUT 2, reported on EF3 while it's held */
+    pub keypad_latch: u8,
+    pub key: Option<u8>,
+    /** Machine cycles run: 2 per instruction, 3 for long branches and skips */
+    pub cycles: u64,
+}
+impl Cdp1802 {
+    pub fn new() -> Cdp1802 {
+        Cdp1802::default()
+    }
+
+    /**
+     * Sets up the registers the way the VIP interpreter leaves them when it runs 0NNN: R3 is
+     * the PC, R2 the stack, R5 the CHIP-8 PC, R6 and R7 point to VX and VY from the
+     * instruction, R8 holds the timers, RA is I and RB the display
+     */
+    pub fn vip_call(chip8: &Chip8, addr: u16) -> Cdp1802 {
+        let mut cpu = Cdp1802 {
+            p: 3,
+            x: 2,
+            ie: true,
+            key: chip8.curr_input_key,
+            ..Cdp1802::default()
+        };
+        cpu.r[2] = (VIP_STACK_TOP - 1 - 2 * chip8.call_stack.len()) as u16;
+        cpu.r[3] = addr;
+        cpu.r[5] = chip8.pc;
+        cpu.r[6] = (VARIABLES_ADDR + (addr as usize >> 8 & 0xF)) as u16;
+        cpu.r[7] = (VARIABLES_ADDR + (addr as usize >> 4 & 0xF)) as u16;
+        cpu.r[8] = u16::from_be_bytes([chip8.delay_timer, chip8.sound_timer]);
+        cpu.r[0xA] = chip8.idx_reg as u16;
+        cpu.r[0xB] = DISPLAY_ADDR as u16;
+        cpu
+    }
+
+    /** Runs from R(P) until SEP R4 hands control back to the interpreter's fetch loop */
+    pub fn run_until_return(&mut self, ram: &mut [u8]) -> Result<(), MachineCodeError> {
+        let start = self.r[self.p as usize];
+        for _ in 0..MAX_INSTRUCTIONS {
+            self.step(ram)?;
+            if self.p == 4 {
+                return Ok(());
+            }
+        }
+        Err(MachineCodeError::NoReturn(start))
+    }
+
+    /** Executes a single instruction */
+    pub fn step(&mut self, ram: &mut [u8]) -> Result<(), MachineCodeError> {
+        let addr = self.r[self.p as usize];
+        let opcode = ram[addr as usize & 0xFFF];
+        self.r[self.p as usize] = addr.wrapping_add(1);
+        self.cycles += 2;
+        let unsupported = Err(MachineCodeError::Unsupported { addr, opcode });
+        let n = (opcode & 0xF) as usize;
+        let (x, p) = (self.x as usize, self.p as usize);
+        let m = |ram: &[u8], reg: u16| ram[reg as usize & 0xFFF];
+
+        match opcode >> 4 {
+            // IDL waits for DMA or an interrupt, which never come while the interpreter waits
+            0x0 if n == 0 => return unsupported,
+            0x0 => self.d = m(ram, self.r[n]),
+            0x1 => self.r[n] = self.r[n].wrapping_add(1),
+            0x2 => self.r[n] = self.r[n].wrapping_sub(1),
+            0x3 => {
+                let target = m(ram, self.r[p]);
+                let taken = match n {
+                    // SKP skips the byte that would be the target
+                    0x8 => false,
+                    0x0..=0x7 => self.condition(n),
+                    _ => !self.condition(n - 8),
+                };
+                match taken {
+                    true => self.r[p] = (self.r[p] & 0xFF00) | target as u16,
+                    false => self.r[p] = self.r[p].wrapping_add(1),
+                }
+            }
+            0x4 => {
+                self.d = m(ram, self.r[n]);
+                self.r[n] = self.r[n].wrapping_add(1);
+            }
+            0x5 => ram[self.r[n] as usize & 0xFFF] = self.d,
+            0x6 => match n {
+                // IRX
+                0x0 => self.r[x] = self.r[x].wrapping_add(1),
+                // OUT 1 turns the display off, OUT 2 selects the key to check on EF3
+                0x1 | 0x2 => {
+                    if n == 2 {
+                        self.keypad_latch = m(ram, self.r[x]) & 0xF;
+                    }
+                    self.r[x] = self.r[x].wrapping_add(1);
+                }
+                // INP 1 turns the display on; the floating bus reads as what's in memory
+                0x9 => self.d = m(ram, self.r[x]),
+                _ => return unsupported,
+            },
+            0x7 => match n {
+                // RET and DIS
+                0x0 | 0x1 => {
+                    let byte = m(ram, self.r[x]);
+                    self.r[x] = self.r[x].wrapping_add(1);
+                    self.x = byte >> 4;
+                    self.p = byte & 0xF;
+                    self.ie = n == 0;
+                }
+                // LDXA
+                0x2 => {
+                    self.d = m(ram, self.r[x]);
+                    self.r[x] = self.r[x].wrapping_add(1);
+                }
+                // STXD
+                0x3 => {
+                    ram[self.r[x] as usize & 0xFFF] = self.d;
+                    self.r[x] = self.r[x].wrapping_sub(1);
+                }
+                0x4 => self.add(m(ram, self.r[x]), self.df),
+                0x5 => self.subtract(m(ram, self.r[x]), self.d, self.df),
+                0x6 => self.shift_right(self.df),
+                0x7 => self.subtract(self.d, m(ram, self.r[x]), self.df),
+                // SAV
+                0x8 => ram[self.r[x] as usize & 0xFFF] = self.t,
+                // MARK
+                0x9 => {
+                    self.t = self.x << 4 | self.p;
+                    ram[self.r[2] as usize & 0xFFF] = self.t;
+                    self.x = self.p;
+                    self.r[2] = self.r[2].wrapping_sub(1);
+                }
+                0xA => self.q = false,
+                0xB => self.q = true,
+                0xE => self.shift_left(self.df),
+                _ => {
+                    let operand = self.immediate(ram);
+                    match n {
+                        0xC => self.add(operand, self.df),
+                        0xD => self.subtract(operand, self.d, self.df),
+                        _ => self.subtract(self.d, operand, self.df),
+                    }
+                }
+            },
+            0x8 => self.d = self.r[n] as u8,
+            0x9 => self.d = (self.r[n] >> 8) as u8,
+            0xA => self.r[n] = (self.r[n] & 0xFF00) | self.d as u16,
+            0xB => self.r[n] = (self.r[n] & 0x00FF) | (self.d as u16) << 8,
+            0xC => self.long_branch(ram, n),
+            0xD => self.p = n as u8,
+            0xE => self.x = n as u8,
+            _ => match n {
+                // SHR and SHL don't take an operand
+                0x6 => self.shift_right(false),
+                0xE => self.shift_left(false),
+                _ => {
+                    // F0 to F7 take their operand from M(R(X)), F8 to FF from the next byte
+                    let operand = match n {
+                        0x0..=0x7 => m(ram, self.r[x]),
+                        _ => self.immediate(ram),
+                    };
+                    match n & 0x7 {
+                        0x0 => self.d = operand,
+                        0x1 => self.d |= operand,
+                        0x2 => self.d &= operand,
+                        0x3 => self.d ^= operand,
+                        0x4 => self.add(operand, false),
+                        0x5 => self.subtract(operand, self.d, true),
+                        _ => self.subtract(self.d, operand, true),
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /** Q, D = 0, DF or an EF line, for branches 1 to 7 of each group */
+    fn condition(&self, n: usize) -> bool {
+        match n {
+            0x0 => true,
+            0x1 => self.q,
+            0x2 => self.d == 0,
+            0x3 => self.df,
+            // EF3 is the VIP keypad; EF1, EF2 and EF4 stay inactive
+            0x6 => self.key == Some(self.keypad_latch),
+            _ => false,
+        }
+    }
+
+    /** C0 to CF: long branches to the next two bytes, and long skips over them */
+    fn long_branch(&mut self, ram: &[u8], n: usize) {
+        self.cycles += 1;
+        let p = self.p as usize;
+        let condition = match n & 0x3 {
+            0x0 => true,
+            0x1 => self.q,
+            0x2 => self.d == 0,
+            _ => self.df,
+        };
+        match n {
+            // NOP
+            0x4 => {}
+            // LSIE
+            0xC => {
+                if self.ie {
+                    self.r[p] = self.r[p].wrapping_add(2);
+                }
+            }
+            // LSNQ, LSNZ, LSNF and LSKP
+            0x5..=0x8 => {
+                let skip = match n {
+                    0x8 => true,
+                    _ => !condition,
+                };
+                if skip {
+                    self.r[p] = self.r[p].wrapping_add(2);
+                }
+            }
+            // LSQ, LSZ and LSDF
+            0xD..=0xF => {
+                if condition {
+                    self.r[p] = self.r[p].wrapping_add(2);
+                }
+            }
+            // LBR, LBQ, LBZ, LBDF and their negations from C8 up
+            _ => {
+                let taken = match n < 0x8 {
+                    true => condition,
+                    false => !condition,
+                };
+                let high = ram[self.r[p] as usize & 0xFFF];
+                let low = ram[self.r[p].wrapping_add(1) as usize & 0xFFF];
+                match taken {
+                    true => self.r[p] = u16::from_be_bytes([high, low]),
+                    false => self.r[p] = self.r[p].wrapping_add(2),
+                }
+            }
+        }
+    }
+
+    /** Reads the byte after the instruction */
+    fn immediate(&mut self, ram: &[u8]) -> u8 {
+        let p = self.p as usize;
+        let byte = ram[self.r[p] as usize & 0xFFF];
+        self.r[p] = self.r[p].wrapping_add(1);
+        byte
+    }
+
+    fn add(&mut self, operand: u8, carry: bool) {
+        let sum = self.d as u16 + operand as u16 + carry as u16;
+        self.d = sum as u8;
+        self.df = sum > 0xFF;
+    }
+
+    /** D = minuend - subtrahend, borrowing when `no_borrow` is false. DF is set if nothing was borrowed */
+    fn subtract(&mut self, minuend: u8, subtrahend: u8, no_borrow: bool) {
+        let difference = minuend as i16 - subtrahend as i16 - !no_borrow as i16;
+        self.d = difference as u8;
+        self.df = difference >= 0;
+    }
+
+    fn shift_right(&mut self, carry: bool) {
+        let out = self.d & 1 == 1;
+        self.d = self.d >> 1 | (carry as u8) << 7;
+        self.df = out;
+    }
+
+    fn shift_left(&mut self, carry: bool) {
+        let out = self.d & 0x80 != 0;
+        self.d = self.d << 1 | carry as u8;
+        self.df = out;
+    }
+}
+
+/**
+ * Runs the machine code routine at `addr` for 0NNN. V0 to VF and the display are copied into
+ * RAM where the VIP keeps them, and read back with I, the timers and the PC once the routine
+ * returns. Nothing changes if the routine fails
+ */
+pub(crate) fn call(chip8: &mut Chip8, addr: u16) -> Result<(), MachineCodeError> {
+    let mut ram = chip8.ram.clone();
+    ram[VARIABLES_ADDR..VARIABLES_ADDR + 16].copy_from_slice(&chip8.var_reg);
+    let rows = chip8.display.as_bitmask_rows();
+    for (y, row) in rows.iter().enumerate() {
+        ram[DISPLAY_ADDR + y * 8..DISPLAY_ADDR + y * 8 + 8].copy_from_slice(&row.to_be_bytes());
+    }
+
+    let mut cpu = Cdp1802::vip_call(chip8, addr);
+    cpu.run_until_return(&mut ram)?;
+
+    chip8
+        .var_reg
+        .copy_from_slice(&ram[VARIABLES_ADDR..VARIABLES_ADDR + 16]);
+    let mut drawn = [0; HEIGHT];
+    for (y, row) in drawn.iter_mut().enumerate() {
+        let start = DISPLAY_ADDR + y * 8;
+        *row = u64::from_be_bytes(ram[start..start + 8].try_into().unwrap());
+    }
+    if drawn != rows {
+        chip8.display.load_bitmask_rows(&drawn);
+        chip8.draw_flag = true;
+    }
+    chip8.ram = ram;
+    chip8.idx_reg = u32::from(cpu.r[0xA] & 0xFFF);
+    chip8.pc = cpu.r[5] & 0xFFF;
+    [chip8.delay_timer, chip8.sound_timer] = cpu.r[8].to_be_bytes();
+    Ok(())
+}
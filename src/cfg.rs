@@ -0,0 +1,337 @@
+use crate::predecode::Op;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+/** Where programs are loaded and start running */
+pub const START: u16 = 0x200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EdgeKind {
+    /** Falling through to the next instruction, or returning to it after a call */
+    Fallthrough,
+    Jump,
+    Call,
+    /** The taken side of a skip instruction */
+    Skip,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub target: u16,
+    pub kind: EdgeKind,
+}
+
+/** How control leaves a block */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exit {
+    /** Runs into the next block, which something else also branches to */
+    Fallthrough,
+    Jump,
+    Call,
+    Skip,
+    Return,
+    /** BNNN, whose target depends on V0 */
+    IndirectJump,
+    /** A jump to itself, the usual way for a program to end */
+    Halt,
+    /** An instruction the interpreter doesn't support, most likely data */
+    Invalid(u16),
+    /** Runs past the end of the ROM */
+    EndOfRom,
+}
+
+/** A run of instructions only ever entered at the top */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub start: u16,
+    /** Address just after the last instruction */
+    pub end: u16,
+    pub exit: Exit,
+    pub successors: Vec<Edge>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegionKind {
+    /** Never executed and pointed to by ANNN, or not decodable as code */
+    Data,
+    /** Never executed and never referenced: dead code, padding or data read in other ways */
+    Unreachable,
+}
+
+/** A run of ROM bytes that no reachable instruction covers */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub range: Range<u16>,
+    pub kind: RegionKind,
+}
+
+/**
+ * Control-flow graph of a ROM, built by decoding from 0x200 and following jumps, calls and
+ * skips. Subroutines are assumed to return to the instruction after their call.
+ * BNNN targets can't be known statically, so they are only recorded in `indirect_jumps`
+ */
+#[derive(Clone, Debug)]
+pub struct ControlFlowGraph {
+    pub blocks: BTreeMap<u16, Block>,
+    /** Targets of 2NNN */
+    pub subroutines: BTreeSet<u16>,
+    /** Addresses of BNNN instructions */
+    pub indirect_jumps: Vec<u16>,
+    /** Addresses of 0NNN machine code calls */
+    pub machine_calls: Vec<u16>,
+    /** Targets of ANNN in reachable code */
+    pub data_references: BTreeSet<u16>,
+    rom: Vec<u8>,
+    /** Start addresses of every reachable instruction */
+    instructions: BTreeSet<u16>,
+}
+impl ControlFlowGraph {
+    /** Analyses a ROM as loaded at 0x200 */
+    pub fn build(rom: &[u8]) -> ControlFlowGraph {
+        let mut graph = ControlFlowGraph {
+            blocks: BTreeMap::new(),
+            subroutines: BTreeSet::new(),
+            indirect_jumps: Vec::new(),
+            machine_calls: Vec::new(),
+            data_references: BTreeSet::new(),
+            rom: rom[..rom.len().min(4096 - START as usize)].to_vec(),
+            instructions: BTreeSet::new(),
+        };
+        let mut leaders = BTreeSet::from([START]);
+        let mut pending = vec![START];
+        while let Some(addr) = pending.pop() {
+            if graph.instructions.contains(&addr) {
+                continue;
+            }
+            let Some(op) = graph.decode(addr) else {
+                continue;
+            };
+            graph.instructions.insert(addr);
+            match op {
+                Op::SetIndex(nnn) => {
+                    graph.data_references.insert(nnn);
+                }
+                Op::Call(nnn) => {
+                    graph.subroutines.insert(nnn);
+                }
+                Op::JumpOffset(_) => graph.indirect_jumps.push(addr),
+                Op::MachineCall(_) => graph.machine_calls.push(addr),
+                _ => {}
+            }
+            let successors = match transfer(addr, op) {
+                Some((_, edges)) => {
+                    leaders.extend(edges.iter().map(|edge| edge.target));
+                    edges
+                }
+                None => vec![Edge {
+                    target: addr + 2,
+                    kind: EdgeKind::Fallthrough,
+                }],
+            };
+            pending.extend(successors.iter().map(|edge| edge.target));
+        }
+
+        for &leader in leaders
+            .iter()
+            .filter(|addr| graph.instructions.contains(addr))
+        {
+            let block = graph.block_from(leader, &leaders);
+            graph.blocks.insert(leader, block);
+        }
+        graph.indirect_jumps.sort_unstable();
+        graph.machine_calls.sort_unstable();
+        graph
+    }
+
+    /** Whether a reachable instruction covers this address */
+    pub fn is_code(&self, addr: u16) -> bool {
+        self.instructions.contains(&addr) || (addr > 0 && self.instructions.contains(&(addr - 1)))
+    }
+
+    /**
+     * Bytes of the ROM that no reachable instruction covers. Gaps are split where ANNN points
+     * into them; the parts from those points on are data
+     */
+    pub fn regions(&self) -> Vec<Region> {
+        let end = START + self.rom.len() as u16;
+        let mut regions = Vec::new();
+        let mut addr = START;
+        while addr < end {
+            if self.is_code(addr) {
+                addr += 1;
+                continue;
+            }
+            let gap_start = addr;
+            while addr < end && !self.is_code(addr) {
+                addr += 1;
+            }
+            let mut starts: Vec<u16> = self
+                .data_references
+                .range(gap_start..addr)
+                .copied()
+                .collect();
+            if starts.first() != Some(&gap_start) {
+                starts.insert(0, gap_start);
+            }
+            let ends = starts.iter().skip(1).copied().chain([addr]);
+            for (start, end) in starts.iter().copied().zip(ends) {
+                let referenced = self.data_references.contains(&start);
+                let kind = if referenced || !self.decodes_as_code(start..end) {
+                    RegionKind::Data
+                } else {
+                    RegionKind::Unreachable
+                };
+                regions.push(Region {
+                    range: start..end,
+                    kind,
+                });
+            }
+        }
+        regions
+    }
+
+    /** Renders the graph for Graphviz, one box per block listing its instructions */
+    pub fn to_dot(&self) -> String {
+        let mut dot =
+            String::from("digraph chip8 {\n    node [shape=box fontname=\"monospace\"];\n");
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for addr in (block.start..block.end).step_by(2) {
+                label.push_str(&format!("{addr:#05x}: {:04X}\\l", self.instruction(addr)));
+            }
+            label.push_str(&format!("{:?}\\l", block.exit));
+            let peripheries = if self.subroutines.contains(&block.start) {
+                2
+            } else {
+                1
+            };
+            dot.push_str(&format!(
+                "    \"{:#05x}\" [label=\"{label}\" peripheries={peripheries}];\n",
+                block.start
+            ));
+            for edge in &block.successors {
+                let style = match edge.kind {
+                    EdgeKind::Fallthrough => "solid",
+                    EdgeKind::Jump => "bold",
+                    EdgeKind::Call => "dashed",
+                    EdgeKind::Skip => "dotted",
+                };
+                dot.push_str(&format!(
+                    "    \"{:#05x}\" -> \"{:#05x}\" [style={style}];\n",
+                    block.start, edge.target
+                ));
+            }
+            if block.exit == Exit::IndirectJump {
+                dot.push_str(&format!(
+                    "    \"{:#05x}\" -> \"BNNN\" [style=dashed color=red];\n",
+                    block.start
+                ));
+            }
+        }
+        if !self.indirect_jumps.is_empty() {
+            dot.push_str("    \"BNNN\" [shape=diamond label=\"indirect\" color=red];\n");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /** The raw instruction at an address inside the ROM, such as the start of a block */
+    pub fn instruction(&self, addr: u16) -> u16 {
+        let offset = (addr - START) as usize;
+        u16::from_be_bytes([self.rom[offset], self.rom[offset + 1]])
+    }
+
+    /** Decodes the instruction at an address, if it lies entirely within the ROM */
+    fn decode(&self, addr: u16) -> Option<Op> {
+        let offset = addr.checked_sub(START)? as usize;
+        (offset + 1 < self.rom.len()).then(|| Op::decode(self.instruction(addr)))
+    }
+
+    /**
+     * Whether every aligned instruction in a range is one the interpreter runs. Machine calls
+     * don't count, so zero padding stays data
+     */
+    fn decodes_as_code(&self, range: Range<u16>) -> bool {
+        range.step_by(2).all(|addr| {
+            !matches!(
+                self.decode(addr),
+                Some(Op::Unsupported(_) | Op::MachineCall(_)) | None
+            )
+        })
+    }
+
+    /** Follows straight-line code from a leader until it branches or reaches another leader */
+    fn block_from(&self, start: u16, leaders: &BTreeSet<u16>) -> Block {
+        let mut addr = start;
+        loop {
+            let op = self
+                .decode(addr)
+                .expect("blocks only start on decoded instructions");
+            if let Some((exit, successors)) = transfer(addr, op) {
+                return Block {
+                    start,
+                    end: addr + 2,
+                    exit,
+                    successors,
+                };
+            }
+            let next = addr + 2;
+            if leaders.contains(&next) || !self.instructions.contains(&next) {
+                let (exit, successors) = match self.instructions.contains(&next) {
+                    true => (
+                        Exit::Fallthrough,
+                        vec![Edge {
+                            target: next,
+                            kind: EdgeKind::Fallthrough,
+                        }],
+                    ),
+                    false => (Exit::EndOfRom, Vec::new()),
+                };
+                return Block {
+                    start,
+                    end: next,
+                    exit,
+                    successors,
+                };
+            }
+            addr = next;
+        }
+    }
+}
+
+/** How an instruction transfers control, or `None` if it just runs into the next one */
+fn transfer(addr: u16, op: Op) -> Option<(Exit, Vec<Edge>)> {
+    let edge = |target: u16, kind| Edge { target, kind };
+    let skip = || {
+        Some((
+            Exit::Skip,
+            vec![
+                edge(addr + 2, EdgeKind::Fallthrough),
+                edge(addr + 4, EdgeKind::Skip),
+            ],
+        ))
+    };
+    match op {
+        Op::Return => Some((Exit::Return, Vec::new())),
+        Op::Jump(nnn) if nnn == addr => Some((Exit::Halt, Vec::new())),
+        Op::Jump(nnn) => Some((Exit::Jump, vec![edge(nnn, EdgeKind::Jump)])),
+        Op::Call(nnn) => Some((
+            Exit::Call,
+            vec![
+                edge(nnn, EdgeKind::Call),
+                edge(addr + 2, EdgeKind::Fallthrough),
+            ],
+        )),
+        Op::SkipEqImm(..)
+        | Op::SkipNeImm(..)
+        | Op::SkipEq(..)
+        | Op::SkipNe(..)
+        | Op::SkipKey(_)
+        | Op::SkipNotKey(_) => skip(),
+        Op::JumpOffset(_) => Some((Exit::IndirectJump, Vec::new())),
+        // 0NNN machine code routines return to the next instruction on the VIP
+        Op::MachineCall(_) => None,
+        Op::Unsupported(instruction) => Some((Exit::Invalid(instruction), Vec::new())),
+        _ => None,
+    }
+}
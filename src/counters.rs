@@ -0,0 +1,17 @@
+/** Execution statistics gathered when the `instrumentation` feature is enabled */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Counters {
+    /** Instructions executed, indexed by their first nibble, e.g. `instructions[0xD]` for DXYN */
+    pub instructions: [u64; 16],
+    /** DXYN executions */
+    pub draws: u64,
+    /** DXYN executions that turned off a lit pixel and set VF */
+    pub collisions: u64,
+    /** FX15 and FX18 executions */
+    pub timer_writes: u64,
+}
+impl Counters {
+    pub fn total_instructions(&self) -> u64 {
+        self.instructions.iter().sum()
+    }
+}
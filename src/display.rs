@@ -1,23 +1,324 @@
+use crate::megachip::{Framebuffer, MEGA_HEIGHT, MEGA_WIDTH};
+use crate::palette::{Palette, Rgb};
+use crate::platform::Platform;
+
 pub const WIDTH: usize = 64;
 pub const HEIGHT: usize = 32;
-const SCREEN_SIZE: usize = WIDTH * HEIGHT;
+/** Rows the tallest display, HiRes CHIP-8's two pages, has */
+pub const MAX_HEIGHT: usize = 64;
+
+/** The VP-590 colour board's colours, indexed by its red 1, blue 2 and green 4 bits */
+pub const VP590_COLORS: [Rgb; 8] = [
+    [0x00, 0x00, 0x00],
+    [0xFF, 0x00, 0x00],
+    [0x00, 0x00, 0xFF],
+    [0xFF, 0x00, 0xFF],
+    [0x00, 0xFF, 0x00],
+    [0xFF, 0xFF, 0x00],
+    [0x00, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xFF],
+];
+/** The background colours 02A0 steps through, starting from blue */
+pub const BACKGROUND_CYCLE: [u8; 4] = [2, 0, 4, 1];
+
+/**
+ * CHIP-8X colours: a foreground for every 8 pixel wide column of each row and one background.
+ * Colours are indices into `VP590_COLORS`
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColorMap {
+    pub foreground: [[u8; WIDTH / 8]; HEIGHT],
+    /** Position in `BACKGROUND_CYCLE` */
+    pub background: u8,
+}
+impl Default for ColorMap {
+    /** Red on blue, as the colour board starts */
+    fn default() -> Self {
+        ColorMap {
+            foreground: [[1; WIDTH / 8]; HEIGHT],
+            background: 0,
+        }
+    }
+}
+impl ColorMap {
+    /** Colours the columns `left..=right` of rows `top..=bottom`, clipped to the screen */
+    pub fn fill(&mut self, top: usize, bottom: usize, left: usize, right: usize, color: u8) {
+        for row in self.foreground.iter_mut().take(bottom + 1).skip(top) {
+            for column in row.iter_mut().take(right + 1).skip(left) {
+                *column = color & 0x7;
+            }
+        }
+    }
+
+    /** Moves the background on to the next colour, as 02A0 does */
+    pub fn cycle_background(&mut self) {
+        self.background = (self.background + 1) % BACKGROUND_CYCLE.len() as u8;
+    }
+
+    pub fn background_rgb(&self) -> Rgb {
+        VP590_COLORS[BACKGROUND_CYCLE[self.background as usize] as usize]
+    }
+
+    pub fn foreground_rgb(&self, y: usize, x: usize) -> Rgb {
+        VP590_COLORS[self.foreground[y % HEIGHT][x / 8] as usize]
+    }
+}
+
+/** Area of the display that changed, in pixels */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DirtyRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+impl DirtyRect {
+    /** Grows the rectangle to also cover the given pixel */
+    fn include(&mut self, y: usize, x: usize) {
+        let right = (self.x + self.width).max(x + 1);
+        let bottom = (self.y + self.height).max(y + 1);
+        self.x = self.x.min(x);
+        self.y = self.y.min(y);
+        self.width = right - self.x;
+        self.height = bottom - self.y;
+    }
+}
 
+#[derive(Clone)]
 pub struct Display {
-    buffer: [[bool; WIDTH]; HEIGHT],
+    buffer: [[bool; WIDTH]; MAX_HEIGHT],
+    /** Rows in use, 32 unless the platform has a taller display */
+    height: usize,
+    /** The CHIP-8X colour board, if fitted */
+    pub colors: Option<ColorMap>,
+    /** MEGA-CHIP's colour screen, shown instead of the buffer while it's enabled */
+    pub mega: Option<Framebuffer>,
+    /** Incremented every time the buffer changes */
+    frame_id: u64,
+    /** Union of everything changed since the dirty state was last cleared */
+    dirty_rect: Option<DirtyRect>,
+}
+impl Default for Display {
+    fn default() -> Self {
+        Display::new()
+    }
 }
 impl Display {
     pub fn new() -> Display {
         Display {
-            buffer: [[false; WIDTH]; HEIGHT],
+            buffer: [[false; WIDTH]; MAX_HEIGHT],
+            height: HEIGHT,
+            colors: None,
+            mega: None,
+            frame_id: 0,
+            dirty_rect: None,
+        }
+    }
+
+    /** A blank display with the platform's geometry and colour board */
+    pub fn for_platform(platform: Platform) -> Display {
+        Display {
+            height: platform.display_height(),
+            colors: platform.has_color().then(ColorMap::default),
+            mega: (platform == Platform::MegaChip).then(Framebuffer::default),
+            ..Display::new()
         }
     }
+
+    pub fn width(&self) -> usize {
+        WIDTH
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /** The MEGA-CHIP framebuffer, if the platform has one and 0011 has switched it on */
+    pub fn mega_mode(&self) -> Option<&Framebuffer> {
+        self.mega.as_ref().filter(|mega| mega.enabled)
+    }
+
+    /** Clears the screen. In MEGA mode the frame drawn so far is presented first, as 00E0 does */
     pub fn clear(&mut self) {
-        self.buffer = [[false; WIDTH]; HEIGHT];
+        match self.mega.as_mut().filter(|mega| mega.enabled) {
+            Some(mega) => mega.present(),
+            None => self.buffer = [[false; WIDTH]; MAX_HEIGHT],
+        }
+        self.frame_id += 1;
+        self.dirty_rect = Some(self.full_rect());
+    }
+
+    /** Switches MEGA-CHIP's colour screen on or off, blanking both screens */
+    pub fn set_mega_mode(&mut self, enabled: bool) {
+        let Some(mega) = self.mega.as_mut() else {
+            return;
+        };
+        mega.enabled = enabled;
+        mega.clear();
+        self.buffer = [[false; WIDTH]; MAX_HEIGHT];
+        self.frame_id += 1;
+        self.dirty_rect = Some(self.full_rect());
+    }
+
+    fn full_rect(&self) -> DirtyRect {
+        match self.mega_mode() {
+            Some(_) => DirtyRect {
+                x: 0,
+                y: 0,
+                width: MEGA_WIDTH,
+                height: MEGA_HEIGHT,
+            },
+            None => DirtyRect {
+                x: 0,
+                y: 0,
+                width: WIDTH,
+                height: self.height,
+            },
+        }
     }
     pub fn get_pixel(&self, y: usize, x: usize) -> bool {
         self.buffer[y][x]
     }
     pub fn flip_pixel(&mut self, y: usize, x: usize) {
         self.buffer[y][x] = !self.buffer[y][x];
+        self.frame_id += 1;
+        match self.dirty_rect.as_mut() {
+            Some(rect) => rect.include(y, x),
+            None => {
+                self.dirty_rect = Some(DirtyRect {
+                    x,
+                    y,
+                    width: 1,
+                    height: 1,
+                })
+            }
+        }
+    }
+
+    /** Changes whenever the display does, so consumers can cheaply tell if a redraw is needed */
+    pub fn frame_id(&self) -> u64 {
+        self.frame_id
+    }
+
+    /** Whether anything changed since `clear_dirty` was last called */
+    pub fn is_dirty(&self) -> bool {
+        self.dirty_rect.is_some()
+    }
+
+    /** Returns the area changed since `clear_dirty` was last called */
+    pub fn dirty_rect(&self) -> Option<DirtyRect> {
+        self.dirty_rect
+    }
+
+    /** Marks the display as presented */
+    pub fn clear_dirty(&mut self) {
+        self.dirty_rect = None;
+    }
+
+    /**
+     * Packs each row into a u64 with the leftmost pixel in the most significant bit. Only the
+     * first 32 rows, the top page of a HiRes display; `rows` has all of them
+     */
+    pub fn as_bitmask_rows(&self) -> [u64; HEIGHT] {
+        let mut rows = [0; HEIGHT];
+        rows.copy_from_slice(&self.rows()[..HEIGHT]);
+        rows
+    }
+
+    /** Replaces the first 32 rows with rows packed like `as_bitmask_rows` */
+    pub fn load_bitmask_rows(&mut self, rows: &[u64; HEIGHT]) {
+        let mut all = self.rows();
+        all[..HEIGHT].copy_from_slice(rows);
+        self.load_rows(&all);
+    }
+
+    /** Packs every row of the display like `as_bitmask_rows` */
+    pub fn rows(&self) -> Vec<u64> {
+        self.buffer[..self.height]
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .fold(0, |mask, &pixel| (mask << 1) | pixel as u64)
+            })
+            .collect()
+    }
+
+    /** Replaces the whole display with packed rows, taking its height from them */
+    pub fn load_rows(&mut self, rows: &[u64]) {
+        assert!(
+            matches!(rows.len(), HEIGHT | MAX_HEIGHT),
+            "Displays have {HEIGHT} or {MAX_HEIGHT} rows"
+        );
+        self.buffer = [[false; WIDTH]; MAX_HEIGHT];
+        for (row, mask) in self.buffer.iter_mut().zip(rows) {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = (mask >> (WIDTH - 1 - x)) & 1 == 1;
+            }
+        }
+        self.height = rows.len();
+        self.frame_id += 1;
+        self.dirty_rect = Some(self.full_rect());
+    }
+
+    /** The background and foreground a pixel is drawn with: the colour board's, if fitted */
+    pub fn palette_at(&self, y: usize, x: usize, palette: &Palette) -> Palette {
+        match &self.colors {
+            Some(colors) => {
+                let (background, foreground) =
+                    (colors.background_rgb(), colors.foreground_rgb(y, x));
+                Palette {
+                    colors: [background, foreground],
+                }
+            }
+            None => *palette,
+        }
+    }
+
+    /**
+     * Converts the display to 4 bytes per pixel using the palette's background and foreground.
+     * In MEGA mode this is the 256x192 presented frame in its own colours
+     */
+    pub fn to_rgba(&self, palette: &Palette) -> Vec<u8> {
+        if let Some(mega) = self.mega_mode() {
+            return mega.to_rgba();
+        }
+        let mut rgba = Vec::with_capacity(WIDTH * self.height * 4);
+        for (y, row) in self.buffer[..self.height].iter().enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                let colors = self.palette_at(y, x, palette);
+                let rgb = match pixel {
+                    true => colors.foreground(),
+                    false => colors.background(),
+                };
+                rgba.extend_from_slice(&[rgb[0], rgb[1], rgb[2], 0xFF]);
+            }
+        }
+        rgba
+    }
+
+    /**
+     * Renders the display as text, '#' for lit pixels and '.' for unlit, one line per row. In
+     * MEGA mode any pixel drawn in the presented frame counts as lit
+     */
+    pub fn to_ascii(&self) -> String {
+        if let Some(mega) = self.mega_mode() {
+            return mega
+                .frame
+                .chunks(MEGA_WIDTH)
+                .flat_map(|row| {
+                    row.iter()
+                        .map(|pixel| if pixel[3] != 0 { '#' } else { '.' })
+                        .chain(['\n'])
+                })
+                .collect();
+        }
+        let mut ascii = String::with_capacity((WIDTH + 1) * self.height);
+        for row in &self.buffer[..self.height] {
+            for &pixel in row {
+                ascii.push(if pixel { '#' } else { '.' });
+            }
+            ascii.push('\n');
+        }
+        ascii
     }
 }
@@ -0,0 +1,40 @@
+use crate::Chip8;
+use crate::timing::{self, Timing};
+use std::convert::Infallible;
+
+/** A strategy for executing instructions on a Chip8. Every engine must give identical results */
+pub trait Engine {
+    /** Executes a single instruction */
+    fn step(&mut self, chip8: &mut Chip8);
+
+    /** Runs a number of cycles followed by a single 60Hz timer tick, like `Chip8::run_frame` */
+    fn run_frame(&mut self, chip8: &mut Chip8, cycles_per_frame: usize) {
+        if chip8.timing == Timing::Vip {
+            return run_vip_frame(self, chip8);
+        }
+        for _ in 0..cycles_per_frame {
+            self.step(chip8);
+        }
+        chip8.decrement_timers(1);
+    }
+
+    /** Drops anything cached about memory. Needed after writing to RAM from outside the engine */
+    fn invalidate_all(&mut self) {}
+}
+
+/** The plain fetch, decode and execute path of `Chip8::cycle` */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Interpreter;
+impl Engine for Interpreter {
+    fn step(&mut self, chip8: &mut Chip8) {
+        chip8.cycle();
+    }
+}
+
+/** Runs a frame of `Timing::Vip` one `step` at a time, for engines that override `run_frame` */
+pub(crate) fn run_vip_frame<E: Engine + ?Sized>(engine: &mut E, chip8: &mut Chip8) {
+    let Ok(()) = timing::run_vip_frame(chip8, |chip8| {
+        engine.step(chip8);
+        Ok::<(), Infallible>(())
+    });
+}
@@ -0,0 +1,220 @@
+use crate::Chip8;
+use crate::display::HEIGHT;
+use crate::quirks::Quirks;
+use crate::rom::ROM;
+
+/** Bytes of memory after 0x200, where ROMs are loaded */
+const MAX_ROM_SIZE: usize = 4096 - 0x200;
+
+/** The display as one u64 per row, leftmost pixel in the most significant bit */
+pub type Observation = [u64; HEIGHT];
+
+/** Turns game-specific machine state into rewards for an agent */
+pub trait RewardExtractor: Send {
+    /** Called after every emulated frame of a step, returns the reward earned during that frame */
+    fn reward(&mut self, chip8: &Chip8) -> f32;
+
+    /** Whether the game is over, e.g. because the player ran out of lives */
+    fn is_done(&self, _chip8: &Chip8) -> bool {
+        false
+    }
+
+    /** Called once the ROM has been loaded on reset, to capture starting values */
+    fn reset(&mut self, _chip8: &Chip8) {}
+
+    fn box_clone(&self) -> Box<dyn RewardExtractor>;
+}
+
+impl Clone for Box<dyn RewardExtractor> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/** How a score is stored in memory */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScoreEncoding {
+    /** A plain binary number spread over big-endian bytes */
+    Binary,
+    /** One decimal digit per byte, as written by FX33 */
+    Bcd,
+}
+
+/** Rewards the increase of a score kept in RAM, e.g. the player's score in Pong */
+#[derive(Clone, Debug, PartialEq)]
+pub struct RamScore {
+    address: u16,
+    len: usize,
+    encoding: ScoreEncoding,
+    /** Ends the episode once the score reaches this value */
+    pub target: Option<u32>,
+    last_score: u32,
+}
+impl RamScore {
+    /** Fails unless the score's `len` bytes at `address` lie within the first 4KB of memory */
+    pub fn new(address: u16, len: usize, encoding: ScoreEncoding) -> Result<RamScore, String> {
+        let end = address as usize + len;
+        if len == 0 || end > 4096 {
+            return Err(format!(
+                "Scores must fit in memory, not {len} bytes at {address:#05x}"
+            ));
+        }
+        Ok(RamScore {
+            address,
+            len,
+            encoding,
+            target: None,
+            last_score: 0,
+        })
+    }
+
+    /** Reads the score currently stored in memory, saturating at `u32::MAX` */
+    pub fn score(&self, chip8: &Chip8) -> u32 {
+        let start = self.address as usize;
+        let bytes = &chip8.ram[start..start + self.len];
+        let base = match self.encoding {
+            ScoreEncoding::Binary => 256,
+            ScoreEncoding::Bcd => 10,
+        };
+        bytes.iter().fold(0u32, |acc, &byte| {
+            acc.saturating_mul(base).saturating_add(byte as u32)
+        })
+    }
+}
+impl RewardExtractor for RamScore {
+    fn reward(&mut self, chip8: &Chip8) -> f32 {
+        let score = self.score(chip8);
+        let reward = score as f32 - self.last_score as f32;
+        self.last_score = score;
+        reward
+    }
+
+    fn is_done(&self, chip8: &Chip8) -> bool {
+        self.target
+            .is_some_and(|target| self.score(chip8) >= target)
+    }
+
+    fn reset(&mut self, chip8: &Chip8) {
+        self.last_score = self.score(chip8);
+    }
+
+    fn box_clone(&self) -> Box<dyn RewardExtractor> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvConfig {
+    /** Instructions executed per 60Hz frame */
+    pub cycles_per_frame: usize,
+    /** Frames emulated per step, with the same action held down for all of them */
+    pub frame_skip: usize,
+    /** Ends the episode after this many steps */
+    pub max_steps: Option<usize>,
+    pub quirks: Quirks,
+}
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            cycles_per_frame: 10,
+            frame_skip: 4,
+            max_steps: None,
+            quirks: Quirks::default(),
+        }
+    }
+}
+
+/**
+ * Reinforcement learning style wrapper around a Chip8 running a single ROM.
+ * Cloning an environment clones the whole machine, so rollouts can branch from any state.
+ */
+#[derive(Clone)]
+pub struct Chip8Env {
+    pub config: EnvConfig,
+    rom: Vec<u8>,
+    chip8: Chip8,
+    reward: Box<dyn RewardExtractor>,
+    steps: usize,
+}
+impl Chip8Env {
+    /** Fails if the ROM doesn't fit in memory after 0x200 */
+    pub fn new(
+        rom: &[u8],
+        config: EnvConfig,
+        reward: Box<dyn RewardExtractor>,
+    ) -> Result<Chip8Env, String> {
+        if rom.len() > MAX_ROM_SIZE {
+            return Err(format!(
+                "ROMs are at most {MAX_ROM_SIZE} bytes, not {}",
+                rom.len()
+            ));
+        }
+        let mut env = Chip8Env {
+            config,
+            rom: rom.to_vec(),
+            chip8: Chip8::with_seed(0),
+            reward,
+            steps: 0,
+        };
+        env.reset(0);
+        Ok(env)
+    }
+
+    /** Restarts the ROM on a fresh machine and returns the first observation */
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.chip8 = Chip8::with_seed(seed);
+        self.chip8.quirks = self.config.quirks;
+        self.chip8.load_rom(ROM::from_bytes(&self.rom));
+        self.reward.reset(&self.chip8);
+        self.steps = 0;
+        self.observation()
+    }
+
+    /**
+     * Holds down the action's key (or none) for `frame_skip` frames. A ROM that hits an
+     * unsupported opcode or a bad return ends the episode
+     */
+    pub fn step(&mut self, action: Option<u8>) -> (Observation, f32, bool) {
+        let mut total_reward = 0.0;
+        let mut done = false;
+        for _ in 0..self.config.frame_skip.max(1) {
+            self.chip8.set_input_key(action);
+            if self
+                .chip8
+                .try_run_frame(self.config.cycles_per_frame)
+                .is_err()
+            {
+                done = true;
+                break;
+            }
+            total_reward += self.reward.reward(&self.chip8);
+            if self.reward.is_done(&self.chip8) {
+                done = true;
+                break;
+            }
+        }
+
+        self.steps += 1;
+        if self
+            .config
+            .max_steps
+            .is_some_and(|max_steps| self.steps >= max_steps)
+        {
+            done = true;
+        }
+        (self.observation(), total_reward, done)
+    }
+
+    pub fn observation(&self) -> Observation {
+        self.chip8.display.as_bitmask_rows()
+    }
+
+    /** Number of steps taken since the last reset */
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn chip8(&self) -> &Chip8 {
+        &self.chip8
+    }
+}
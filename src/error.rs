@@ -0,0 +1,36 @@
+use crate::cdp1802::MachineCodeError;
+use std::fmt;
+
+/** Ways a program can fail that would otherwise panic in `Chip8::cycle` */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8Error {
+    /** The instruction at the PC isn't implemented */
+    UnsupportedOpcode(u16),
+    /** 00EE with nothing on the call stack */
+    StackUnderflow,
+    /** 2NNN with the call stack already as deep as the quirks allow */
+    StackOverflow,
+    /** The PC points past the end of memory */
+    PcOutOfBounds(u16),
+    /** The instruction would access memory past the end of RAM, starting from I */
+    MemoryOutOfBounds(u32),
+    /** A 0NNN machine code routine hit an instruction the 1802 emulator can't run */
+    MachineCode(MachineCodeError),
+}
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Error::UnsupportedOpcode(instruction) => {
+                write!(f, "Unsupported opcode: {instruction:#06x}")
+            }
+            Chip8Error::StackUnderflow => write!(f, "Returned with an empty call stack"),
+            Chip8Error::StackOverflow => write!(f, "Called with a full call stack"),
+            Chip8Error::PcOutOfBounds(pc) => write!(f, "PC out of bounds: {pc:#06x}"),
+            Chip8Error::MemoryOutOfBounds(idx) => {
+                write!(f, "Memory access out of bounds from I = {idx:#06x}")
+            }
+            Chip8Error::MachineCode(error) => write!(f, "Machine code failed: {error}"),
+        }
+    }
+}
+impl std::error::Error for Chip8Error {}
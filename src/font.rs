@@ -0,0 +1,229 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/** Bytes in a small font: 16 hexadecimal glyphs, 5 rows of 4 pixels each */
+pub const SMALL_FONT_LEN: usize = 80;
+/** Bytes in the big glyph for one digit: 10 rows of 8 pixels */
+pub const BIG_GLYPH_LEN: usize = 10;
+
+/** The CHIP-48 font, which SUPER-CHIP and Octo kept. Loaded unless another is chosen */
+const CHIP48_SMALL: [u8; SMALL_FONT_LEN] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+/** The glyphs in the COSMAC VIP's interpreter ROM */
+const VIP_SMALL: [u8; SMALL_FONT_LEN] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x60, 0x20, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+/** The DREAM 6800's glyphs, 3 pixels wide */
+const DREAM6800_SMALL: [u8; SMALL_FONT_LEN] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+    0x40, 0x40, 0x40, 0x40, 0x40, // 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+    0x80, 0xA0, 0xA0, 0xE0, 0x20, // 4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+    0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+];
+
+/** The ETI-660's glyphs: the DREAM 6800's with a different 1 and 4 */
+const ETI660_SMALL: [u8; SMALL_FONT_LEN] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+    0x20, 0x20, 0x20, 0x20, 0x20, // 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+    0xA0, 0xA0, 0xE0, 0x20, 0x20, // 4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+    0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+];
+
+/** SUPER-CHIP 1.1's big digits. It has no big letters */
+const SCHIP_BIG: [u8; 10 * BIG_GLYPH_LEN] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+];
+
+/** Octo's big glyphs, which cover all 16 hexadecimal digits */
+const OCTO_BIG: [u8; 16 * BIG_GLYPH_LEN] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+/** The font of one of the interpreters ROMs were written for, which some rely on the shapes of */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FontSet {
+    Vip,
+    Dream6800,
+    Eti660,
+    /** SUPER-CHIP's small font and its big digits */
+    Schip,
+    /** Octo's small font and its big hexadecimal digits */
+    Octo,
+}
+impl FontSet {
+    pub const ALL: [FontSet; 5] = [
+        FontSet::Vip,
+        FontSet::Dream6800,
+        FontSet::Eti660,
+        FontSet::Schip,
+        FontSet::Octo,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FontSet::Vip => "vip",
+            FontSet::Dream6800 => "dream6800",
+            FontSet::Eti660 => "eti660",
+            FontSet::Schip => "schip",
+            FontSet::Octo => "octo",
+        }
+    }
+
+    /** Looks a font set up by the name returned from `FontSet::name` */
+    pub fn from_name(name: &str) -> Option<FontSet> {
+        FontSet::ALL
+            .into_iter()
+            .find(|set| set.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn font(&self) -> Font {
+        let (small, big): (_, &[u8]) = match self {
+            FontSet::Vip => (VIP_SMALL, &[]),
+            FontSet::Dream6800 => (DREAM6800_SMALL, &[]),
+            FontSet::Eti660 => (ETI660_SMALL, &[]),
+            FontSet::Schip => (CHIP48_SMALL, &SCHIP_BIG),
+            FontSet::Octo => (CHIP48_SMALL, &OCTO_BIG),
+        };
+        Font {
+            small,
+            big: big.to_vec(),
+        }
+    }
+}
+
+/**
+ * Glyphs for FX29 and FX30. `Chip8::load_font` puts the small glyphs at the font address and
+ * the big ones straight after them
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Font {
+    /** 5 bytes for each hexadecimal digit, read by FX29 */
+    pub small: [u8; SMALL_FONT_LEN],
+    /** 10 bytes for each digit FX30 can draw: 0 to 9, or 0 to F. Empty without a big font */
+    pub big: Vec<u8>,
+}
+impl Default for Font {
+    fn default() -> Self {
+        Font {
+            small: CHIP48_SMALL,
+            big: Vec::new(),
+        }
+    }
+}
+impl Font {
+    /**
+     * Reads a font file: the 80 bytes of a small font, optionally followed by the 100 bytes of
+     * big digits or the 160 bytes of big hexadecimal digits
+     */
+    pub fn from_bytes(bytes: &[u8]) -> Result<Font, String> {
+        if !matches!(bytes.len(), 80 | 180 | 240) {
+            return Err(format!(
+                "Font files are 80, 180 or 240 bytes, not {}",
+                bytes.len()
+            ));
+        }
+        let (small, big) = bytes.split_at(SMALL_FONT_LEN);
+        Ok(Font {
+            small: small.try_into().unwrap(),
+            big: big.to_vec(),
+        })
+    }
+
+    pub fn load(path: &Path) -> io::Result<Font> {
+        let bytes = fs::read(path)?;
+        Font::from_bytes(&bytes).map_err(io::Error::other)
+    }
+
+    /** Bytes the font takes up in memory */
+    pub fn size(&self) -> usize {
+        SMALL_FONT_LEN + self.big.len()
+    }
+
+    /** The small glyph of a hexadecimal digit */
+    pub fn glyph(&self, digit: u8) -> &[u8] {
+        let start = (digit & 0xF) as usize * 5;
+        &self.small[start..start + 5]
+    }
+}
@@ -1,34 +1,53 @@
+#[cfg(feature = "instrumentation")]
+use crate::counters::Counters;
 use crate::display::Display;
+use crate::error::Chip8Error;
+use crate::font::{BIG_GLYPH_LEN, Font, SMALL_FONT_LEN};
+use crate::megachip::{BlendMode, DigitalSound, MEGA_HEIGHT, MEGA_SPRITES_START};
 use crate::opcode::Opcode;
+use crate::platform::{HIRES_START, Platform};
+use crate::predecode::Op;
+use crate::quirks::Quirks;
+use crate::rng::{Generator, RandomSource, Xorshift};
 use crate::rom::ROM;
+use crate::state::Snapshot;
+use crate::timing::{Timing, VipClock};
+use std::convert::Infallible;
+use std::ops::Range;
 
 pub mod rom;
 
+pub mod batch;
+pub mod blocks;
+pub mod capture;
+pub mod cdp1802;
+pub mod cfg;
+#[cfg(feature = "instrumentation")]
+pub mod counters;
 pub mod display;
+pub mod engine;
+pub mod env;
+pub mod error;
+pub mod font;
+pub mod lint;
+pub mod megachip;
+pub mod memview;
+pub mod movie;
 mod opcode;
+pub mod palette;
+pub mod platform;
+pub mod predecode;
+pub mod quirks;
+pub mod renderer;
+pub mod rng;
+pub mod sprites;
+pub mod state;
+pub mod timing;
 
 pub const WIDTH: usize = 64;
 pub const HEIGHT: usize = 32;
-const SCREEN_SIZE: usize = WIDTH * HEIGHT;
-
-const FONT: [u8; 80] = [
-    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-    0x20, 0x60, 0x20, 0x20, 0x70, // 1
-    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-];
+/** Where the font is loaded unless `Chip8::load_font` puts it elsewhere */
+pub const FONT_START: usize = 0x50;
 
 pub enum CurrentKey {
     LEFT,
@@ -37,29 +56,64 @@ pub enum CurrentKey {
     DOWN,
 }
 
+#[derive(Clone)]
 pub struct Chip8 {
-    pub ram: [u8; 4096],
+    /** 4KB, except on MEGA-CHIP where it grows to hold the whole ROM */
+    pub ram: Vec<u8>,
     pub display: Display,
     /** Program counter that points to the current instruction in memory */
     pub pc: u16,
-    /** Index register that points to a specific location in memory */
-    pub idx_reg: u16,
+    /** Index register that points to a specific location in memory. 24 bits on MEGA-CHIP */
+    pub idx_reg: u32,
     /** Variable registers - 0xF is used as a flag register */
     pub var_reg: [u8; 16],
     pub call_stack: Vec<u16>,
     pub delay_timer: u8,
     pub sound_timer: u8,
     pub curr_input_key: Option<u8>,
+    /** Set whenever 00E0 or DXYN changes the display. Cleared by the frontend once presented */
+    pub draw_flag: bool,
+    /** Source of random numbers for CXNN */
+    pub rng: Box<dyn RandomSource>,
+    pub quirks: Quirks,
+    /** How `run_frame` decides how many instructions make up a frame */
+    pub timing: Timing,
+    /** Cycle accounting carried between frames in `Timing::Vip` */
+    pub vip_clock: VipClock,
+    /** Which interpreter's opcodes, load address and display to emulate. Set with `set_platform` */
+    pub platform: Platform,
+    /** Last byte written to the I/O port by CHIP-8X FXF8 or CHIP-8E FX03 */
+    pub port_out: u8,
+    /** What CHIP-8X FXFB and CHIP-8E FXE3/FXE7 read from the I/O port */
+    pub port_in: u8,
+    /** MEGA-CHIP digitised sound started by 060N, until 0700 stops it */
+    pub digital_sound: Option<DigitalSound>,
+    /** Where FX29 finds the small glyphs, with FX30's big glyphs straight after them */
+    pub font_addr: u16,
+    /** Bytes of the font loaded at `font_addr` */
+    font_size: usize,
+    /** CHIP-8E FX4F has set the delay timer and is waiting for it to run out */
+    waiting_for_delay: bool,
+    #[cfg(feature = "instrumentation")]
+    counters: Counters,
 }
+#[cfg(not(target_arch = "wasm32"))]
 impl Default for Chip8 {
     fn default() -> Self {
         Chip8::new()
     }
 }
 impl Chip8 {
+    /** Creates a Chip8 with a randomly seeded RNG. On wasm use `with_seed` instead */
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn new() -> Chip8 {
+        Chip8::with_seed(rand::random::<u64>())
+    }
+
+    /** Creates a Chip8 whose CXNN results are reproducible for a given seed */
+    pub fn with_seed(seed: u64) -> Chip8 {
         let mut chip = Chip8 {
-            ram: [0; 4096],
+            ram: vec![0; 4096],
             display: Display::new(),
             pc: 0x200,
             idx_reg: 0,
@@ -68,20 +122,187 @@ impl Chip8 {
             delay_timer: 0,
             sound_timer: 0,
             curr_input_key: None,
+            draw_flag: false,
+            rng: Box::new(Xorshift::new(seed)),
+            quirks: Quirks::default(),
+            timing: Timing::default(),
+            vip_clock: VipClock::default(),
+            platform: Platform::default(),
+            port_out: 0,
+            port_in: 0,
+            digital_sound: None,
+            font_addr: FONT_START as u16,
+            font_size: 0,
+            waiting_for_delay: false,
+            #[cfg(feature = "instrumentation")]
+            counters: Counters::default(),
         };
 
-        for i in 0..FONT.len() {
-            chip.ram[0x50 + i] = FONT[i];
-        }
+        chip.load_font(&Font::default(), FONT_START as u16).unwrap();
 
         chip
     }
+
+    /**
+     * Replaces the font with another at an address, wiping the one loaded before. Fails if the
+     * font would run past the first 4KB, which every platform keeps
+     */
+    pub fn load_font(&mut self, font: &Font, addr: u16) -> Result<(), String> {
+        let start = addr as usize;
+        if start + font.size() > 4096 {
+            return Err(format!(
+                "A {} byte font doesn't fit at {addr:#05x}",
+                font.size()
+            ));
+        }
+        let old = self.font_region();
+        self.ram[old].fill(0);
+        self.ram[start..start + SMALL_FONT_LEN].copy_from_slice(&font.small);
+        self.ram[start + SMALL_FONT_LEN..start + font.size()].copy_from_slice(&font.big);
+        self.font_addr = addr;
+        self.font_size = font.size();
+        Ok(())
+    }
+
+    /** The memory the loaded font takes up */
+    pub fn font_region(&self) -> Range<usize> {
+        self.font_addr as usize..self.font_addr as usize + self.font_size
+    }
+
+    /** Whether the loaded font has a big glyph for FX30 to point at: 0 to 9, or 0 to F */
+    pub(crate) fn has_big_glyph(&self, digit: u8) -> bool {
+        (digit as usize) < self.font_size.saturating_sub(SMALL_FONT_LEN) / BIG_GLYPH_LEN
+    }
+    /**
+     * Loads a ROM at the platform's load address and starts running it from there. On
+     * MEGA-CHIP memory grows to fit the ROM; elsewhere anything past 4KB is dropped
+     */
     pub fn load_rom(&mut self, rom: ROM) {
-        self.load_memory(0x200, &rom.data);
+        let start = self.platform.load_address() as usize;
+        let end = (start + rom.data.len()).min(self.platform.memory_size());
+        if end > self.ram.len() {
+            self.ram.resize(end, 0);
+        }
+        let room = self.ram.len() - start;
+        self.load_memory(start as u16, &rom.data[..room.min(rom.data.len())]);
+        self.pc = start as u16;
+    }
+
+    /**
+     * Switches to another platform's opcodes and display, clearing the screen and moving the
+     * PC to its load address. Engines caching decoded ops must be invalidated afterwards
+     */
+    pub fn set_platform(&mut self, platform: Platform) {
+        self.platform = platform;
+        self.ram.resize(4096, 0);
+        self.display = Display::for_platform(platform);
+        self.digital_sound = None;
+        self.pc = platform.load_address();
+        self.draw_flag = true;
+    }
+
+    /** Reseeds the current random source */
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng.set_state(seed);
+    }
+
+    /** Switches CXNN to a built-in generator, seeded with the current source's state */
+    pub fn set_generator(&mut self, generator: Generator) {
+        self.rng = generator.source(self.rng.state());
+    }
+
+    /** Replaces the random source with a custom generator */
+    pub fn set_random_source(&mut self, rng: Box<dyn RandomSource>) {
+        self.rng = rng;
+    }
+
+    /** Captures the complete machine state */
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            ram: self.ram.clone(),
+            display: self.display.rows(),
+            colors: self.display.colors,
+            mega: self.display.mega.clone(),
+            pc: self.pc,
+            idx_reg: self.idx_reg,
+            var_reg: self.var_reg,
+            call_stack: self.call_stack.clone(),
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            curr_input_key: self.curr_input_key,
+            rng_state: self.rng.state(),
+            rng_generator: self.rng.generator(),
+            digital_sound: self.digital_sound,
+            platform: self.platform,
+            timing: self.timing,
+            vip_clock: self.vip_clock,
+            font_addr: self.font_addr,
+            font_size: self.font_size,
+            waiting_for_delay: self.waiting_for_delay,
+            port_in: self.port_in,
+            port_out: self.port_out,
+        }
+    }
+
+    /**
+     * Puts the machine back into a previously captured state, including its platform. Engines
+     * caching decoded ops must be invalidated afterwards
+     */
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.platform = snapshot.platform;
+        self.ram = snapshot.ram.clone();
+        self.display.load_rows(&snapshot.display);
+        self.display.colors = snapshot.colors;
+        self.display.mega = snapshot.mega.clone();
+        self.pc = snapshot.pc;
+        self.idx_reg = snapshot.idx_reg;
+        self.var_reg = snapshot.var_reg;
+        self.call_stack = snapshot.call_stack.clone();
+        self.delay_timer = snapshot.delay_timer;
+        self.sound_timer = snapshot.sound_timer;
+        self.curr_input_key = snapshot.curr_input_key;
+        if let Some(generator) = snapshot.rng_generator
+            && self.rng.generator() != Some(generator)
+        {
+            self.rng = generator.source(snapshot.rng_state);
+        }
+        self.rng.set_state(snapshot.rng_state);
+        self.digital_sound = snapshot.digital_sound;
+        self.timing = snapshot.timing;
+        self.vip_clock = snapshot.vip_clock;
+        self.font_addr = snapshot.font_addr;
+        self.font_size = snapshot.font_size;
+        self.waiting_for_delay = snapshot.waiting_for_delay;
+        self.port_in = snapshot.port_in;
+        self.port_out = snapshot.port_out;
+        self.draw_flag = true;
+    }
+
+    /** Execution statistics since creation or the last `reset_counters` */
+    #[cfg(feature = "instrumentation")]
+    pub fn counters(&self) -> &Counters {
+        &self.counters
+    }
+
+    #[cfg(feature = "instrumentation")]
+    pub fn reset_counters(&mut self) {
+        self.counters = Counters::default();
+    }
+
+    /** Counts the instruction at the PC, which is about to run. Does nothing without instrumentation */
+    #[inline(always)]
+    pub(crate) fn count_instruction(&mut self) {
+        #[cfg(feature = "instrumentation")]
+        {
+            let class = self.ram[self.pc as usize] >> 4;
+            self.counters.instructions[class as usize] += 1;
+        }
     }
 
     /** Performs a single fetch, decode, and execute cycle */
     pub fn cycle(&mut self) {
+        self.count_instruction();
+
         // fetch instruction
         let instruction: u16 = self.fetch_instruction();
 
@@ -90,9 +311,13 @@ impl Chip8 {
         let nibbles: (u8, u8, u8, u8) = (opcode.w, opcode.x, opcode.y, opcode.n);
 
         // execute instruction
+        if self.platform != Platform::Chip8 && self.platform_opcode(&opcode) {
+            return;
+        }
         match nibbles {
             (0x0, 0x0, 0xE, 0x0) => self.op_00e0(),
             (0x0, 0x0, 0xE, 0xE) => self.op_00ee(),
+            (0x0, _, _, _) => self.op_0nnn(opcode.nnn),
             (0x1, _, _, _) => self.op_1nnn(opcode.nnn),
             (0x2, _, _, _) => self.op_2nnn(opcode.nnn),
             (0x3, _, _, _) => self.op_3xnn(opcode.x as usize, opcode.nn),
@@ -100,7 +325,7 @@ impl Chip8 {
             (0x5, _, _, _) => self.op_5xnn(opcode.x as usize, opcode.y as usize),
             (0x6, _, _, _) => self.op_6xnn(opcode.x as usize, opcode.nn),
             (0x7, _, _, _) => self.op_7xnn(opcode.x as usize, opcode.nn),
-            /** ALU Instructions */
+            // ALU Instructions
             (0x8, _, _, _) => match (nibbles.1, nibbles.2, nibbles.3) {
                 (_, _, 0x0) => self.op_8xy0(opcode.x as usize, opcode.y as usize),
                 (_, _, 0x1) => self.op_8xy1(opcode.x as usize, opcode.y as usize),
@@ -125,13 +350,15 @@ impl Chip8 {
             (0xD, _, _, _) => self.op_dxyn(opcode.x as usize, opcode.y as usize, opcode.n),
             (0xE, _, 0x9, 0xE) => self.op_ex9e(opcode.x as usize),
             (0xE, _, 0xA, 0x1) => self.op_exa1(opcode.x as usize),
-            /** Timers */
+            // Timers
             (0xF, _, _, _) => match (nibbles.1, nibbles.2, nibbles.3) {
                 (_, 0x0, 0x7) => self.op_fx07(opcode.x as usize),
                 (_, 0x0, 0xA) => self.op_fx0a(opcode.x as usize),
                 (_, 0x1, 0x5) => self.op_fx15(opcode.x as usize),
                 (_, 0x1, 0x8) => self.op_fx18(opcode.x as usize),
                 (_, 0x1, 0xE) => self.op_fx1e(opcode.x as usize),
+                (_, 0x2, 0x9) => self.op_fx29(opcode.x as usize),
+                (_, 0x3, 0x0) => self.op_fx30(opcode.x as usize),
                 (_, 0x3, 0x3) => self.op_fx33(opcode.x as usize),
                 (_, 0x5, 0x5) => self.op_fx55(opcode.x as usize),
                 (_, 0x6, 0x5) => self.op_fx65(opcode.x as usize),
@@ -151,11 +378,63 @@ impl Chip8 {
         }
     }
 
+    /**
+     * Runs a number of cycles followed by a single 60Hz timer tick. With `Timing::Vip` the
+     * frame is as long as on the COSMAC VIP instead
+     */
+    pub fn run_frame(&mut self, cycles_per_frame: usize) {
+        if self.timing == Timing::Vip {
+            let Ok(()) = timing::run_vip_frame(self, |chip8| {
+                chip8.cycle();
+                Ok::<(), Infallible>(())
+            });
+            return;
+        }
+        for _ in 0..cycles_per_frame {
+            self.cycle();
+        }
+        self.decrement_timers(1);
+    }
+
+    /** Like `cycle`, but reports an instruction that would panic as an error instead of running it */
+    pub fn try_cycle(&mut self) -> Result<(), Chip8Error> {
+        let pc = self.pc as usize;
+        if pc + 1 >= self.ram.len() {
+            return Err(Chip8Error::PcOutOfBounds(self.pc));
+        }
+        let instruction = (self.ram[pc] as u16) << 8 | self.ram[pc + 1] as u16;
+        let op = Op::decode_for(instruction, self.platform);
+        op.check(self)?;
+
+        self.count_instruction();
+        self.pc += 2;
+        if let Op::MachineCall(nnn) = op {
+            // the routine runs on a copy of the machine, so a failed call changes nothing
+            return cdp1802::call(self, nnn).map_err(|error| {
+                self.pc -= 2;
+                Chip8Error::MachineCode(error)
+            });
+        }
+        op.execute(self);
+        Ok(())
+    }
+
+    /** Like `run_frame`, but stops at the first error without ticking the timers */
+    pub fn try_run_frame(&mut self, cycles_per_frame: usize) -> Result<(), Chip8Error> {
+        if self.timing == Timing::Vip {
+            return timing::run_vip_frame(self, Chip8::try_cycle);
+        }
+        for _ in 0..cycles_per_frame {
+            self.try_cycle()?;
+        }
+        self.decrement_timers(1);
+        Ok(())
+    }
+
     /** Loads a block of data into memory starting at the given position */
     pub fn load_memory(&mut self, start_pos: u16, load: &[u8]) {
-        for i in 0..load.len() {
-            self.ram[start_pos as usize + i] = load[i];
-        }
+        let start = start_pos as usize;
+        self.ram[start..start + load.len()].copy_from_slice(load);
     }
 
     pub fn set_input_key(&mut self, input_key: Option<u8>) {
@@ -184,18 +463,297 @@ impl Chip8 {
     /** Clear screen */
     fn op_00e0(&mut self) {
         self.display.clear();
+        self.draw_flag = true;
     }
 
+    /** Return - Pops the PC from the stack, or from RAM when the stack lives there */
     fn op_00ee(&mut self) {
-        self.pc = self.call_stack.pop().unwrap();
+        let addr = self
+            .call_stack
+            .pop()
+            .expect("Returned with an empty call stack");
+        self.pc = match self.quirks.stack_in_ram {
+            true => {
+                let slot = stack_slot(self.call_stack.len());
+                u16::from_be_bytes([self.ram[slot], self.ram[slot + 1]])
+            }
+            false => addr,
+        };
+    }
+
+    /**
+     * Machine code - Runs the 1802 routine at NNN when the quirks allow it. Panics if they
+     * don't or the routine fails
+     */
+    fn op_0nnn(&mut self, nnn: u16) {
+        if !self.quirks.machine_code {
+            panic!("Unsupported opcode: {nnn:#06x}");
+        }
+        if let Err(error) = cdp1802::call(self, nnn) {
+            panic!("Machine code failed: {error}");
+        }
+    }
+
+    /** Runs an opcode the platform adds or redefines, returning false for any other */
+    fn platform_opcode(&mut self, opcode: &Opcode) -> bool {
+        let (x, y, n) = (opcode.x as usize, opcode.y as usize, opcode.n);
+        match (self.platform, opcode.w, opcode.x, opcode.y, opcode.n) {
+            (Platform::Chip8X, 0x0, 0x2, 0xA, 0x0) => self.op_02a0(),
+            (Platform::Chip8X, 0x5, _, _, 0x1) => self.op_5xy1_add_nibbles(x, y),
+            (Platform::Chip8X, 0xB, _, _, _) => self.op_bxyn(x, y, n),
+            // nothing is plugged into the second keypad
+            (Platform::Chip8X, 0xE, _, 0xF, 0x2) => {}
+            (Platform::Chip8X, 0xE, _, 0xF, 0x5) => self.pc += 2,
+            (Platform::Chip8X, 0xF, _, 0xF, 0x8) => self.port_out = self.var_reg[x],
+            (Platform::Chip8X, 0xF, _, 0xF, 0xB) => self.var_reg[x] = self.port_in,
+            (Platform::Chip8E, 0x0, 0x0, 0xE, 0xD) => self.pc -= 2,
+            (Platform::Chip8E, 0x0, 0x0, 0xF, 0x2) => {}
+            (Platform::Chip8E, 0x0, 0x1, 0x5, 0x1) => self.op_0151(),
+            (Platform::Chip8E, 0x0, 0x1, 0x8, 0x8) => self.pc += 2,
+            (Platform::Chip8E, 0x5, _, _, 0x1) => self.op_5xy1_skip_greater(x, y),
+            (Platform::Chip8E, 0x5, _, _, 0x2) => self.op_5xy2(x, y),
+            (Platform::Chip8E, 0x5, _, _, 0x3) => self.op_5xy3(x, y),
+            (Platform::Chip8E, 0xB, 0xB, _, _) => self.op_bbnn(opcode.nn),
+            (Platform::Chip8E, 0xB, 0xF, _, _) => self.op_bfnn(opcode.nn),
+            (Platform::Chip8E, 0xF, _, 0x0, 0x3) => self.port_out = self.var_reg[x],
+            (Platform::Chip8E, 0xF, _, 0x1, 0xB) => self.op_fx1b(x),
+            (Platform::Chip8E, 0xF, _, 0x4, 0xF) => self.op_fx4f(x),
+            // the port is always ready, so FXE3 doesn't wait for a strobe
+            (Platform::Chip8E, 0xF, _, 0xE, 0x3 | 0x7) => self.var_reg[x] = self.port_in,
+            (Platform::HiRes, 0x0, 0x2, 0x3, 0x0) => self.op_00e0(),
+            (Platform::MegaChip, 0x0, 0x0, 0x1, 0x0) => self.op_001n(false),
+            (Platform::MegaChip, 0x0, 0x0, 0x1, 0x1) => self.op_001n(true),
+            (Platform::MegaChip, 0x0, 0x1, _, _) => self.op_01nn(opcode.nn),
+            (Platform::MegaChip, 0x0, 0x2, _, _) => self.op_02nn(opcode.nn),
+            (Platform::MegaChip, 0x0, 0x3, _, _) => self.op_03nn(opcode.nn),
+            (Platform::MegaChip, 0x0, 0x4, _, _) => self.op_04nn(opcode.nn),
+            (Platform::MegaChip, 0x0, 0x5, _, _) => self.op_05nn(opcode.nn),
+            (Platform::MegaChip, 0x0, 0x6, 0x0, _) => self.op_060n(n),
+            (Platform::MegaChip, 0x0, 0x7, 0x0, 0x0) => self.digital_sound = None,
+            (Platform::MegaChip, 0x0, 0x8, 0x0, 0x0..=0x5) => self.op_080n(n),
+            _ => return false,
+        }
+        true
+    }
+
+    /** CHIP-8X background - Steps the background colour through blue, black, green and red */
+    fn op_02a0(&mut self) {
+        if let Some(colors) = self.display.colors.as_mut() {
+            colors.cycle_background();
+            self.draw_flag = true;
+        }
+    }
+
+    /** CHIP-8X add - Adds VY to VX nibble by nibble, each wrapping at 8 like the colours they hold */
+    fn op_5xy1_add_nibbles(&mut self, x: usize, y: usize) {
+        let (vx, vy) = (self.var_reg[x], self.var_reg[y]);
+        let high = (vx & 0xF0).wrapping_add(vy & 0xF0) & 0x70;
+        let low = (vx & 0xF).wrapping_add(vy & 0xF) & 0x7;
+        self.var_reg[x] = high | low;
+    }
+
+    /**
+     * CHIP-8X colour - Sets the foreground to VY. BXY0 colours zones 8 pixels wide and 4 rows
+     * tall: the low nibbles of VX and V(X+1) pick the first zone, the high nibbles how many
+     * more to colour right and down. BXYN colours N rows from V(X+1) in the column holding VX
+     */
+    fn op_bxyn(&mut self, x: usize, y: usize, n: u8) {
+        let color = self.var_reg[y];
+        let (vx, vy) = (
+            self.var_reg[x] as usize,
+            self.var_reg[(x + 1) & 0xF] as usize,
+        );
+        let Some(colors) = self.display.colors.as_mut() else {
+            return;
+        };
+        match n {
+            0 => {
+                let (left, top) = (vx & 0xF, vy & 0xF);
+                let (right, bottom) = (left + (vx >> 4), top + (vy >> 4));
+                colors.fill(top * 4, bottom * 4 + 3, left, right, color);
+            }
+            _ => {
+                let (column, top) = ((vx % WIDTH) / 8, vy % HEIGHT);
+                colors.fill(top, top + n as usize - 1, column, column, color);
+            }
+        }
+        self.draw_flag = true;
+    }
+
+    /** MEGA-CHIP mode - 0011 switches to the 256x192 colour screen, 0010 back to 64x32 */
+    fn op_001n(&mut self, enabled: bool) {
+        self.display.set_mega_mode(enabled);
+        self.draw_flag = true;
+    }
+
+    /** MEGA-CHIP long index - Sets I to the 24-bit NN NNNN, reading NNNN from the next word */
+    fn op_01nn(&mut self, nn: u8) {
+        let pc = self.pc as usize;
+        let low = u16::from_be_bytes([self.ram[pc], self.ram[pc + 1]]);
+        self.idx_reg = (nn as u32) << 16 | low as u32;
+        self.pc += 2;
+    }
+
+    /** MEGA-CHIP palette - Loads NN ARGB colours from I into palette entries 1 to NN */
+    fn op_02nn(&mut self, nn: u8) {
+        let start = self.idx_reg as usize;
+        if let Some(mega) = self.display.mega.as_mut() {
+            mega.load_palette(&self.ram[start..start + nn as usize * 4]);
+        }
     }
 
-    /** Jump - Sets the PC to NNN */
+    /** MEGA-CHIP sprite width - Sets the width of MEGA sprites, 0 meaning 256 */
+    fn op_03nn(&mut self, nn: u8) {
+        if let Some(mega) = self.display.mega.as_mut() {
+            mega.set_sprite_width(nn);
+        }
+    }
+
+    /** MEGA-CHIP sprite height - Sets the height of MEGA sprites, 0 meaning 256 */
+    fn op_04nn(&mut self, nn: u8) {
+        if let Some(mega) = self.display.mega.as_mut() {
+            mega.set_sprite_height(nn);
+        }
+    }
+
+    /** MEGA-CHIP alpha - Sets the opacity of the whole screen */
+    fn op_05nn(&mut self, nn: u8) {
+        if let Some(mega) = self.display.mega.as_mut() {
+            mega.alpha = nn;
+            self.draw_flag = true;
+        }
+    }
+
+    /** MEGA-CHIP sound - Plays the digitised sound at I, looping it when N is 0 */
+    fn op_060n(&mut self, n: u8) {
+        let sound = DigitalSound::from_header(&self.ram, self.idx_reg, n == 0)
+            .expect("Sound header runs past the end of memory");
+        self.digital_sound = Some(sound);
+    }
+
+    /** MEGA-CHIP blend - Picks how sprites mix with the screen */
+    fn op_080n(&mut self, n: u8) {
+        if let (Some(mega), Some(blend)) = (self.display.mega.as_mut(), BlendMode::from_nibble(n)) {
+            mega.blend = blend;
+        }
+    }
+
+    /**
+     * MEGA-CHIP draw - Draws a `sprite_width` by `sprite_height` sprite of palette indices
+     * from I at (VX, VY), or an ordinary 8xN sprite if I points below 0x200. VF is set if it
+     * covers anything drawn since the last 00E0
+     */
+    fn op_dxyn_mega(&mut self, x: usize, y: usize, n: u8) {
+        let (x_coord, y_coord) = (
+            self.var_reg[x] as usize,
+            self.var_reg[y] as usize % MEGA_HEIGHT,
+        );
+        let addr = self.idx_reg as usize;
+        let Some(mega) = self.display.mega.as_mut() else {
+            return;
+        };
+        let sprite = &self.ram[addr..addr + mega.sprite_len(addr, n)];
+        let collision = match addr < MEGA_SPRITES_START {
+            true => mega.draw_bits(x_coord, y_coord, sprite),
+            false => mega.draw_sprite(x_coord, y_coord, sprite),
+        };
+        self.var_reg[0xF] = collision as u8;
+
+        #[cfg(feature = "instrumentation")]
+        {
+            self.counters.draws += 1;
+            self.counters.collisions += self.var_reg[0xF] as u64;
+        }
+    }
+
+    /** CHIP-8E wait - Repeats until the delay timer reaches 0 */
+    fn op_0151(&mut self) {
+        if self.delay_timer > 0 {
+            self.pc -= 2;
+        }
+    }
+
+    /** CHIP-8E skip conditional - Skips instruction if VX is greater than VY */
+    fn op_5xy1_skip_greater(&mut self, x: usize, y: usize) {
+        if self.var_reg[x] > self.var_reg[y] {
+            self.pc += 2;
+        }
+    }
+
+    /** CHIP-8E store - Stores VX to VY from I, which moves past them with the memory quirk */
+    fn op_5xy2(&mut self, x: usize, y: usize) {
+        let start = self.idx_reg as usize;
+        for (offset, reg) in (x..=y).enumerate() {
+            self.ram[start + offset] = self.var_reg[reg];
+        }
+        self.advance_index_after_range(x, y);
+    }
+
+    /** CHIP-8E load - Loads VX to VY from I, which moves past them with the memory quirk */
+    fn op_5xy3(&mut self, x: usize, y: usize) {
+        let start = self.idx_reg as usize;
+        for (offset, reg) in (x..=y).enumerate() {
+            self.var_reg[reg] = self.ram[start + offset];
+        }
+        self.advance_index_after_range(x, y);
+    }
+
+    fn advance_index_after_range(&mut self, x: usize, y: usize) {
+        if self.quirks.memory_increments_i {
+            self.idx_reg += (y + 1).saturating_sub(x) as u32;
+        }
+    }
+
+    /** CHIP-8E branch - Jumps NN bytes back from this instruction */
+    fn op_bbnn(&mut self, nn: u8) {
+        self.pc = self.pc.wrapping_sub(2 + nn as u16);
+    }
+
+    /** CHIP-8E branch - Jumps NN bytes forward from this instruction */
+    fn op_bfnn(&mut self, nn: u8) {
+        self.pc = self.pc - 2 + nn as u16;
+    }
+
+    /** CHIP-8E skip - Skips the next VX bytes */
+    fn op_fx1b(&mut self, x: usize) {
+        self.pc += self.var_reg[x] as u16;
+    }
+
+    /** CHIP-8E delay - Sets the delay timer to VX, then repeats until it reaches 0 */
+    fn op_fx4f(&mut self, x: usize) {
+        if !self.waiting_for_delay {
+            self.delay_timer = self.var_reg[x];
+            self.waiting_for_delay = true;
+        }
+        match self.delay_timer {
+            0 => self.waiting_for_delay = false,
+            _ => self.pc -= 2,
+        }
+    }
+
+    /**
+     * Jump - Sets the PC to NNN. HiRes programs start with a jump to 0x260, where their
+     * interpreter sets up the 64x64 display and carries on at 0x2C0
+     */
     fn op_1nnn(&mut self, nnn: u16) {
-        self.pc = nnn;
+        self.pc = match self.platform == Platform::HiRes && nnn == 0x260 && self.pc == 0x202 {
+            true => HIRES_START,
+            false => nnn,
+        };
     }
 
+    /** Call - Pushes the PC and jumps to NNN. Panics once calls nest deeper than the quirks allow */
     fn op_2nnn(&mut self, nnn: u16) {
+        if self.call_stack.len() >= self.quirks.stack_depth as usize {
+            panic!(
+                "Stack overflow: calls nest deeper than {}",
+                self.quirks.stack_depth
+            );
+        }
+        if self.quirks.stack_in_ram {
+            let slot = stack_slot(self.call_stack.len());
+            self.ram[slot..slot + 2].copy_from_slice(&self.pc.to_be_bytes());
+        }
         self.call_stack.push(self.pc);
         self.pc = nnn;
     }
@@ -252,14 +810,23 @@ impl Chip8 {
     /** Sets VX to the OR of VX and VY */
     fn op_8xy1(&mut self, x: usize, y: usize) {
         self.var_reg[x] |= self.var_reg[y];
+        self.reset_vf_after_logic();
     }
 
     fn op_8xy2(&mut self, x: usize, y: usize) {
         self.var_reg[x] &= self.var_reg[y];
+        self.reset_vf_after_logic();
     }
 
     fn op_8xy3(&mut self, x: usize, y: usize) {
         self.var_reg[x] ^= self.var_reg[y];
+        self.reset_vf_after_logic();
+    }
+
+    fn reset_vf_after_logic(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.var_reg[0xF] = 0;
+        }
     }
 
     fn op_8xy4(&mut self, x: usize, y: usize) {
@@ -274,8 +841,14 @@ impl Chip8 {
         self.var_reg[x] = self.var_reg[x].wrapping_sub(self.var_reg[y]);
     }
 
-    /** Shift - puts VY into VX and shifts VX 1 bit to the right */
+    /** Shift - puts VY into VX and shifts VX 1 bit to the right, or shifts VX in place */
     fn op_8xy6(&mut self, x: usize, y: usize) {
+        if self.quirks.shift_in_place {
+            let val = self.var_reg[x];
+            self.var_reg[x] = val >> 1;
+            self.var_reg[0xF] = val & 1;
+            return;
+        }
         self.var_reg[0xF] = self.var_reg[y] & 1;
         self.var_reg[x] = self.var_reg[y] >> 1;
     }
@@ -288,8 +861,14 @@ impl Chip8 {
         self.var_reg[y] = self.var_reg[y].wrapping_sub(self.var_reg[x]);
     }
 
-    /** Shift - puts VY into VX and shifts VX 1 bit to the left */
+    /** Shift - puts VY into VX and shifts VX 1 bit to the left, or shifts VX in place */
     fn op_8xye(&mut self, x: usize, y: usize) {
+        if self.quirks.shift_in_place {
+            let val = self.var_reg[x];
+            self.var_reg[x] = val << 1;
+            self.var_reg[0xF] = (val >> 7) & 1;
+            return;
+        }
         let val = (self.var_reg[y] >> 7) & 1;
         self.var_reg[0xF] = val;
         self.var_reg[x] = self.var_reg[y];
@@ -297,28 +876,37 @@ impl Chip8 {
 
     /** Sets index register to NNN */
     fn op_annn(&mut self, nnn: u16) {
-        self.idx_reg = nnn;
+        self.idx_reg = nnn as u32;
     }
 
-    /** Jumps to NNN plus value in V0 */
+    /** Jumps to NNN plus value in V0 (or VX with the jump quirk) */
     fn op_bnnn(&mut self, nnn: u16) {
-        self.pc = nnn + self.var_reg[0x0] as u16;
+        let offset_reg = match self.quirks.jump_uses_vx {
+            true => (nnn >> 8) as usize,
+            false => 0x0,
+        };
+        self.pc = nnn + self.var_reg[offset_reg] as u16;
     }
 
     fn op_cxnn(&mut self, x: usize, nn: u8) {
-        let rand_num: u8 = rand::random::<u8>() & nn;
+        let rand_num: u8 = self.rng.next_byte() & nn;
         self.var_reg[x] = rand_num;
     }
 
     fn op_dxyn(&mut self, x: usize, y: usize, n: u8) {
-        let y_coord = self.var_reg[y] as usize;
-        let x_coord = self.var_reg[x] as usize & (WIDTH - 1);
+        if self.display.mega_mode().is_some() {
+            return self.op_dxyn_mega(x, y, n);
+        }
+        let (width, height) = (self.display.width(), self.display.height());
+        let y_coord = self.var_reg[y] as usize % height;
+        let x_coord = self.var_reg[x] as usize % width;
+        let wrap = self.quirks.wrap_sprites;
 
         self.var_reg[0xF] = 0x0;
 
         for row in 0..n {
             // get the Nth byte of sprite data starting from address at idx_reg
-            let sprite_data: u8 = self.ram[(self.idx_reg + row as u16) as usize];
+            let sprite_data: u8 = self.ram[self.idx_reg as usize + row as usize];
             // for each bit in sprite data...
             for col in 0..8 {
                 let sprite_pixel = (sprite_data >> (7 - col)) & 0x1 == 1;
@@ -326,12 +914,14 @@ impl Chip8 {
                     continue;
                 }
 
-                let x_pos = x_coord + col as usize;
-                if x_pos >= WIDTH {
+                let mut x_pos = x_coord + col as usize;
+                let mut y_pos = y_coord + row as usize;
+                if !wrap && (x_pos >= width || y_pos >= height) {
                     break;
                 }
+                x_pos %= width;
+                y_pos %= height;
 
-                let y_pos = (y_coord + row as usize) % HEIGHT;
                 let curr_pixel = self.display.get_pixel(y_pos, x_pos);
 
                 // set VF to 1 if sprite pixel and display pixel are both on
@@ -342,16 +932,24 @@ impl Chip8 {
                 self.display.flip_pixel(y_pos, x_pos);
             }
         }
+        self.draw_flag = true;
+
+        #[cfg(feature = "instrumentation")]
+        {
+            self.counters.draws += 1;
+            self.counters.collisions += self.var_reg[0xF] as u64;
+        }
     }
 
     /** Skips instruction if key in VX is pressed */
     fn op_ex9e(&mut self, x: usize) {
         match self.curr_input_key {
             None => self.pc += 2,
-            Some(val) => match self.var_reg[x] == val {
-                true => self.pc += 2,
-                false => {}
-            },
+            Some(val) => {
+                if self.var_reg[x] == val {
+                    self.pc += 2;
+                }
+            }
         }
     }
 
@@ -359,10 +957,11 @@ impl Chip8 {
     fn op_exa1(&mut self, x: usize) {
         match self.curr_input_key {
             None => self.pc += 2,
-            Some(val) => match self.var_reg[x] == val {
-                true => {}
-                false => self.pc += 2,
-            },
+            Some(val) => {
+                if self.var_reg[x] != val {
+                    self.pc += 2;
+                }
+            }
         }
     }
 
@@ -380,15 +979,38 @@ impl Chip8 {
 
     fn op_fx15(&mut self, x: usize) {
         self.delay_timer = self.var_reg[x];
+        #[cfg(feature = "instrumentation")]
+        {
+            self.counters.timer_writes += 1;
+        }
     }
 
     /** Sets sound timer to value in VX */
     fn op_fx18(&mut self, x: usize) {
         self.sound_timer = self.var_reg[x];
+        #[cfg(feature = "instrumentation")]
+        {
+            self.counters.timer_writes += 1;
+        }
     }
 
     fn op_fx1e(&mut self, x: usize) {
-        self.idx_reg = self.idx_reg.wrapping_add(self.var_reg[x] as u16);
+        self.idx_reg = self.idx_reg.wrapping_add(self.var_reg[x] as u32);
+    }
+
+    /** Points I at the small glyph for the low nibble of VX */
+    fn op_fx29(&mut self, x: usize) {
+        self.idx_reg = self.font_addr as u32 + (self.var_reg[x] & 0xF) as u32 * 5;
+    }
+
+    /** Points I at the big glyph for the low nibble of VX */
+    fn op_fx30(&mut self, x: usize) {
+        let digit = self.var_reg[x] & 0xF;
+        if !self.has_big_glyph(digit) {
+            panic!("Unsupported opcode: {:#06x}", 0xF030 | (x as u16) << 8);
+        }
+        let big_start = self.font_addr as u32 + SMALL_FONT_LEN as u32;
+        self.idx_reg = big_start + digit as u32 * BIG_GLYPH_LEN as u32;
     }
 
     /** Binary-coded decimal conversion */
@@ -404,11 +1026,22 @@ impl Chip8 {
         for i in 0..x + 1 {
             self.ram[self.idx_reg as usize + i] = self.var_reg[i];
         }
+        if self.quirks.memory_increments_i {
+            self.idx_reg += x as u32 + 1;
+        }
     }
 
     fn op_fx65(&mut self, x: usize) {
         for i in 0..x + 1 {
             self.var_reg[i] = self.ram[self.idx_reg as usize + i];
         }
+        if self.quirks.memory_increments_i {
+            self.idx_reg += x as u32 + 1;
+        }
     }
 }
+
+/** Address of the high byte of the return address at a stack level, when the stack is in RAM */
+pub(crate) fn stack_slot(level: usize) -> usize {
+    quirks::VIP_STACK_TOP - 2 * (level + 1)
+}
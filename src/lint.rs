@@ -0,0 +1,465 @@
+use crate::Chip8;
+use crate::cfg::{ControlFlowGraph, EdgeKind, START};
+use crate::display::{HEIGHT, WIDTH};
+use crate::error::Chip8Error;
+use crate::predecode::Op;
+use crate::quirks::{SCHIP_STACK_DEPTH, VIP_STACK_DEPTH};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/** How many instructions to follow after FX55/FX65 looking for the next use of I */
+const INDEX_SEARCH_LIMIT: usize = 64;
+
+/** Something a ROM does that behaves differently between platforms, or doesn't run here at all */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lint {
+    /** 8XY6/8XYE with X≠Y: the VIP shifts VY into VX, SUPER-CHIP shifts VX in place */
+    Shift { addr: u16, x: u8, y: u8 },
+    /** FX55/FX65 at `addr` followed by a use of I at `used_at` before I is set again */
+    IndexAfterMemory { addr: u16, used_at: u16 },
+    /** BNNN, which adds V0 on the VIP and VX on SUPER-CHIP */
+    JumpOffset { addr: u16 },
+    /** A sprite crossing the edge of the screen, which clips or wraps depending on the platform */
+    EdgeDraw { addr: u16 },
+    /** Calls nesting deeper than the VIP's stack */
+    StackDepth { depth: usize },
+    /** A subroutine that can call itself, so its stack depth has no static bound */
+    Recursion { subroutine: u16 },
+    /** FX33/FX55 at `addr` writing over code at `target` */
+    SelfModifying { addr: u16, target: u16 },
+    /** A 0NNN machine code routine, which only the VIP can run */
+    MachineCall { addr: u16 },
+    /** An instruction no supported platform defines */
+    Unsupported { addr: u16, instruction: u16 },
+}
+impl Lint {
+    /** The presets that run the ROM the way this lint suggests it expects, and why */
+    fn presets(&self) -> Option<(&'static [&'static str], &'static str)> {
+        match *self {
+            Lint::Shift { y: 0, .. } => Some((&["schip"], "shifts ignore VY")),
+            Lint::Shift { .. } => Some((&["default", "vip", "xochip"], "shifts read VY")),
+            Lint::IndexAfterMemory { .. } => {
+                Some((&["vip", "xochip"], "relies on FX55/FX65 incrementing I"))
+            }
+            Lint::MachineCall { .. } => Some((&["vip"], "calls machine code")),
+            Lint::StackDepth { depth } if depth > VIP_STACK_DEPTH as usize => Some((
+                &["schip", "xochip"],
+                "nests calls deeper than the VIP's stack",
+            )),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Lint::Shift { addr, x, y } => write!(
+                f,
+                "{addr:#05x}: 8XY6/8XYE shifts V{y:X} into V{x:X} on the VIP but V{x:X} in place on SUPER-CHIP"
+            ),
+            Lint::IndexAfterMemory { addr, used_at } => write!(
+                f,
+                "{addr:#05x}: I is used again at {used_at:#05x}, but only the VIP leaves it incremented after FX55/FX65"
+            ),
+            Lint::JumpOffset { addr } => write!(
+                f,
+                "{addr:#05x}: BNNN jumps relative to V0 on the VIP but to VX on SUPER-CHIP"
+            ),
+            Lint::EdgeDraw { addr } => write!(
+                f,
+                "{addr:#05x}: sprite crosses the screen edge, which clips on the VIP and SUPER-CHIP but wraps on XO-CHIP"
+            ),
+            Lint::StackDepth { depth } if depth > SCHIP_STACK_DEPTH as usize => write!(
+                f,
+                "calls nest {depth} deep, more than SUPER-CHIP's {SCHIP_STACK_DEPTH} levels"
+            ),
+            Lint::StackDepth { depth } => write!(
+                f,
+                "calls nest {depth} deep, more than the VIP's {VIP_STACK_DEPTH} levels"
+            ),
+            Lint::Recursion { subroutine } => write!(
+                f,
+                "{subroutine:#05x}: subroutine can call itself, so its stack depth has no static bound"
+            ),
+            Lint::SelfModifying { addr, target } => {
+                write!(f, "{addr:#05x}: writes over code at {target:#05x}")
+            }
+            Lint::MachineCall { addr } => write!(
+                f,
+                "{addr:#05x}: 0NNN calls a machine code routine, which only runs on the VIP"
+            ),
+            Lint::Unsupported { addr, instruction } => write!(
+                f,
+                "{addr:#05x}: {instruction:04X} isn't defined on any supported platform"
+            ),
+        }
+    }
+}
+
+/** A quirks preset that suits a ROM, with the lints that point to it */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recommendation {
+    /** A name `Quirks::preset` accepts */
+    pub preset: &'static str,
+    pub reasons: Vec<&'static str>,
+}
+
+/**
+ * Picks the preset that agrees with the most kinds of platform-specific behaviour in the lints.
+ * Ties, including ROMs with nothing platform-specific, go to the default quirks
+ */
+pub fn recommend(lints: &[Lint]) -> Recommendation {
+    let mut reasons: BTreeMap<&'static str, BTreeSet<&'static str>> = BTreeMap::new();
+    for (presets, reason) in lints.iter().filter_map(Lint::presets) {
+        for preset in presets {
+            reasons.entry(preset).or_default().insert(reason);
+        }
+    }
+    let mut best = Recommendation {
+        preset: "default",
+        reasons: Vec::new(),
+    };
+    for preset in ["default", "vip", "schip", "xochip"] {
+        let Some(found) = reasons.get(preset) else {
+            continue;
+        };
+        if found.len() > best.reasons.len() {
+            best = Recommendation {
+                preset,
+                reasons: found.iter().copied().collect(),
+            };
+        }
+    }
+    best
+}
+
+/**
+ * Lints a ROM without running it. Registers and I are tracked through each block of the
+ * control-flow graph, so edge draws and writes over code are only found where they're constant
+ */
+pub fn check(rom: &[u8]) -> Vec<Lint> {
+    let graph = ControlFlowGraph::build(rom);
+    let mut lints = Vec::new();
+    for block in graph.blocks.values() {
+        let mut registers: [Option<u8>; 16] = [None; 16];
+        let mut index: Option<u16> = None;
+        for addr in (block.start..block.end).step_by(2) {
+            let op = Op::decode(graph.instruction(addr));
+            match op {
+                Op::ShiftRight(x, y) | Op::ShiftLeft(x, y) if x != y => {
+                    lints.push(Lint::Shift { addr, x, y })
+                }
+                Op::JumpOffset(_) => lints.push(Lint::JumpOffset { addr }),
+                Op::Draw(x, y, n) => {
+                    if let (Some(vx), Some(vy)) = (registers[x as usize], registers[y as usize]) {
+                        let rows: Vec<u8> = (0..n as u16)
+                            .map(|row| match index {
+                                Some(i) if i >= START => {
+                                    rom.get((i - START + row) as usize).copied().unwrap_or(0)
+                                }
+                                _ => 0xFF,
+                            })
+                            .collect();
+                        if crosses_edge(vx, vy, &rows) {
+                            lints.push(Lint::EdgeDraw { addr });
+                        }
+                    }
+                }
+                Op::MachineCall(_) => lints.push(Lint::MachineCall { addr }),
+                Op::Unsupported(instruction) => lints.push(Lint::Unsupported { addr, instruction }),
+                _ => {}
+            }
+            let written = match op {
+                Op::Bcd(_) => 3,
+                Op::Store(x) => x as u16 + 1,
+                _ => 0,
+            };
+            if let Some(i) = index
+                && let Some(target) = (i..i.saturating_add(written)).find(|a| graph.is_code(*a))
+            {
+                lints.push(Lint::SelfModifying { addr, target });
+            }
+            if matches!(op, Op::Store(_) | Op::Load(_))
+                && let Some(used_at) = next_index_use(&graph, addr)
+            {
+                lints.push(Lint::IndexAfterMemory { addr, used_at });
+            }
+            track(op, &mut registers, &mut index);
+        }
+    }
+
+    match call_depth(&graph, START, &mut Vec::new(), &mut BTreeMap::new()) {
+        Ok(depth) if depth > VIP_STACK_DEPTH as usize => lints.push(Lint::StackDepth { depth }),
+        Ok(_) => {}
+        Err(subroutine) => lints.push(Lint::Recursion { subroutine }),
+    }
+    lints.sort_unstable();
+    lints.dedup();
+    lints
+}
+
+/**
+ * Lints a ROM while it runs, catching what the static pass can't see: computed sprite
+ * positions, writes through a computed I and the stack depth actually reached
+ */
+#[derive(Clone, Debug)]
+pub struct Linter {
+    lints: BTreeSet<Lint>,
+    /** The deepest the stack has been, or could be according to the static pass */
+    depth: usize,
+    /** Addresses of instruction bytes that have run */
+    executed: Box<[bool; 4096]>,
+    /** The last FX55/FX65 if I hasn't been set since */
+    stale_index: Option<u16>,
+}
+impl Default for Linter {
+    fn default() -> Linter {
+        Linter {
+            lints: BTreeSet::new(),
+            depth: 0,
+            executed: Box::new([false; 4096]),
+            stale_index: None,
+        }
+    }
+}
+impl Linter {
+    pub fn new() -> Linter {
+        Linter::default()
+    }
+
+    /** Adds the lints `check` finds statically */
+    pub fn check_rom(&mut self, rom: &[u8]) {
+        for lint in check(rom) {
+            match lint {
+                Lint::StackDepth { depth } => self.depth = self.depth.max(depth),
+                _ => {
+                    self.lints.insert(lint);
+                }
+            }
+        }
+    }
+
+    /** Lints the instruction at the PC. Call before it runs */
+    pub fn observe(&mut self, chip8: &Chip8) {
+        let pc = chip8.pc as usize;
+        if pc + 1 >= chip8.ram.len().min(self.executed.len()) {
+            return;
+        }
+        let addr = chip8.pc;
+        let op = Op::decode(u16::from_be_bytes([chip8.ram[pc], chip8.ram[pc + 1]]));
+        self.executed[pc] = true;
+        self.executed[pc + 1] = true;
+
+        match op {
+            Op::ShiftRight(x, y) | Op::ShiftLeft(x, y) if x != y => {
+                self.lints.insert(Lint::Shift { addr, x, y });
+            }
+            Op::JumpOffset(_) => {
+                self.lints.insert(Lint::JumpOffset { addr });
+            }
+            Op::Draw(x, y, n) => {
+                let rows: Vec<u8> = (0..n as usize)
+                    .map(|row| chip8.ram[(chip8.idx_reg as usize + row) % chip8.ram.len()])
+                    .collect();
+                if crosses_edge(chip8.var_reg[x as usize], chip8.var_reg[y as usize], &rows) {
+                    self.lints.insert(Lint::EdgeDraw { addr });
+                }
+            }
+            Op::Call(_) => self.depth = self.depth.max(chip8.call_stack.len() + 1),
+            Op::MachineCall(_) => {
+                self.lints.insert(Lint::MachineCall { addr });
+            }
+            Op::Unsupported(instruction) => {
+                self.lints.insert(Lint::Unsupported { addr, instruction });
+            }
+            _ => {}
+        }
+        // a machine call may write anywhere and is already reported as one
+        if let Some((start, end)) = op.write_range(chip8)
+            && !matches!(op, Op::MachineCall(_))
+            && let Some(target) = (start..end.min(self.executed.len())).find(|a| self.executed[*a])
+        {
+            self.lints.insert(Lint::SelfModifying {
+                addr,
+                target: target as u16,
+            });
+        }
+        if let Some(stale) = self.stale_index
+            && uses_index(op)
+        {
+            self.lints.insert(Lint::IndexAfterMemory {
+                addr: stale,
+                used_at: addr,
+            });
+        }
+        match op {
+            Op::Store(_) | Op::Load(_) => self.stale_index = Some(addr),
+            Op::SetIndex(_) | Op::FontChar(_) | Op::BigFontChar(_) => self.stale_index = None,
+            _ => {}
+        }
+    }
+
+    /**
+     * Runs a loaded program for a number of frames, linting each instruction. Stops at the
+     * first instruction the interpreter can't run, which is linted before the error is returned
+     */
+    pub fn run(
+        &mut self,
+        chip8: &mut Chip8,
+        frames: usize,
+        cycles_per_frame: usize,
+    ) -> Result<(), Chip8Error> {
+        for _ in 0..frames {
+            for _ in 0..cycles_per_frame {
+                self.observe(chip8);
+                chip8.try_cycle()?;
+            }
+            chip8.decrement_timers(1);
+        }
+        Ok(())
+    }
+
+    /** Everything found so far, ordered by kind and address */
+    pub fn lints(&self) -> Vec<Lint> {
+        let mut lints: Vec<Lint> = self.lints.iter().copied().collect();
+        if self.depth > VIP_STACK_DEPTH as usize {
+            lints.push(Lint::StackDepth { depth: self.depth });
+            lints.sort_unstable();
+        }
+        lints
+    }
+
+    pub fn recommend(&self) -> Recommendation {
+        recommend(&self.lints())
+    }
+}
+
+/** Whether any lit pixel of a sprite drawn at (VX, VY) lands past the right or bottom edge */
+fn crosses_edge(vx: u8, vy: u8, rows: &[u8]) -> bool {
+    let (left, top) = (vx as usize % WIDTH, vy as usize % HEIGHT);
+    rows.iter().enumerate().any(|(row, &bits)| {
+        bits != 0 && (top + row >= HEIGHT || left + 8 - bits.trailing_zeros() as usize > WIDTH)
+    })
+}
+
+/** Whether an op reads I, and so depends on whether FX55/FX65 incremented it */
+fn uses_index(op: Op) -> bool {
+    matches!(
+        op,
+        Op::Draw(..) | Op::AddIndex(_) | Op::Bcd(_) | Op::Store(_) | Op::Load(_)
+    )
+}
+
+/** Updates the registers and I known to be constant after an op runs */
+fn track(op: Op, registers: &mut [Option<u8>; 16], index: &mut Option<u16>) {
+    match op {
+        Op::SetImm(x, nn) => registers[x as usize] = Some(nn),
+        Op::AddImm(x, nn) => {
+            registers[x as usize] = registers[x as usize].map(|vx| vx.wrapping_add(nn))
+        }
+        Op::Set(x, y) => registers[x as usize] = registers[y as usize],
+        Op::Or(x, _)
+        | Op::And(x, _)
+        | Op::Xor(x, _)
+        | Op::Add(x, _)
+        | Op::Sub(x, _)
+        | Op::ShiftRight(x, _)
+        | Op::SubReverse(x, _)
+        | Op::ShiftLeft(x, _) => {
+            registers[x as usize] = None;
+            registers[0xF] = None;
+        }
+        Op::Random(x, _) | Op::GetDelay(x) | Op::WaitKey(x) => registers[x as usize] = None,
+        Op::Draw(..) => registers[0xF] = None,
+        Op::SetIndex(nnn) => *index = Some(nnn),
+        Op::AddIndex(_) | Op::FontChar(_) | Op::BigFontChar(_) | Op::Store(_) => *index = None,
+        Op::Load(x) => {
+            registers[..=x as usize].fill(None);
+            *index = None;
+        }
+        Op::Call(_) | Op::MachineCall(_) | Op::Unsupported(_) => {
+            *registers = [None; 16];
+            *index = None;
+        }
+        _ => {}
+    }
+}
+
+/** Follows the code after an instruction until something uses I or sets it */
+fn next_index_use(graph: &ControlFlowGraph, from: u16) -> Option<u16> {
+    let mut pending = vec![from + 2];
+    let mut seen = BTreeSet::new();
+    while let Some(addr) = pending.pop() {
+        let Some(block) = graph
+            .blocks
+            .range(..=addr)
+            .next_back()
+            .map(|(_, block)| block)
+            .filter(|block| addr < block.end)
+        else {
+            continue;
+        };
+        if seen.len() >= INDEX_SEARCH_LIMIT || !seen.insert(addr) {
+            continue;
+        }
+        let op = Op::decode(graph.instruction(addr));
+        if uses_index(op) {
+            return Some(addr);
+        }
+        if matches!(op, Op::SetIndex(_) | Op::FontChar(_) | Op::BigFontChar(_)) {
+            continue;
+        }
+        match addr + 2 == block.end {
+            true => pending.extend(block.successors.iter().map(|edge| edge.target)),
+            false => pending.push(addr + 2),
+        }
+    }
+    None
+}
+
+/**
+ * How many return addresses calls from `entry` can push, following every path through the
+ * code it reaches. Returns the subroutine that recurses if there is no bound
+ */
+fn call_depth(
+    graph: &ControlFlowGraph,
+    entry: u16,
+    calling: &mut Vec<u16>,
+    depths: &mut BTreeMap<u16, usize>,
+) -> Result<usize, u16> {
+    if let Some(&depth) = depths.get(&entry) {
+        return Ok(depth);
+    }
+    if calling.contains(&entry) {
+        return Err(entry);
+    }
+    calling.push(entry);
+
+    // the calls made by code reachable from the entry without calling anything
+    let mut callees = BTreeSet::new();
+    let mut seen = BTreeSet::new();
+    let mut pending = vec![entry];
+    while let Some(start) = pending.pop() {
+        let Some(block) = graph.blocks.get(&start) else {
+            continue;
+        };
+        if !seen.insert(start) {
+            continue;
+        }
+        for edge in &block.successors {
+            if edge.kind == EdgeKind::Call {
+                callees.insert(edge.target);
+            } else {
+                pending.push(edge.target);
+            }
+        }
+    }
+
+    let mut depth = 0;
+    for callee in callees {
+        depth = depth.max(1 + call_depth(graph, callee, calling, depths)?);
+    }
+    calling.pop();
+    depths.insert(entry, depth);
+    Ok(depth)
+}
@@ -1,22 +1,68 @@
-use chip8::Chip8;
+use chip8::capture::{Capture, Recorder, RecordingFormat};
+use chip8::engine::Engine;
+use chip8::font::{Font, FontSet};
+use chip8::megachip::{DigitalSound, MEGA_HEIGHT, MEGA_WIDTH};
+use chip8::memview::{BYTES_PER_ROW, EditTarget, Highlight, MemoryView, Register};
+use chip8::movie::{Movie, MovieHeader, MovieMode};
+use chip8::palette::{Palette, PaletteDatabase, Rgb, Theme};
+use chip8::platform::Platform;
+use chip8::quirks::Quirks;
+use chip8::renderer::{Renderer, RendererConfig};
+use chip8::rng::Generator;
 use chip8::rom::ROM;
+use chip8::sprites::{self, SpriteSheet, SpriteTracker};
+use chip8::state::Snapshot;
+use chip8::timing::Timing;
+use chip8::{Chip8, FONT_START};
 use macroquad::audio;
-use macroquad::color::{BLACK, WHITE};
-use macroquad::input::{KeyCode, is_key_down, is_key_pressed};
+use macroquad::audio::{PlaySoundParams, Sound};
+use macroquad::color::{BLACK, Color, GRAY, GREEN, RED, SKYBLUE, WHITE, YELLOW};
+use macroquad::input::{
+    KeyCode, MouseButton, is_key_down, is_key_pressed, is_mouse_button_pressed, mouse_position,
+    mouse_wheel,
+};
+use macroquad::math::vec2;
 use macroquad::shapes::{draw_line, draw_rectangle};
 use macroquad::text::draw_text;
-use macroquad::time::get_frame_time;
+use macroquad::texture::{DrawTextureParams, FilterMode, Texture2D, draw_texture_ex};
 use macroquad::window::{Conf, clear_background, next_frame};
 use std::env;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 const SCALE: f32 = 10.0;
 const GAME_HEIGHT: f32 = chip8::HEIGHT as f32 * SCALE;
 const GAME_WIDTH: f32 = chip8::WIDTH as f32 * SCALE;
 
+// memory panel below the display
+const MEMORY_ROWS: usize = 16;
+const ROW_HEIGHT: f32 = 18.0;
+const MEMORY_TOP: f32 = GAME_HEIGHT + 30.0;
+const MEMORY_HEIGHT: f32 = 30.0 + MEMORY_ROWS as f32 * ROW_HEIGHT + 10.0;
+const BYTE_X: f32 = 60.0;
+const BYTE_WIDTH: f32 = 24.0;
+const SPRITE_X: f32 = 470.0;
+const SPRITE_SCALE: f32 = 10.0;
+const REGISTER_X: f32 = 580.0;
+const REGISTER_WIDTH: f32 = 150.0;
+const REGISTERS_PER_COLUMN: usize = 10;
+const STACK_ROW: usize = REGISTERS_PER_COLUMN + 1;
+const STACK_COLUMNS: usize = 4;
+const STACK_WIDTH: f32 = 75.0;
+const SHEET_SCALE: f32 = 2.0;
+const SHEET_COLUMNS: usize = 16;
+
+/** What the panel below the display shows, switched with Tab */
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BottomPanel {
+    Memory,
+    Sprites,
+}
+
 fn window_conf() -> Conf {
     Conf {
         window_title: "Chip8".to_owned(),
-        window_height: GAME_HEIGHT as i32,
+        window_height: (GAME_HEIGHT + MEMORY_HEIGHT) as i32,
         window_width: GAME_WIDTH as i32 + 250,
         ..Default::default()
     }
@@ -25,63 +71,318 @@ fn window_conf() -> Conf {
 #[macroquad::main(window_conf)]
 async fn main() {
     let debug_mode: bool = true;
-    let mut opcode_stack: Vec<u16> = vec![];
     let sound1 = audio::load_sound("resources/sound.wav").await.unwrap();
     let args: Vec<String> = env::args().collect();
     let mut rom_path = "IBM Logo.ch8";
-    if args.len() > 1 {
-        rom_path = &args[1];
+    let mut palette: Option<Palette> = None;
+    let mut palette_db: Option<PaletteDatabase> = None;
+    let mut seed: Option<u64> = None;
+    let mut generator = Generator::default();
+    let mut quirks = Quirks::default();
+    let mut cycles_per_frame: usize = 1;
+    let mut timing = Timing::default();
+    let mut platform = Platform::default();
+    let mut font = Font::default();
+    let mut font_addr = FONT_START as u16;
+    let mut record_movie_path: Option<PathBuf> = None;
+    let mut play_movie_path: Option<PathBuf> = None;
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--theme" => {
+                let name = args_iter.next().expect("Missing theme name");
+                palette = Some(
+                    Theme::from_name(name)
+                        .unwrap_or_else(|| panic!("Unknown theme: {name}"))
+                        .palette(),
+                );
+            }
+            "--seed" => {
+                let value = args_iter.next().expect("Missing seed");
+                seed = Some(value.parse().expect("Seed must be a number"));
+            }
+            "--rng" => {
+                let name = args_iter.next().expect("Missing random generator");
+                generator = Generator::from_name(name)
+                    .unwrap_or_else(|| panic!("Unknown random generator: {name}"));
+            }
+            "--palette" => {
+                let hex = args_iter.next().expect("Missing palette colours");
+                palette = Some(Palette::from_hex(hex).unwrap_or_else(|err| panic!("{err}")));
+            }
+            "--palette-db" => {
+                let path = args_iter.next().expect("Missing palette database path");
+                palette_db =
+                    Some(PaletteDatabase::load(Path::new(path)).expect("Unable to load palettes"));
+            }
+            "--quirks" => {
+                let name = args_iter.next().expect("Missing quirks preset");
+                quirks = Quirks::preset(name).unwrap_or_else(|| panic!("Unknown quirks: {name}"));
+            }
+            "--cycles" => {
+                let value = args_iter.next().expect("Missing cycles per frame");
+                cycles_per_frame = value.parse().expect("Cycles must be a number");
+            }
+            "--timing" => {
+                let name = args_iter.next().expect("Missing timing mode");
+                timing =
+                    Timing::from_name(name).unwrap_or_else(|| panic!("Unknown timing: {name}"));
+            }
+            "--platform" => {
+                let name = args_iter.next().expect("Missing platform");
+                platform =
+                    Platform::from_name(name).unwrap_or_else(|| panic!("Unknown platform: {name}"));
+            }
+            "--font" => {
+                let name = args_iter.next().expect("Missing font set");
+                font = FontSet::from_name(name)
+                    .unwrap_or_else(|| panic!("Unknown font set: {name}"))
+                    .font();
+            }
+            "--font-file" => {
+                let path = args_iter.next().expect("Missing font path");
+                font = Font::load(Path::new(path)).expect("Unable to load font");
+            }
+            "--font-addr" => {
+                let hex = args_iter.next().expect("Missing font address");
+                font_addr = u16::from_str_radix(hex.trim_start_matches("0x"), 16)
+                    .expect("Font address must be hexadecimal");
+            }
+            "--record-movie" => {
+                record_movie_path = Some(args_iter.next().expect("Missing movie path").into());
+            }
+            "--play-movie" => {
+                play_movie_path = Some(args_iter.next().expect("Missing movie path").into());
+            }
+            _ => rom_path = arg,
+        }
     }
 
     let rom: ROM = ROM::new(format!("ROMs/{rom_path}").as_str());
-    let mut chip8: Chip8 = Chip8::new();
-    (&mut chip8).load_rom(rom);
+    // an explicit theme or palette wins over the database
+    let palette = palette
+        .or_else(|| palette_db?.lookup(&rom.sha1()))
+        .unwrap_or_default();
+    let mut movie: Option<Movie> = match (&play_movie_path, &record_movie_path) {
+        (Some(path), _) => {
+            let movie = Movie::load(path).expect("Unable to load movie");
+            if movie.header.rom_sha1 != rom.sha1() {
+                eprintln!("Warning: movie was recorded with a different ROM");
+            }
+            Some(movie)
+        }
+        (None, Some(_)) => Some(Movie::new(MovieHeader {
+            rom_sha1: rom.sha1(),
+            quirks,
+            seed: seed.unwrap_or_else(rand::random),
+            rng: generator,
+            cycles_per_frame,
+            timing,
+            platform,
+            font: font.clone(),
+            font_addr,
+        })),
+        (None, None) => None,
+    };
+    let mut chip8: Chip8 = match (&movie, seed) {
+        (Some(movie), _) => movie.create_machine().unwrap_or_else(|err| panic!("{err}")),
+        (None, Some(seed)) => Chip8::with_seed(seed),
+        (None, None) => Chip8::new(),
+    };
+    if movie.is_none() {
+        chip8.set_generator(generator);
+        chip8.quirks = quirks;
+        chip8.timing = timing;
+        chip8.set_platform(platform);
+        chip8
+            .load_font(&font, font_addr)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+    let mut memory_view = MemoryView::new(rom.size, MEMORY_ROWS);
+    let mut bottom_panel = BottomPanel::Memory;
+    let mut sprite_tracker = SpriteTracker::new();
+    sprite_tracker.extend(sprites::scan(&rom.data[..rom.size]));
+    chip8.load_rom(rom);
+    let mut paused = false;
+    let mut save_state: Option<(Snapshot, usize)> = None;
+    let mut renderer = Renderer::new(RendererConfig::phosphor());
+    let capture = Capture {
+        rows: chip8.display.height(),
+        ..Capture::new(palette, SCALE as usize)
+    };
+    let mut recorder: Option<Recorder> = None;
+    let mega_texture = Texture2D::from_rgba8(
+        MEGA_WIDTH as u16,
+        MEGA_HEIGHT as u16,
+        &[0; MEGA_WIDTH * MEGA_HEIGHT * 4],
+    );
+    mega_texture.set_filter(FilterMode::Nearest);
+    let mut digital_sound: Option<(DigitalSound, Sound)> = None;
 
     let mut is_running: bool = true;
 
-    let mut curr_timer = 0.0;
-    let timer_update = 60.0;
-
     while is_running {
-        clear_background(BLACK);
+        clear_background(to_color(palette.background()));
 
         // input
         if is_key_pressed(KeyCode::Escape) {
             is_running = false;
         }
-        let curr_key = get_user_input();
-        chip8.set_input_key(curr_key);
-
-        // update timers by 60Hz
-        if curr_timer < timer_update {
-            curr_timer += get_frame_time();
-            chip8.decrement_timers(1);
-        } else {
-            curr_timer = 0.0;
+        if is_key_pressed(KeyCode::F12) {
+            let path = capture_path("screenshot", "png");
+            match capture.save_png(&chip8.display, &path) {
+                Ok(()) => println!("Saved screenshot to {}", path.display()),
+                Err(err) => eprintln!("Unable to save screenshot: {err}"),
+            }
+        }
+        if is_key_pressed(KeyCode::F11) {
+            match recorder.take() {
+                Some(clip) => {
+                    let path = capture_path("recording", "gif");
+                    match clip.save(&path) {
+                        Ok(()) => println!("Saved recording to {}", path.display()),
+                        Err(err) => eprintln!("Unable to save recording: {err}"),
+                    }
+                }
+                None => recorder = Some(Recorder::new(capture, RecordingFormat::Gif)),
+            }
+        }
+        // movie controls: pause, frame advance, read-only toggle and save states
+        if is_key_pressed(KeyCode::P) {
+            paused = !paused;
         }
-        if chip8.sound_timer > 0 {
-            audio::play_sound_once(&sound1);
+        if let Some(movie) = movie.as_mut()
+            && is_key_pressed(KeyCode::M)
+        {
+            movie.mode = match movie.mode {
+                MovieMode::ReadOnly => MovieMode::ReadWrite,
+                MovieMode::ReadWrite => MovieMode::ReadOnly,
+            };
         }
+        if is_key_pressed(KeyCode::F5) {
+            let frame = movie.as_ref().map(Movie::frame).unwrap_or_default();
+            save_state = Some((chip8.snapshot(), frame));
+        }
+        if is_key_pressed(KeyCode::F9)
+            && let Some((snapshot, frame)) = &save_state
+        {
+            chip8.restore(snapshot);
+            if let Some(movie) = movie.as_mut() {
+                movie.seek(*frame);
+            }
+        }
+        if is_key_pressed(KeyCode::Tab) {
+            memory_view.select(None);
+            bottom_panel = match bottom_panel {
+                BottomPanel::Memory => BottomPanel::Sprites,
+                BottomPanel::Sprites => BottomPanel::Memory,
+            };
+        }
+        if bottom_panel == BottomPanel::Memory {
+            handle_memory_input(&mut chip8, &mut memory_view, paused);
+        }
+        // typing into the memory view shouldn't press CHIP-8 keys
+        let curr_key = match memory_view.editing() {
+            Some(_) => None,
+            None => get_user_input(),
+        };
 
-        // execute
-        (&mut chip8).cycle();
+        // execute one 60Hz frame
+        if !paused || is_key_pressed(KeyCode::N) {
+            // the tracker records the sprites drawn, for the sprite panel
+            let (input, cycles) = match movie.as_mut() {
+                Some(movie) => (movie.next_input(curr_key), movie.header.cycles_per_frame),
+                None => (curr_key, cycles_per_frame),
+            };
+            chip8.set_input_key(input);
+            sprite_tracker.run_frame(&mut chip8, cycles);
+            if chip8.sound_timer > 0 {
+                audio::play_sound_once(&sound1);
+            }
+        }
+        if digital_sound.as_ref().map(|(playing, _)| *playing) != chip8.digital_sound {
+            if let Some((_, sound)) = digital_sound.take() {
+                audio::stop_sound(&sound);
+            }
+            if let Some(started) = chip8.digital_sound {
+                match audio::load_sound_from_bytes(&started.to_wav(&chip8.ram)).await {
+                    Ok(sound) => {
+                        let params = PlaySoundParams {
+                            looped: started.looping,
+                            volume: 1.0,
+                        };
+                        audio::play_sound(&sound, params);
+                        digital_sound = Some((started, sound));
+                    }
+                    Err(err) => eprintln!("Unable to play digitised sound: {err}"),
+                }
+            }
+        }
 
         // draw display to terminal
-        for row in 0..chip8::HEIGHT {
-            for col in 0..chip8::WIDTH {
+        renderer.update(&chip8.display, chip8.draw_flag);
+        chip8.draw_flag = false;
+        if let Some(colors) = &chip8.display.colors {
+            let background = to_color(colors.background_rgb());
+            draw_rectangle(0.0, 0.0, GAME_WIDTH, GAME_HEIGHT, background);
+        }
+        // MEGA-CHIP's 4:3 screen is drawn as large as fits, centred
+        if chip8.display.mega_mode().is_some() {
+            let width = GAME_HEIGHT * MEGA_WIDTH as f32 / MEGA_HEIGHT as f32;
+            mega_texture.update_from_bytes(
+                MEGA_WIDTH as u32,
+                MEGA_HEIGHT as u32,
+                &chip8.display.to_rgba(&palette),
+            );
+            let params = DrawTextureParams {
+                dest_size: Some(vec2(width, GAME_HEIGHT)),
+                ..Default::default()
+            };
+            draw_texture_ex(
+                &mega_texture,
+                (GAME_WIDTH - width) / 2.0,
+                0.0,
+                WHITE,
+                params,
+            );
+        }
+        // taller displays fit the same screen area with shorter pixels, like on the VIP
+        let pixel_height = GAME_HEIGHT / chip8.display.height() as f32;
+        for row in 0..chip8.display.height() {
+            for col in 0..chip8.display.width() {
                 let x_coord = col as f32 * SCALE;
-                let y_coord = row as f32 * SCALE;
-                match chip8.display.get_pixel(row, col) {
-                    true => draw_rectangle(x_coord, y_coord, SCALE, SCALE, WHITE),
-                    false => {
-                        // Not drawing a black square because the screen is set black each loop
-                    }
+                let y_coord = row as f32 * pixel_height;
+                let intensity = renderer.intensity(row, col);
+                // Not drawing unlit squares because the screen is set black each loop
+                if intensity > 0.0 {
+                    let shades = chip8.display.palette_at(row, col, &palette);
+                    let color = to_color(shades.shade(intensity));
+                    draw_rectangle(x_coord, y_coord, SCALE, pixel_height, color);
                 }
             }
         }
 
+        if let Some(clip) = recorder.as_mut() {
+            clip.push_frame(&chip8.display);
+        }
+
         // draw debug details
         draw_line(GAME_WIDTH, 0.0, GAME_WIDTH, GAME_HEIGHT, 2.0, WHITE);
+        draw_line(
+            0.0,
+            GAME_HEIGHT,
+            GAME_WIDTH + 250.0,
+            GAME_HEIGHT,
+            2.0,
+            WHITE,
+        );
+        match bottom_panel {
+            BottomPanel::Memory => {
+                memory_view.update(&chip8);
+                draw_memory(&chip8, &memory_view);
+            }
+            BottomPanel::Sprites => draw_sprites(&chip8, &sprite_tracker),
+        }
 
         if debug_mode {
             // current key input
@@ -93,14 +394,52 @@ async fn main() {
                 25.0,
                 WHITE,
             );
+            if recorder.is_some() {
+                draw_text("REC", GAME_WIDTH + 10.0, 50.0, 25.0, WHITE);
+            }
+            if paused {
+                draw_text("PAUSED", GAME_WIDTH + 80.0, 50.0, 25.0, WHITE);
+            }
+            if let Some(movie) = &movie {
+                let status = match movie.mode {
+                    MovieMode::ReadOnly => format!("Movie: {}/{}", movie.frame(), movie.len()),
+                    MovieMode::ReadWrite => format!("Movie: {} (recording)", movie.frame()),
+                };
+                draw_text(&status, GAME_WIDTH + 10.0, 75.0, 25.0, WHITE);
+            }
+            #[cfg(feature = "instrumentation")]
+            draw_counters(&chip8);
         }
 
         next_frame().await
     }
 
+    if let Some(movie) = movie
+        && movie.mode == MovieMode::ReadWrite
+        && let Some(path) = record_movie_path.or(play_movie_path)
+    {
+        match movie.save(&path) {
+            Ok(()) => println!("Saved movie to {}", path.display()),
+            Err(err) => eprintln!("Unable to save movie: {err}"),
+        }
+    }
+
     println!("Goodbye");
 }
 
+/** Builds a unique file name for a screenshot or recording in the working directory */
+fn capture_path(prefix: &str, extension: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or_default();
+    PathBuf::from(format!("{prefix}-{timestamp}.{extension}"))
+}
+
+fn to_color(rgb: Rgb) -> Color {
+    Color::from_rgba(rgb[0], rgb[1], rgb[2], 255)
+}
+
 fn get_user_input() -> Option<u8> {
     let mut curr_key: Option<u8> = None;
 
@@ -154,3 +493,262 @@ fn get_user_input() -> Option<u8> {
     }
     curr_key
 }
+
+/** Scrolling, "follow I", sprite height and, while paused, inline editing of bytes and registers */
+fn handle_memory_input(chip8: &mut Chip8, view: &mut MemoryView, paused: bool) {
+    if is_key_pressed(KeyCode::I) {
+        view.follow_index = !view.follow_index;
+    }
+    if is_key_pressed(KeyCode::LeftBracket) {
+        view.sprite_height = view.sprite_height.saturating_sub(1).max(1);
+    }
+    if is_key_pressed(KeyCode::RightBracket) {
+        view.sprite_height = (view.sprite_height + 1).min(15);
+    }
+    if is_key_pressed(KeyCode::PageUp) {
+        view.scroll(-(MEMORY_ROWS as isize));
+    }
+    if is_key_pressed(KeyCode::PageDown) {
+        view.scroll(MEMORY_ROWS as isize);
+    }
+    let (_, wheel) = mouse_wheel();
+    if wheel != 0.0 {
+        view.scroll(if wheel > 0.0 { -2 } else { 2 });
+    }
+
+    if !paused {
+        view.select(None);
+        return;
+    }
+    if is_mouse_button_pressed(MouseButton::Left) {
+        let (x, y) = mouse_position();
+        view.select(memory_hit_test(view, x, y));
+    }
+    let Some(target) = view.editing() else {
+        return;
+    };
+    if let Some(digit) = typed_hex_digit() {
+        view.type_digit(chip8, digit);
+    }
+    // bytes move like a hex dump, registers down and across their columns
+    let (across, down) = match target {
+        EditTarget::Byte(_) => (1, BYTES_PER_ROW as isize),
+        EditTarget::Register(_) => (REGISTERS_PER_COLUMN as isize, 1),
+    };
+    if is_key_pressed(KeyCode::Left) {
+        view.move_selection(-across);
+    }
+    if is_key_pressed(KeyCode::Right) {
+        view.move_selection(across);
+    }
+    if is_key_pressed(KeyCode::Up) {
+        view.move_selection(-down);
+    }
+    if is_key_pressed(KeyCode::Down) {
+        view.move_selection(down);
+    }
+    if is_key_pressed(KeyCode::Enter) {
+        view.select(None);
+    }
+}
+
+fn typed_hex_digit() -> Option<u8> {
+    const KEYS: [KeyCode; 16] = [
+        KeyCode::Key0,
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+        KeyCode::A,
+        KeyCode::B,
+        KeyCode::C,
+        KeyCode::D,
+        KeyCode::E,
+        KeyCode::F,
+    ];
+    KEYS.iter()
+        .position(|&key| is_key_pressed(key))
+        .map(|digit| digit as u8)
+}
+
+/** Finds the byte or register drawn under a point of the memory panel */
+fn memory_hit_test(view: &MemoryView, x: f32, y: f32) -> Option<EditTarget> {
+    if y < MEMORY_TOP {
+        return None;
+    }
+    let row = ((y - MEMORY_TOP) / ROW_HEIGHT) as usize;
+    if (BYTE_X..BYTE_X + BYTES_PER_ROW as f32 * BYTE_WIDTH).contains(&x) && row < view.visible_rows
+    {
+        let addr = (view.top_row + row) * BYTES_PER_ROW + ((x - BYTE_X) / BYTE_WIDTH) as usize;
+        return (addr < 4096).then_some(EditTarget::Byte(addr));
+    }
+    if (REGISTER_X..REGISTER_X + 2.0 * REGISTER_WIDTH).contains(&x) && row < REGISTERS_PER_COLUMN {
+        let column = ((x - REGISTER_X) / REGISTER_WIDTH) as usize;
+        return Some(EditTarget::Register(
+            Register::ALL[column * REGISTERS_PER_COLUMN + row],
+        ));
+    }
+    None
+}
+
+/** Draws the hex dump, the sprite at I and the registers below the display */
+fn draw_memory(chip8: &Chip8, view: &MemoryView) {
+    let help = format!(
+        "Memory | Tab: sprites | I: follow I ({}) | [ ]: sprite height {} | P then click: edit",
+        if view.follow_index { "on" } else { "off" },
+        view.sprite_height
+    );
+    draw_text(&help, 10.0, GAME_HEIGHT + 20.0, 16.0, WHITE);
+    let selected = view.editing();
+    let row_baseline = |row: usize| MEMORY_TOP + (row + 1) as f32 * ROW_HEIGHT - 4.0;
+
+    for (row, start) in view.rows().enumerate() {
+        let y = row_baseline(row);
+        draw_text(&format!("{start:03X}"), 10.0, y, 18.0, GRAY);
+        for col in 0..BYTES_PER_ROW {
+            let addr = start + col;
+            let x = BYTE_X + col as f32 * BYTE_WIDTH;
+            let mut color = match view.highlight(chip8, addr) {
+                Highlight::Pc => RED,
+                Highlight::Index => YELLOW,
+                Highlight::Font => SKYBLUE,
+                Highlight::Rom => GREEN,
+                Highlight::None => GRAY,
+            };
+            if selected == Some(EditTarget::Byte(addr)) {
+                draw_rectangle(x - 2.0, y - 14.0, BYTE_WIDTH - 2.0, ROW_HEIGHT, WHITE);
+                color = BLACK;
+            }
+            draw_text(&format!("{:02X}", chip8.ram[addr]), x, y, 18.0, color);
+        }
+    }
+
+    draw_text("Sprite @ I", SPRITE_X, row_baseline(0), 16.0, WHITE);
+    for (row, byte) in view.sprite(chip8).iter().enumerate() {
+        for bit in 0..8 {
+            if byte & (0x80 >> bit) != 0 {
+                draw_rectangle(
+                    SPRITE_X + bit as f32 * SPRITE_SCALE,
+                    MEMORY_TOP + ROW_HEIGHT + row as f32 * SPRITE_SCALE,
+                    SPRITE_SCALE - 1.0,
+                    SPRITE_SCALE - 1.0,
+                    WHITE,
+                );
+            }
+        }
+    }
+
+    for (index, register) in Register::ALL.iter().enumerate() {
+        let x = REGISTER_X + (index / REGISTERS_PER_COLUMN) as f32 * REGISTER_WIDTH;
+        let y = row_baseline(index % REGISTERS_PER_COLUMN);
+        let text = format!(
+            "{:<3}{:0width$X}",
+            register.name(),
+            register.get(chip8),
+            width = register.digits()
+        );
+        let mut color = WHITE;
+        if selected == Some(EditTarget::Register(*register)) {
+            draw_rectangle(x - 2.0, y - 14.0, 70.0, ROW_HEIGHT, WHITE);
+            color = BLACK;
+        }
+        draw_text(&text, x, y, 18.0, color);
+    }
+
+    // the stack below the registers, oldest return address first
+    let depth = format!(
+        "Stack {}/{}",
+        chip8.call_stack.len(),
+        chip8.quirks.stack_depth
+    );
+    draw_text(&depth, REGISTER_X, row_baseline(STACK_ROW), 18.0, WHITE);
+    for (level, addr) in chip8.call_stack.iter().enumerate() {
+        let x = REGISTER_X + (level % STACK_COLUMNS) as f32 * STACK_WIDTH;
+        let y = row_baseline(STACK_ROW + 1 + level / STACK_COLUMNS);
+        let color = match level + 1 == chip8.call_stack.len() {
+            true => YELLOW,
+            false => GRAY,
+        };
+        draw_text(&format!("{addr:03X}"), x, y, 18.0, color);
+    }
+}
+
+/** Draws the loaded font and every sprite found in the ROM or drawn so far */
+fn draw_sprites(chip8: &Chip8, tracker: &SpriteTracker) {
+    let found = tracker.sprites();
+    let help = format!("Sprites | Tab: memory | {} found", found.len());
+    draw_text(&help, 10.0, GAME_HEIGHT + 20.0, 16.0, WHITE);
+
+    let font = SpriteSheet::loaded_font(chip8);
+    draw_sheet(&font, 10.0, MEMORY_TOP, SKYBLUE);
+    let sheet = SpriteSheet::from_sprites(&chip8.ram, &found, SHEET_COLUMNS);
+    let sprites_x = 20.0 + font.width() as f32 * SHEET_SCALE;
+    draw_sheet(&sheet, sprites_x, MEMORY_TOP, WHITE);
+}
+
+/** Draws a sprite sheet's lit pixels, clipped to the bottom of the window */
+fn draw_sheet(sheet: &SpriteSheet, left: f32, top: f32, color: Color) {
+    let width = sheet.width();
+    for (index, _) in sheet.pixels().iter().enumerate().filter(|(_, lit)| **lit) {
+        let x = left + (index % width) as f32 * SHEET_SCALE;
+        let y = top + (index / width) as f32 * SHEET_SCALE;
+        if y + SHEET_SCALE <= GAME_HEIGHT + MEMORY_HEIGHT {
+            draw_rectangle(x, y, SHEET_SCALE, SHEET_SCALE, color);
+        }
+    }
+}
+
+/** Draws the instrumentation counters below the status lines of the debug panel */
+#[cfg(feature = "instrumentation")]
+fn draw_counters(chip8: &Chip8) {
+    let counters = chip8.counters();
+    let lines = [
+        format!(
+            "Instructions: {}",
+            short_count(counters.total_instructions())
+        ),
+        format!(
+            "Draws: {} Hits: {}",
+            short_count(counters.draws),
+            short_count(counters.collisions)
+        ),
+        format!("Timer writes: {}", short_count(counters.timer_writes)),
+    ];
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(
+            line,
+            GAME_WIDTH + 10.0,
+            110.0 + i as f32 * 20.0,
+            20.0,
+            WHITE,
+        );
+    }
+
+    // per opcode class, four to a row
+    for (class, count) in counters.instructions.iter().enumerate() {
+        let x = GAME_WIDTH + 10.0 + (class % 4) as f32 * 60.0;
+        let y = 180.0 + (class / 4) as f32 * 18.0;
+        draw_text(
+            &format!("{class:X}:{}", short_count(*count)),
+            x,
+            y,
+            16.0,
+            WHITE,
+        );
+    }
+}
+
+/** Formats large counts as e.g. 12k or 3.4M so they fit the debug panel */
+#[cfg(feature = "instrumentation")]
+fn short_count(count: u64) -> String {
+    match count {
+        0..1_000 => count.to_string(),
+        1_000..1_000_000 => format!("{}k", count / 1_000),
+        _ => format!("{:.1}M", count as f64 / 1_000_000.0),
+    }
+}
@@ -0,0 +1,248 @@
+/** Pixels across the MEGA-CHIP screen */
+pub const MEGA_WIDTH: usize = 256;
+/** Rows on the MEGA-CHIP screen */
+pub const MEGA_HEIGHT: usize = 192;
+/** I is 24 bits wide, so MEGA-CHIP programs can reach 16MB */
+pub const MEGA_MEMORY: usize = 0x100_0000;
+/** Sprites below this address, such as the font, are drawn as 1-bit sprites even in MEGA mode */
+pub const MEGA_SPRITES_START: usize = 0x200;
+/** Bytes before a digitised sound's samples: a 16-bit rate, a 24-bit length and one reserved */
+pub const SOUND_HEADER_LEN: usize = 6;
+
+/** A colour with its alpha, in that order */
+pub type Rgba = [u8; 4];
+
+/** How 080N mixes sprite pixels with what is already on screen */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Alpha25,
+    Alpha50,
+    Alpha75,
+    Add,
+    Multiply,
+}
+impl BlendMode {
+    /** In the order 080N numbers them */
+    pub const ALL: [BlendMode; 6] = [
+        BlendMode::Normal,
+        BlendMode::Alpha25,
+        BlendMode::Alpha50,
+        BlendMode::Alpha75,
+        BlendMode::Add,
+        BlendMode::Multiply,
+    ];
+
+    /** The mode 080N selects, if N names one */
+    pub fn from_nibble(n: u8) -> Option<BlendMode> {
+        BlendMode::ALL.get(n as usize).copied()
+    }
+
+    /** Mixes a sprite pixel over a screen pixel. The result is always opaque */
+    pub fn blend(&self, sprite: Rgba, screen: Rgba) -> Rgba {
+        let mix = |weight: u16| {
+            let channel = |i: usize| {
+                ((sprite[i] as u16 * weight + screen[i] as u16 * (4 - weight)) / 4) as u8
+            };
+            [channel(0), channel(1), channel(2), 0xFF]
+        };
+        match self {
+            BlendMode::Normal => [sprite[0], sprite[1], sprite[2], 0xFF],
+            BlendMode::Alpha25 => mix(1),
+            BlendMode::Alpha50 => mix(2),
+            BlendMode::Alpha75 => mix(3),
+            BlendMode::Add => {
+                let channel = |i: usize| sprite[i].saturating_add(screen[i]);
+                [channel(0), channel(1), channel(2), 0xFF]
+            }
+            BlendMode::Multiply => {
+                let channel = |i: usize| (sprite[i] as u16 * screen[i] as u16 / 0xFF) as u8;
+                [channel(0), channel(1), channel(2), 0xFF]
+            }
+        }
+    }
+}
+
+/**
+ * The MEGA-CHIP screen and drawing state. Sprites are drawn into `pixels`, which 00E0 presents
+ * as `frame` and then clears, so programs only ever show finished frames
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
+    /** Whether 0011 switched to the 256x192 screen; until then the platform is plain CHIP-8 */
+    pub enabled: bool,
+    /** Colours loaded by 02NN. Sprite bytes index it and entry 0 is transparent */
+    pub palette: [Rgba; 256],
+    /** Width of MEGA sprites in pixels, set by 03NN */
+    pub sprite_width: usize,
+    /** Height of MEGA sprites in pixels, set by 04NN */
+    pub sprite_height: usize,
+    /** Opacity of the whole screen, set by 05NN for fades */
+    pub alpha: u8,
+    pub blend: BlendMode,
+    /** The frame being drawn, row by row */
+    pub pixels: Vec<Rgba>,
+    /** The frame last presented by 00E0 */
+    pub frame: Vec<Rgba>,
+}
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Framebuffer {
+            enabled: false,
+            palette: [[0; 4]; 256],
+            sprite_width: 8,
+            sprite_height: 8,
+            alpha: 0xFF,
+            blend: BlendMode::Normal,
+            pixels: vec![[0; 4]; MEGA_WIDTH * MEGA_HEIGHT],
+            frame: vec![[0; 4]; MEGA_WIDTH * MEGA_HEIGHT],
+        }
+    }
+}
+impl Framebuffer {
+    /** Loads colours stored as ARGB into the palette from entry 1 on, as 02NN does */
+    pub fn load_palette(&mut self, argb: &[u8]) {
+        for (entry, color) in self.palette[1..].iter_mut().zip(argb.chunks_exact(4)) {
+            *entry = [color[1], color[2], color[3], color[0]];
+        }
+    }
+
+    /** Sets the sprite width as 03NN does, where 0 stands for 256 */
+    pub fn set_sprite_width(&mut self, nn: u8) {
+        self.sprite_width = sprite_size(nn);
+    }
+
+    /** Sets the sprite height as 04NN does, where 0 stands for 256 */
+    pub fn set_sprite_height(&mut self, nn: u8) {
+        self.sprite_height = sprite_size(nn);
+    }
+
+    /** Bytes a sprite drawn from `addr` takes up */
+    pub fn sprite_len(&self, addr: usize, n: u8) -> usize {
+        match addr < MEGA_SPRITES_START {
+            true => n as usize,
+            false => self.sprite_width * self.sprite_height,
+        }
+    }
+
+    /**
+     * Draws a sprite of palette indices, `sprite_width` by `sprite_height`, clipped at the
+     * screen edges. Returns whether it covered a pixel already drawn this frame
+     */
+    pub fn draw_sprite(&mut self, x: usize, y: usize, indices: &[u8]) -> bool {
+        let mut collision = false;
+        for (row, line) in indices.chunks(self.sprite_width).enumerate() {
+            for (col, &index) in line.iter().enumerate() {
+                if index != 0 {
+                    collision |= self.plot(x + col, y + row, self.palette[index as usize]);
+                }
+            }
+        }
+        collision
+    }
+
+    /** Draws a 1-bit sprite, such as a font glyph, in opaque white */
+    pub fn draw_bits(&mut self, x: usize, y: usize, rows: &[u8]) -> bool {
+        let mut collision = false;
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..8 {
+                if bits >> (7 - col) & 1 == 1 {
+                    collision |= self.plot(x + col, y + row, [0xFF; 4]);
+                }
+            }
+        }
+        collision
+    }
+
+    fn plot(&mut self, x: usize, y: usize, color: Rgba) -> bool {
+        if x >= MEGA_WIDTH || y >= MEGA_HEIGHT {
+            return false;
+        }
+        let pixel = &mut self.pixels[y * MEGA_WIDTH + x];
+        let covered = pixel[3] != 0;
+        *pixel = self.blend.blend(color, *pixel);
+        covered
+    }
+
+    /** Shows the frame drawn so far and starts a blank one, as 00E0 does in MEGA mode */
+    pub fn present(&mut self) {
+        self.frame.copy_from_slice(&self.pixels);
+        self.pixels.fill([0; 4]);
+    }
+
+    /** Wipes both frames, as switching modes does */
+    pub fn clear(&mut self) {
+        self.pixels.fill([0; 4]);
+        self.frame.fill([0; 4]);
+    }
+
+    /** The presented frame faded by the screen alpha, 4 opaque bytes per pixel */
+    pub fn to_rgba(&self) -> Vec<u8> {
+        let fade = |channel: u8| (channel as u16 * self.alpha as u16 / 0xFF) as u8;
+        self.frame
+            .iter()
+            .flat_map(|pixel| [fade(pixel[0]), fade(pixel[1]), fade(pixel[2]), 0xFF])
+            .collect()
+    }
+}
+
+fn sprite_size(nn: u8) -> usize {
+    match nn {
+        0 => 256,
+        _ => nn as usize,
+    }
+}
+
+/** A digitised sound started by 060N: unsigned 8-bit mono samples following a header at I */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DigitalSound {
+    /** Address of the header */
+    pub addr: u32,
+    pub sample_rate: u16,
+    /** Number of samples */
+    pub len: u32,
+    /** 0600 loops until 0700 stops it, other 060N play once */
+    pub looping: bool,
+}
+impl DigitalSound {
+    /** Reads the header at `addr`, or None if it runs past the end of memory */
+    pub fn from_header(ram: &[u8], addr: u32, looping: bool) -> Option<DigitalSound> {
+        let header = ram.get(addr as usize..addr as usize + SOUND_HEADER_LEN)?;
+        Some(DigitalSound {
+            addr,
+            sample_rate: u16::from_be_bytes([header[0], header[1]]),
+            len: u32::from_be_bytes([0, header[2], header[3], header[4]]),
+            looping,
+        })
+    }
+
+    /** The samples, cut short if they run past the end of memory */
+    pub fn samples<'a>(&self, ram: &'a [u8]) -> &'a [u8] {
+        let start = (self.addr as usize + SOUND_HEADER_LEN).min(ram.len());
+        let end = (start + self.len as usize).min(ram.len());
+        &ram[start..end]
+    }
+
+    /** The samples as a WAV file, which is what frontends' audio libraries load */
+    pub fn to_wav(&self, ram: &[u8]) -> Vec<u8> {
+        let samples = self.samples(ram);
+        let rate = self.sample_rate as u32;
+        let mut wav = Vec::with_capacity(44 + samples.len());
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + samples.len() as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        // PCM, one channel, one byte per sample
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&rate.to_le_bytes());
+        wav.extend_from_slice(&rate.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&8u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        wav.extend_from_slice(samples);
+        wav
+    }
+}
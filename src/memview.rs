@@ -0,0 +1,237 @@
+use crate::Chip8;
+
+pub const BYTES_PER_ROW: usize = 16;
+pub const ROWS: usize = 4096 / BYTES_PER_ROW;
+pub const ROM_START: usize = 0x200;
+
+/** How a byte in the dump is highlighted, most important first */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Highlight {
+    Pc,
+    Index,
+    Font,
+    Rom,
+    None,
+}
+
+/** A register that can be shown and edited next to the dump */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    V(u8),
+    I,
+    Pc,
+    Delay,
+    Sound,
+}
+impl Register {
+    /** V0 to VF followed by I, PC and the timers, in display order */
+    pub const ALL: [Register; 20] = [
+        Register::V(0x0),
+        Register::V(0x1),
+        Register::V(0x2),
+        Register::V(0x3),
+        Register::V(0x4),
+        Register::V(0x5),
+        Register::V(0x6),
+        Register::V(0x7),
+        Register::V(0x8),
+        Register::V(0x9),
+        Register::V(0xA),
+        Register::V(0xB),
+        Register::V(0xC),
+        Register::V(0xD),
+        Register::V(0xE),
+        Register::V(0xF),
+        Register::I,
+        Register::Pc,
+        Register::Delay,
+        Register::Sound,
+    ];
+
+    pub fn name(&self) -> String {
+        match self {
+            Register::V(x) => format!("V{x:X}"),
+            Register::I => "I".to_owned(),
+            Register::Pc => "PC".to_owned(),
+            Register::Delay => "DT".to_owned(),
+            Register::Sound => "ST".to_owned(),
+        }
+    }
+
+    /** Number of hex digits shown and typed: 3 for addresses, 2 for everything else */
+    pub fn digits(&self) -> usize {
+        match self {
+            Register::I | Register::Pc => 3,
+            _ => 2,
+        }
+    }
+
+    pub fn get(&self, chip8: &Chip8) -> u16 {
+        match self {
+            Register::V(x) => chip8.var_reg[*x as usize] as u16,
+            Register::I => chip8.idx_reg as u16,
+            Register::Pc => chip8.pc,
+            Register::Delay => chip8.delay_timer as u16,
+            Register::Sound => chip8.sound_timer as u16,
+        }
+    }
+
+    /** Writes a value, truncated to the register's width */
+    pub fn set(&self, chip8: &mut Chip8, value: u16) {
+        match self {
+            Register::V(x) => chip8.var_reg[*x as usize] = value as u8,
+            Register::I => chip8.idx_reg = u32::from(value & 0xFFF),
+            Register::Pc => chip8.pc = value & 0xFFF,
+            Register::Delay => chip8.delay_timer = value as u8,
+            Register::Sound => chip8.sound_timer = value as u8,
+        }
+    }
+}
+
+/** What the editor is currently typing into */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditTarget {
+    Byte(usize),
+    Register(Register),
+}
+
+/**
+ * State of the hex dump panel: scrolling, highlighting, inline editing and the sprite preview.
+ * Edits go straight into the machine; call `Engine::invalidate_all` afterwards when using a caching engine.
+ */
+#[derive(Clone, Debug)]
+pub struct MemoryView {
+    /** First row shown, in units of `BYTES_PER_ROW` */
+    pub top_row: usize,
+    pub visible_rows: usize,
+    /** Keeps the row holding I in view as it changes */
+    pub follow_index: bool,
+    /** Number of bytes at I drawn as a sprite, from 1 to 15 like DXYN */
+    pub sprite_height: usize,
+    /** End of the loaded ROM, for highlighting */
+    pub rom_end: usize,
+    editing: Option<EditTarget>,
+    typed_digits: usize,
+}
+impl MemoryView {
+    pub fn new(rom_len: usize, visible_rows: usize) -> MemoryView {
+        MemoryView {
+            top_row: ROM_START / BYTES_PER_ROW,
+            visible_rows,
+            follow_index: false,
+            sprite_height: 5,
+            rom_end: (ROM_START + rom_len).min(4096),
+            editing: None,
+            typed_digits: 0,
+        }
+    }
+
+    /** Addresses of the rows currently shown */
+    pub fn rows(&self) -> impl Iterator<Item = usize> {
+        (self.top_row..(self.top_row + self.visible_rows).min(ROWS)).map(|row| row * BYTES_PER_ROW)
+    }
+
+    pub fn highlight(&self, chip8: &Chip8, addr: usize) -> Highlight {
+        let pc = chip8.pc as usize;
+        if addr == pc || addr == pc + 1 {
+            Highlight::Pc
+        } else if addr == chip8.idx_reg as usize {
+            Highlight::Index
+        } else if chip8.font_region().contains(&addr) {
+            Highlight::Font
+        } else if (ROM_START..self.rom_end).contains(&addr) {
+            Highlight::Rom
+        } else {
+            Highlight::None
+        }
+    }
+
+    /** Scrolls by a number of rows. Scrolling by hand turns off "follow I" */
+    pub fn scroll(&mut self, rows: isize) {
+        let last = ROWS.saturating_sub(self.visible_rows);
+        self.top_row = self.top_row.saturating_add_signed(rows).min(last);
+        self.follow_index = false;
+    }
+
+    /** Brings I into view when following it. Call once per frame */
+    pub fn update(&mut self, chip8: &Chip8) {
+        if !self.follow_index {
+            return;
+        }
+        let row = (chip8.idx_reg as usize % 4096) / BYTES_PER_ROW;
+        if row < self.top_row || row >= self.top_row + self.visible_rows {
+            let last = ROWS.saturating_sub(self.visible_rows);
+            self.top_row = row.saturating_sub(self.visible_rows / 2).min(last);
+        }
+    }
+
+    /** The bytes at I drawn by the sprite preview, wrapping around the end of memory */
+    pub fn sprite(&self, chip8: &Chip8) -> Vec<u8> {
+        (0..self.sprite_height)
+            .map(|offset| chip8.ram[(chip8.idx_reg as usize + offset) % 4096])
+            .collect()
+    }
+
+    pub fn editing(&self) -> Option<EditTarget> {
+        self.editing
+    }
+
+    /** Starts editing a byte or register, or stops with `None` */
+    pub fn select(&mut self, target: Option<EditTarget>) {
+        self.editing = target;
+        self.typed_digits = 0;
+    }
+
+    /**
+     * Shifts a hex digit into the selected byte or register. Once all its digits are typed
+     * the selection moves on to the next one, like a hex editor
+     */
+    pub fn type_digit(&mut self, chip8: &mut Chip8, digit: u8) {
+        let digit = (digit & 0xF) as u16;
+        match self.editing {
+            Some(EditTarget::Byte(addr)) => {
+                chip8.ram[addr] = (chip8.ram[addr] << 4) | digit as u8;
+                self.typed_digits += 1;
+                if self.typed_digits == 2 {
+                    self.move_selection(1);
+                }
+            }
+            Some(EditTarget::Register(register)) => {
+                register.set(chip8, (register.get(chip8) << 4) | digit);
+                self.typed_digits += 1;
+                if self.typed_digits == register.digits() {
+                    self.move_selection(1);
+                }
+            }
+            None => {}
+        }
+    }
+
+    /** Moves the selection by a number of bytes or registers, keeping it in range */
+    pub fn move_selection(&mut self, delta: isize) {
+        let target = match self.editing {
+            Some(EditTarget::Byte(addr)) => {
+                let addr = addr.saturating_add_signed(delta).min(4095);
+                let row = addr / BYTES_PER_ROW;
+                if row < self.top_row {
+                    self.top_row = row;
+                } else if row >= self.top_row + self.visible_rows {
+                    self.top_row = row + 1 - self.visible_rows;
+                }
+                EditTarget::Byte(addr)
+            }
+            Some(EditTarget::Register(register)) => {
+                let index = Register::ALL
+                    .iter()
+                    .position(|r| *r == register)
+                    .unwrap_or(0);
+                let index = index
+                    .saturating_add_signed(delta)
+                    .min(Register::ALL.len() - 1);
+                EditTarget::Register(Register::ALL[index])
+            }
+            None => return,
+        };
+        self.select(Some(target));
+    }
+}
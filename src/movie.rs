@@ -0,0 +1,249 @@
+use crate::Chip8;
+use crate::font::Font;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use crate::rng::Generator;
+use crate::timing::Timing;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/** First line of every movie file, followed by the format version */
+const MAGIC: &str = "CHIP8MOVIE";
+const VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MovieMode {
+    /** Inputs come from the movie and live input is ignored */
+    ReadOnly,
+    /** Live input is recorded, overwriting anything after the current frame */
+    ReadWrite,
+}
+
+/** Everything that has to match for a movie to play back identically */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MovieHeader {
+    /** SHA-1 of the ROM the movie was recorded with, see `ROM::sha1` */
+    pub rom_sha1: String,
+    pub quirks: Quirks,
+    pub seed: u64,
+    pub rng: Generator,
+    pub cycles_per_frame: usize,
+    pub timing: Timing,
+    pub platform: Platform,
+    pub font: Font,
+    pub font_addr: u16,
+}
+
+/** Per-frame record of the 16-key input state, one bit per key */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Movie {
+    pub header: MovieHeader,
+    pub mode: MovieMode,
+    inputs: Vec<u16>,
+    frame: usize,
+}
+impl Movie {
+    /** Starts an empty movie ready for recording */
+    pub fn new(header: MovieHeader) -> Movie {
+        Movie {
+            header,
+            mode: MovieMode::ReadWrite,
+            inputs: Vec::new(),
+            frame: 0,
+        }
+    }
+
+    /**
+     * Creates a machine configured with the movie's seed, generator, quirks, timing, platform and font.
+     * Fails if the font doesn't fit at its address. The ROM still has to be loaded
+     */
+    pub fn create_machine(&self) -> Result<Chip8, String> {
+        let mut chip8 = Chip8::with_seed(self.header.seed);
+        chip8.set_generator(self.header.rng);
+        chip8.quirks = self.header.quirks;
+        chip8.timing = self.header.timing;
+        chip8.set_platform(self.header.platform);
+        chip8.load_font(&self.header.font, self.header.font_addr)?;
+        Ok(chip8)
+    }
+
+    /** The next frame to be played or recorded */
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /** Number of frames of input in the movie */
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    /** Whether playback has run past the last recorded frame */
+    pub fn is_finished(&self) -> bool {
+        self.mode == MovieMode::ReadOnly && self.frame >= self.inputs.len()
+    }
+
+    pub fn inputs(&self) -> &[u16] {
+        &self.inputs
+    }
+
+    /**
+     * Advances one frame and returns the input to feed the machine. Read-only movies
+     * replay their recorded input (and pass live input through once finished), while
+     * read-write movies record the live input.
+     */
+    pub fn next_input(&mut self, live: Option<u8>) -> Option<u8> {
+        let input = match self.mode {
+            MovieMode::ReadOnly => match self.inputs.get(self.frame) {
+                Some(&mask) => mask_key(mask),
+                None => live,
+            },
+            MovieMode::ReadWrite => {
+                self.inputs.truncate(self.frame);
+                self.inputs.push(key_mask(live));
+                live
+            }
+        };
+        self.frame += 1;
+        input
+    }
+
+    /** Feeds the next frame of input to the machine and runs that frame */
+    pub fn run_frame(&mut self, chip8: &mut Chip8, live: Option<u8>) {
+        let input = self.next_input(live);
+        chip8.set_input_key(input);
+        chip8.run_frame(self.header.cycles_per_frame);
+    }
+
+    /**
+     * Moves to a frame, e.g. after loading a save state taken at that frame.
+     * In read-write mode everything after it is dropped so recording branches from there.
+     */
+    pub fn seek(&mut self, frame: usize) {
+        self.frame = frame;
+        if self.mode == MovieMode::ReadWrite {
+            self.inputs.truncate(frame);
+        }
+    }
+
+    /** Writes the movie as text: a header followed by one hex key mask per frame */
+    pub fn to_text(&self) -> String {
+        let font: String = [&self.header.font.small[..], &self.header.font.big]
+            .concat()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        let mut text = format!(
+            "{MAGIC} {VERSION}\nrom {}\nquirks {}\nseed {}\nrng {}\ncycles {}\ntiming {}\nplatform {}\n\
+             font {font}\nfont-addr {:03x}\ninputs\n",
+            self.header.rom_sha1,
+            self.header.quirks.to_flags(),
+            self.header.seed,
+            self.header.rng.name(),
+            self.header.cycles_per_frame,
+            self.header.timing.name(),
+            self.header.platform.name(),
+            self.header.font_addr
+        );
+        for mask in &self.inputs {
+            text.push_str(&format!("{mask:04x}\n"));
+        }
+        text
+    }
+
+    /** Reads a movie written by `to_text`. Loaded movies start in read-only mode */
+    pub fn from_text(text: &str) -> Result<Movie, String> {
+        let mut lines = text.lines();
+        let version = header_field(lines.next(), MAGIC)?;
+        if version.parse::<u32>() != Ok(VERSION) {
+            return Err(format!("Unsupported movie version {version}"));
+        }
+        let rom_sha1 = header_field(lines.next(), "rom")?.to_owned();
+        let quirks = Quirks::from_flags(header_field(lines.next(), "quirks")?)?;
+        let seed = header_field(lines.next(), "seed")?
+            .parse()
+            .map_err(|_| "Invalid movie seed".to_owned())?;
+        let name = header_field(lines.next(), "rng")?;
+        let rng =
+            Generator::from_name(name).ok_or_else(|| format!("Unknown movie generator: {name}"))?;
+        let cycles_per_frame = header_field(lines.next(), "cycles")?
+            .parse()
+            .map_err(|_| "Invalid movie cycles".to_owned())?;
+        let name = header_field(lines.next(), "timing")?;
+        let timing =
+            Timing::from_name(name).ok_or_else(|| format!("Unknown movie timing: {name}"))?;
+        let name = header_field(lines.next(), "platform")?;
+        let platform =
+            Platform::from_name(name).ok_or_else(|| format!("Unknown movie platform: {name}"))?;
+        let hex = header_field(lines.next(), "font")?;
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                hex.get(i..i + 2)
+                    .and_then(|d| u8::from_str_radix(d, 16).ok())
+            })
+            .collect::<Option<Vec<u8>>>()
+            .ok_or("Invalid movie font")?;
+        let font = Font::from_bytes(&bytes)?;
+        let font_addr = u16::from_str_radix(header_field(lines.next(), "font-addr")?, 16)
+            .map_err(|_| "Invalid movie font address".to_owned())?;
+        if lines.next() != Some("inputs") {
+            return Err("Missing movie inputs".to_owned());
+        }
+
+        let inputs = lines
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                u16::from_str_radix(line, 16).map_err(|_| format!("Invalid movie input: {line}"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Movie {
+            header: MovieHeader {
+                rom_sha1,
+                quirks,
+                seed,
+                rng,
+                cycles_per_frame,
+                timing,
+                platform,
+                font,
+                font_addr,
+            },
+            mode: MovieMode::ReadOnly,
+            inputs,
+            frame: 0,
+        })
+    }
+
+    pub fn load(path: &Path) -> io::Result<Movie> {
+        let text = fs::read_to_string(path)?;
+        Movie::from_text(&text).map_err(io::Error::other)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+}
+
+/** Returns the value after `name` on a "name value" header line */
+fn header_field<'a>(line: Option<&'a str>, name: &str) -> Result<&'a str, String> {
+    line.and_then(|line| line.strip_prefix(name))
+        .and_then(|rest| rest.strip_prefix(' ').or(rest.is_empty().then_some(rest)))
+        .map(str::trim)
+        .ok_or_else(|| format!("Missing movie header field: {name}"))
+}
+
+/** Converts the currently pressed key to a 16-key bitmask */
+pub fn key_mask(key: Option<u8>) -> u16 {
+    key.map(|key| 1 << (key & 0xF)).unwrap_or(0)
+}
+
+/** Converts a 16-key bitmask back to a pressed key, picking the lowest if several are held */
+pub fn mask_key(mask: u16) -> Option<u8> {
+    (mask != 0).then(|| mask.trailing_zeros() as u8)
+}
@@ -0,0 +1,151 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/** An RGB colour */
+pub type Rgb = [u8; 3];
+
+/** Colours used to draw the display: the background, then lit pixels */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [Rgb; 2],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    OctoClassic,
+    Amber,
+    GreenPhosphor,
+    Lcd,
+    HighContrast,
+}
+impl Theme {
+    pub const ALL: [Theme; 5] = [
+        Theme::OctoClassic,
+        Theme::Amber,
+        Theme::GreenPhosphor,
+        Theme::Lcd,
+        Theme::HighContrast,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Theme::OctoClassic => "octo",
+            Theme::Amber => "amber",
+            Theme::GreenPhosphor => "green",
+            Theme::Lcd => "lcd",
+            Theme::HighContrast => "contrast",
+        }
+    }
+
+    /** Looks a theme up by the name returned from `Theme::name` */
+    pub fn from_name(name: &str) -> Option<Theme> {
+        Theme::ALL
+            .into_iter()
+            .find(|theme| theme.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn palette(&self) -> Palette {
+        let colors = match self {
+            Theme::OctoClassic => [[0x99, 0x66, 0x00], [0xFF, 0xCC, 0x00]],
+            Theme::Amber => [[0x1A, 0x0F, 0x00], [0xFF, 0xB0, 0x00]],
+            Theme::GreenPhosphor => [[0x05, 0x14, 0x05], [0x33, 0xFF, 0x33]],
+            Theme::Lcd => [[0xC7, 0xF0, 0xD8], [0x43, 0x52, 0x3D]],
+            Theme::HighContrast => [[0x00, 0x00, 0x00], [0xFF, 0xFF, 0xFF]],
+        };
+        Palette { colors }
+    }
+}
+
+impl Default for Palette {
+    /** White pixels on a black background */
+    fn default() -> Self {
+        Palette {
+            colors: [[0x00, 0x00, 0x00], [0xFF, 0xFF, 0xFF]],
+        }
+    }
+}
+impl Palette {
+    /** Parses a background and foreground as comma separated hex colours, e.g. "#000000,#FFFFFF" */
+    pub fn from_hex(hex: &str) -> Result<Palette, String> {
+        let parsed: Vec<Rgb> = hex
+            .split(',')
+            .map(|color| parse_hex_color(color.trim()))
+            .collect::<Result<_, _>>()?;
+        let colors = parsed.try_into().map_err(|parsed: Vec<Rgb>| {
+            format!("Expected 2 colours in palette, found {}", parsed.len())
+        })?;
+        Ok(Palette { colors })
+    }
+
+    pub fn background(&self) -> Rgb {
+        self.colors[0]
+    }
+
+    pub fn foreground(&self) -> Rgb {
+        self.colors[1]
+    }
+
+    /** Mixes the background and foreground by a pixel intensity between 0.0 and 1.0 */
+    pub fn shade(&self, intensity: f32) -> Rgb {
+        let t = intensity.clamp(0.0, 1.0);
+        let (bg, fg) = (self.background(), self.foreground());
+        std::array::from_fn(|i| (bg[i] as f32 + (fg[i] as f32 - bg[i] as f32) * t).round() as u8)
+    }
+}
+
+fn parse_hex_color(color: &str) -> Result<Rgb, String> {
+    let digits = color.strip_prefix('#').unwrap_or(color);
+    if digits.len() != 6 || !digits.is_ascii() {
+        return Err(format!("Invalid hex colour: {color}"));
+    }
+
+    let mut rgb = [0; 3];
+    for (i, channel) in rgb.iter_mut().enumerate() {
+        *channel = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16)
+            .map_err(|_| format!("Invalid hex colour: {color}"))?;
+    }
+    Ok(rgb)
+}
+
+/**
+ * Palettes for particular ROMs, looked up by `ROM::sha1`. Database files have one ROM per line:
+ * its SHA-1, then a theme name or hex colours as for `Palette::from_hex`. Blank lines and lines
+ * starting with '#' are skipped
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PaletteDatabase {
+    palettes: Vec<(String, Palette)>,
+}
+impl PaletteDatabase {
+    pub fn from_text(text: &str) -> Result<PaletteDatabase, String> {
+        let mut palettes = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (sha1, colors) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| format!("Missing palette for ROM: {line}"))?;
+            let palette = match Theme::from_name(colors.trim()) {
+                Some(theme) => theme.palette(),
+                None => Palette::from_hex(colors)?,
+            };
+            palettes.push((sha1.to_ascii_lowercase(), palette));
+        }
+        Ok(PaletteDatabase { palettes })
+    }
+
+    pub fn load(path: &Path) -> io::Result<PaletteDatabase> {
+        let text = fs::read_to_string(path)?;
+        PaletteDatabase::from_text(&text).map_err(io::Error::other)
+    }
+
+    /** The palette for the ROM with this SHA-1, if the database has one */
+    pub fn lookup(&self, sha1: &str) -> Option<Palette> {
+        self.palettes
+            .iter()
+            .find(|(entry, _)| entry.eq_ignore_ascii_case(sha1))
+            .map(|(_, palette)| *palette)
+    }
+}
@@ -0,0 +1,95 @@
+/** Where CHIP-8X programs start, after its larger interpreter */
+pub const CHIP8X_START: u16 = 0x300;
+/** Where HiRes CHIP-8 programs really start: their first instruction, 1260, jumps here */
+pub const HIRES_START: u16 = 0x2C0;
+/** Rows on the two-page HiRes CHIP-8 display */
+pub const HIRES_HEIGHT: usize = 64;
+
+/**
+ * Which historical CHIP-8 interpreter to emulate. Each adds opcodes on top of the original
+ * set and may load programs elsewhere or use a different display
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Platform {
+    /** The original COSMAC VIP interpreter: 64x32, programs at 0x200 */
+    #[default]
+    Chip8,
+    /**
+     * CHIP-8X for the VP-590 colour board: 02A0 cycles the background colour, BXY0 and BXYN
+     * colour areas of the screen, 5XY1 adds nibbles, EXF2/EXF5 read the second keypad and
+     * FXF8/FXFB use the I/O port. Programs start at 0x300
+     */
+    Chip8X,
+    /**
+     * CHIP-8E: 00ED stops, 00F2 does nothing, 0151 waits for the delay timer, 0188 skips,
+     * 5XY1 skips if VX > VY, 5XY2/5XY3 store and load VX to VY, BBNN/BFNN branch back or
+     * forward, FX1B skips VX bytes, FX4F waits VX ticks and FX03/FXE3/FXE7 use port 3
+     */
+    Chip8E,
+    /**
+     * The two-page HiRes CHIP-8: a 64x64 display, 0230 clears it and the 1260 at the start of
+     * every program jumps to 0x2C0
+     */
+    HiRes,
+    /**
+     * MEGA-CHIP: plain CHIP-8 until 0011 switches to a 256x192 colour screen and 0010 back.
+     * 01NN NNNN loads a 24-bit I, 02NN loads a palette, 03NN/04NN size sprites, 05NN fades
+     * the screen, 060N/0700 play and stop digitised sound and 080N picks a blend mode
+     */
+    MegaChip,
+}
+impl Platform {
+    pub const ALL: [Platform; 5] = [
+        Platform::Chip8,
+        Platform::Chip8X,
+        Platform::Chip8E,
+        Platform::HiRes,
+        Platform::MegaChip,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Platform::Chip8 => "chip8",
+            Platform::Chip8X => "chip8x",
+            Platform::Chip8E => "chip8e",
+            Platform::HiRes => "hires",
+            Platform::MegaChip => "megachip",
+        }
+    }
+
+    /** Looks a platform up by the name returned from `Platform::name` */
+    pub fn from_name(name: &str) -> Option<Platform> {
+        Platform::ALL
+            .into_iter()
+            .find(|platform| platform.name().eq_ignore_ascii_case(name))
+    }
+
+    /** Where `Chip8::load_rom` puts programs and starts running them */
+    pub fn load_address(&self) -> u16 {
+        match self {
+            Platform::Chip8X => CHIP8X_START,
+            _ => 0x200,
+        }
+    }
+
+    /** Bytes of memory a program can address: 4KB, or 16MB for MEGA-CHIP's 24-bit I */
+    pub fn memory_size(&self) -> usize {
+        match self {
+            Platform::MegaChip => crate::megachip::MEGA_MEMORY,
+            _ => 4096,
+        }
+    }
+
+    /** Rows on the 1-bit display; every platform is 64 pixels wide */
+    pub fn display_height(&self) -> usize {
+        match self {
+            Platform::HiRes => HIRES_HEIGHT,
+            _ => crate::display::HEIGHT,
+        }
+    }
+
+    /** Whether the display has the CHIP-8X colour board */
+    pub fn has_color(&self) -> bool {
+        *self == Platform::Chip8X
+    }
+}
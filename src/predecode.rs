@@ -0,0 +1,415 @@
+use crate::engine::Engine;
+use crate::error::Chip8Error;
+use crate::megachip::SOUND_HEADER_LEN;
+use crate::opcode::Opcode;
+use crate::platform::Platform;
+use crate::{Chip8, stack_slot};
+
+/**
+ * A decoded instruction: which handler runs it plus the operands it needs, pulled out of
+ * the instruction once.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Op {
+    /** 00E0 */
+    ClearScreen,
+    /** 00EE */
+    Return,
+    /** 0NNN, a machine code subroutine */
+    MachineCall(u16),
+    /** 1NNN */
+    Jump(u16),
+    /** 2NNN */
+    Call(u16),
+    /** 3XNN */
+    SkipEqImm(u8, u8),
+    /** 4XNN */
+    SkipNeImm(u8, u8),
+    /** 5XY0 */
+    SkipEq(u8, u8),
+    /** 6XNN */
+    SetImm(u8, u8),
+    /** 7XNN */
+    AddImm(u8, u8),
+    /** 8XY0 */
+    Set(u8, u8),
+    /** 8XY1 */
+    Or(u8, u8),
+    /** 8XY2 */
+    And(u8, u8),
+    /** 8XY3 */
+    Xor(u8, u8),
+    /** 8XY4 */
+    Add(u8, u8),
+    /** 8XY5 */
+    Sub(u8, u8),
+    /** 8XY6 */
+    ShiftRight(u8, u8),
+    /** 8XY7 */
+    SubReverse(u8, u8),
+    /** 8XYE */
+    ShiftLeft(u8, u8),
+    /** 9XY0 */
+    SkipNe(u8, u8),
+    /** ANNN */
+    SetIndex(u16),
+    /** BNNN */
+    JumpOffset(u16),
+    /** CXNN */
+    Random(u8, u8),
+    /** DXYN */
+    Draw(u8, u8, u8),
+    /** EX9E */
+    SkipKey(u8),
+    /** EXA1 */
+    SkipNotKey(u8),
+    /** FX07 */
+    GetDelay(u8),
+    /** FX0A */
+    WaitKey(u8),
+    /** FX15 */
+    SetDelay(u8),
+    /** FX18 */
+    SetSound(u8),
+    /** FX1E */
+    AddIndex(u8),
+    /** FX29 */
+    FontChar(u8),
+    /** FX30 */
+    BigFontChar(u8),
+    /** FX33 */
+    Bcd(u8),
+    /** FX55 */
+    Store(u8),
+    /** FX65 */
+    Load(u8),
+    /** CHIP-8X 02A0 */
+    CycleBackground,
+    /** CHIP-8X 5XY1 */
+    AddNibbles(u8, u8),
+    /** CHIP-8X BXYN, including BXY0 */
+    Color(u8, u8, u8),
+    /** CHIP-8X EXF2 */
+    SkipKey2(u8),
+    /** CHIP-8X EXF5 */
+    SkipNotKey2(u8),
+    /** CHIP-8X FXF8, CHIP-8E FX03 */
+    Output(u8),
+    /** CHIP-8X FXFB, CHIP-8E FXE3 and FXE7 */
+    Input(u8),
+    /** CHIP-8E 00ED */
+    Stop,
+    /** CHIP-8E 00F2 */
+    Nop,
+    /** CHIP-8E 0151 */
+    WaitDelay,
+    /** CHIP-8E 0188 */
+    Skip,
+    /** CHIP-8E 5XY1 */
+    SkipGreater(u8, u8),
+    /** CHIP-8E 5XY2 */
+    StoreRange(u8, u8),
+    /** CHIP-8E 5XY3 */
+    LoadRange(u8, u8),
+    /** CHIP-8E BBNN */
+    BranchBack(u8),
+    /** CHIP-8E BFNN */
+    BranchForward(u8),
+    /** CHIP-8E FX1B */
+    SkipBytes(u8),
+    /** CHIP-8E FX4F */
+    DelayWait(u8),
+    /** MEGA-CHIP 0010 and 0011 */
+    MegaMode(bool),
+    /** MEGA-CHIP 01NN NNNN, whose second word the op reads itself */
+    LongIndex(u8),
+    /** MEGA-CHIP 02NN */
+    LoadPalette(u8),
+    /** MEGA-CHIP 03NN */
+    SpriteWidth(u8),
+    /** MEGA-CHIP 04NN */
+    SpriteHeight(u8),
+    /** MEGA-CHIP 05NN */
+    ScreenAlpha(u8),
+    /** MEGA-CHIP 060N */
+    PlaySound(u8),
+    /** MEGA-CHIP 0700 */
+    StopSound,
+    /** MEGA-CHIP 080N */
+    Blend(u8),
+    Unsupported(u16),
+}
+impl Op {
+    /** Decodes an instruction, following the same table as `Chip8::cycle` */
+    pub(crate) fn decode(instruction: u16) -> Op {
+        let opcode = Opcode::new(instruction);
+        let (x, y) = (opcode.x, opcode.y);
+        match (opcode.w, opcode.x, opcode.y, opcode.n) {
+            (0x0, 0x0, 0xE, 0x0) => Op::ClearScreen,
+            (0x0, 0x0, 0xE, 0xE) => Op::Return,
+            (0x0, _, _, _) => Op::MachineCall(opcode.nnn),
+            (0x1, _, _, _) => Op::Jump(opcode.nnn),
+            (0x2, _, _, _) => Op::Call(opcode.nnn),
+            (0x3, _, _, _) => Op::SkipEqImm(x, opcode.nn),
+            (0x4, _, _, _) => Op::SkipNeImm(x, opcode.nn),
+            (0x5, _, _, _) => Op::SkipEq(x, y),
+            (0x6, _, _, _) => Op::SetImm(x, opcode.nn),
+            (0x7, _, _, _) => Op::AddImm(x, opcode.nn),
+            (0x8, _, _, 0x0) => Op::Set(x, y),
+            (0x8, _, _, 0x1) => Op::Or(x, y),
+            (0x8, _, _, 0x2) => Op::And(x, y),
+            (0x8, _, _, 0x3) => Op::Xor(x, y),
+            (0x8, _, _, 0x4) => Op::Add(x, y),
+            (0x8, _, _, 0x5) => Op::Sub(x, y),
+            (0x8, _, _, 0x6) => Op::ShiftRight(x, y),
+            (0x8, _, _, 0x7) => Op::SubReverse(x, y),
+            (0x8, _, _, 0xE) => Op::ShiftLeft(x, y),
+            (0x9, _, _, _) => Op::SkipNe(x, y),
+            (0xA, _, _, _) => Op::SetIndex(opcode.nnn),
+            (0xB, _, _, _) => Op::JumpOffset(opcode.nnn),
+            (0xC, _, _, _) => Op::Random(x, opcode.nn),
+            (0xD, _, _, _) => Op::Draw(x, y, opcode.n),
+            (0xE, _, 0x9, 0xE) => Op::SkipKey(x),
+            (0xE, _, 0xA, 0x1) => Op::SkipNotKey(x),
+            (0xF, _, 0x0, 0x7) => Op::GetDelay(x),
+            (0xF, _, 0x0, 0xA) => Op::WaitKey(x),
+            (0xF, _, 0x1, 0x5) => Op::SetDelay(x),
+            (0xF, _, 0x1, 0x8) => Op::SetSound(x),
+            (0xF, _, 0x1, 0xE) => Op::AddIndex(x),
+            (0xF, _, 0x2, 0x9) => Op::FontChar(x),
+            (0xF, _, 0x3, 0x0) => Op::BigFontChar(x),
+            (0xF, _, 0x3, 0x3) => Op::Bcd(x),
+            (0xF, _, 0x5, 0x5) => Op::Store(x),
+            (0xF, _, 0x6, 0x5) => Op::Load(x),
+            _ => Op::Unsupported(instruction),
+        }
+    }
+
+    /** Decodes an instruction for a platform, which may add or redefine opcodes */
+    pub(crate) fn decode_for(instruction: u16, platform: Platform) -> Op {
+        let opcode = Opcode::new(instruction);
+        let (x, y) = (opcode.x, opcode.y);
+        match (platform, opcode.w, opcode.x, opcode.y, opcode.n) {
+            (Platform::Chip8X, 0x0, 0x2, 0xA, 0x0) => Op::CycleBackground,
+            (Platform::Chip8X, 0x5, _, _, 0x1) => Op::AddNibbles(x, y),
+            (Platform::Chip8X, 0xB, _, _, n) => Op::Color(x, y, n),
+            (Platform::Chip8X, 0xE, _, 0xF, 0x2) => Op::SkipKey2(x),
+            (Platform::Chip8X, 0xE, _, 0xF, 0x5) => Op::SkipNotKey2(x),
+            (Platform::Chip8X, 0xF, _, 0xF, 0x8) => Op::Output(x),
+            (Platform::Chip8X, 0xF, _, 0xF, 0xB) => Op::Input(x),
+            (Platform::Chip8E, 0x0, 0x0, 0xE, 0xD) => Op::Stop,
+            (Platform::Chip8E, 0x0, 0x0, 0xF, 0x2) => Op::Nop,
+            (Platform::Chip8E, 0x0, 0x1, 0x5, 0x1) => Op::WaitDelay,
+            (Platform::Chip8E, 0x0, 0x1, 0x8, 0x8) => Op::Skip,
+            (Platform::Chip8E, 0x5, _, _, 0x1) => Op::SkipGreater(x, y),
+            (Platform::Chip8E, 0x5, _, _, 0x2) => Op::StoreRange(x, y),
+            (Platform::Chip8E, 0x5, _, _, 0x3) => Op::LoadRange(x, y),
+            (Platform::Chip8E, 0xB, 0xB, _, _) => Op::BranchBack(opcode.nn),
+            (Platform::Chip8E, 0xB, 0xF, _, _) => Op::BranchForward(opcode.nn),
+            (Platform::Chip8E, 0xF, _, 0x0, 0x3) => Op::Output(x),
+            (Platform::Chip8E, 0xF, _, 0x1, 0xB) => Op::SkipBytes(x),
+            (Platform::Chip8E, 0xF, _, 0x4, 0xF) => Op::DelayWait(x),
+            (Platform::Chip8E, 0xF, _, 0xE, 0x3 | 0x7) => Op::Input(x),
+            (Platform::HiRes, 0x0, 0x2, 0x3, 0x0) => Op::ClearScreen,
+            (Platform::MegaChip, 0x0, 0x0, 0x1, 0x0) => Op::MegaMode(false),
+            (Platform::MegaChip, 0x0, 0x0, 0x1, 0x1) => Op::MegaMode(true),
+            (Platform::MegaChip, 0x0, 0x1, _, _) => Op::LongIndex(opcode.nn),
+            (Platform::MegaChip, 0x0, 0x2, _, _) => Op::LoadPalette(opcode.nn),
+            (Platform::MegaChip, 0x0, 0x3, _, _) => Op::SpriteWidth(opcode.nn),
+            (Platform::MegaChip, 0x0, 0x4, _, _) => Op::SpriteHeight(opcode.nn),
+            (Platform::MegaChip, 0x0, 0x5, _, _) => Op::ScreenAlpha(opcode.nn),
+            (Platform::MegaChip, 0x0, 0x6, 0x0, n) => Op::PlaySound(n),
+            (Platform::MegaChip, 0x0, 0x7, 0x0, 0x0) => Op::StopSound,
+            (Platform::MegaChip, 0x0, 0x8, 0x0, n @ 0x0..=0x5) => Op::Blend(n),
+            _ => Op::decode(instruction),
+        }
+    }
+
+    /** The range of RAM the op is about to write, given the machine state before it runs */
+    pub(crate) fn write_range(&self, chip8: &Chip8) -> Option<(usize, usize)> {
+        let start = chip8.idx_reg as usize;
+        match *self {
+            Op::Bcd(_) => Some((start, start + 3)),
+            Op::Store(x) => Some((start, start + x as usize + 1)),
+            Op::StoreRange(x, y) => Some((start, start + range_len(x, y))),
+            // the return address goes onto the stack in RAM
+            Op::Call(_) if chip8.quirks.stack_in_ram => {
+                let slot = stack_slot(chip8.call_stack.len());
+                Some((slot, slot + 2))
+            }
+            // machine code can write anywhere
+            Op::MachineCall(_) => Some((0, chip8.ram.len())),
+            _ => None,
+        }
+    }
+
+    /** Reports whether running the op in the machine's current state would fail */
+    pub(crate) fn check(&self, chip8: &Chip8) -> Result<(), Chip8Error> {
+        let idx = chip8.idx_reg;
+        let accessed = match *self {
+            Op::Unsupported(instruction) => return Err(Chip8Error::UnsupportedOpcode(instruction)),
+            Op::MachineCall(nnn) if !chip8.quirks.machine_code => {
+                return Err(Chip8Error::UnsupportedOpcode(nnn));
+            }
+            Op::Return if chip8.call_stack.is_empty() => return Err(Chip8Error::StackUnderflow),
+            Op::Call(_) if chip8.call_stack.len() >= chip8.quirks.stack_depth as usize => {
+                return Err(Chip8Error::StackOverflow);
+            }
+            Op::BigFontChar(x) if !chip8.has_big_glyph(chip8.var_reg[x as usize] & 0xF) => {
+                return Err(Chip8Error::UnsupportedOpcode(0xF030 | (x as u16) << 8));
+            }
+            Op::LongIndex(_) if chip8.pc as usize + 3 >= chip8.ram.len() => {
+                return Err(Chip8Error::PcOutOfBounds(chip8.pc));
+            }
+            Op::Draw(_, _, n) => match chip8.display.mega_mode() {
+                Some(mega) => mega.sprite_len(idx as usize, n),
+                None => n as usize,
+            },
+            Op::LoadPalette(nn) => nn as usize * 4,
+            Op::PlaySound(_) => SOUND_HEADER_LEN,
+            Op::Bcd(_) => 3,
+            Op::Store(x) | Op::Load(x) => x as usize + 1,
+            Op::StoreRange(x, y) | Op::LoadRange(x, y) => range_len(x, y),
+            _ => return Ok(()),
+        };
+        if idx as usize + accessed > chip8.ram.len() {
+            return Err(Chip8Error::MemoryOutOfBounds(idx));
+        }
+        Ok(())
+    }
+
+    /** Runs the op's handler. The PC must already point past the instruction */
+    #[inline(always)]
+    pub(crate) fn execute(self, chip8: &mut Chip8) {
+        match self {
+            Op::ClearScreen => chip8.op_00e0(),
+            Op::Return => chip8.op_00ee(),
+            Op::MachineCall(nnn) => chip8.op_0nnn(nnn),
+            Op::Jump(nnn) => chip8.op_1nnn(nnn),
+            Op::Call(nnn) => chip8.op_2nnn(nnn),
+            Op::SkipEqImm(x, nn) => chip8.op_3xnn(x as usize, nn),
+            Op::SkipNeImm(x, nn) => chip8.op_4xnn(x as usize, nn),
+            Op::SkipEq(x, y) => chip8.op_5xnn(x as usize, y as usize),
+            Op::SetImm(x, nn) => chip8.op_6xnn(x as usize, nn),
+            Op::AddImm(x, nn) => chip8.op_7xnn(x as usize, nn),
+            Op::Set(x, y) => chip8.op_8xy0(x as usize, y as usize),
+            Op::Or(x, y) => chip8.op_8xy1(x as usize, y as usize),
+            Op::And(x, y) => chip8.op_8xy2(x as usize, y as usize),
+            Op::Xor(x, y) => chip8.op_8xy3(x as usize, y as usize),
+            Op::Add(x, y) => chip8.op_8xy4(x as usize, y as usize),
+            Op::Sub(x, y) => chip8.op_8xy5(x as usize, y as usize),
+            Op::ShiftRight(x, y) => chip8.op_8xy6(x as usize, y as usize),
+            Op::SubReverse(x, y) => chip8.op_8xy7(x as usize, y as usize),
+            Op::ShiftLeft(x, y) => chip8.op_8xye(x as usize, y as usize),
+            Op::SkipNe(x, y) => chip8.op_9xnn(x as usize, y as usize),
+            Op::SetIndex(nnn) => chip8.op_annn(nnn),
+            Op::JumpOffset(nnn) => chip8.op_bnnn(nnn),
+            Op::Random(x, nn) => chip8.op_cxnn(x as usize, nn),
+            Op::Draw(x, y, n) => chip8.op_dxyn(x as usize, y as usize, n),
+            Op::SkipKey(x) => chip8.op_ex9e(x as usize),
+            Op::SkipNotKey(x) => chip8.op_exa1(x as usize),
+            Op::GetDelay(x) => chip8.op_fx07(x as usize),
+            Op::WaitKey(x) => chip8.op_fx0a(x as usize),
+            Op::SetDelay(x) => chip8.op_fx15(x as usize),
+            Op::SetSound(x) => chip8.op_fx18(x as usize),
+            Op::AddIndex(x) => chip8.op_fx1e(x as usize),
+            Op::FontChar(x) => chip8.op_fx29(x as usize),
+            Op::BigFontChar(x) => chip8.op_fx30(x as usize),
+            Op::Bcd(x) => chip8.op_fx33(x as usize),
+            Op::Store(x) => chip8.op_fx55(x as usize),
+            Op::Load(x) => chip8.op_fx65(x as usize),
+            Op::CycleBackground => chip8.op_02a0(),
+            Op::AddNibbles(x, y) => chip8.op_5xy1_add_nibbles(x as usize, y as usize),
+            Op::Color(x, y, n) => chip8.op_bxyn(x as usize, y as usize, n),
+            Op::SkipKey2(_) => {}
+            Op::SkipNotKey2(_) => chip8.pc += 2,
+            Op::Output(x) => chip8.port_out = chip8.var_reg[x as usize],
+            Op::Input(x) => chip8.var_reg[x as usize] = chip8.port_in,
+            Op::Stop => chip8.pc -= 2,
+            Op::Nop => {}
+            Op::WaitDelay => chip8.op_0151(),
+            Op::Skip => chip8.pc += 2,
+            Op::SkipGreater(x, y) => chip8.op_5xy1_skip_greater(x as usize, y as usize),
+            Op::StoreRange(x, y) => chip8.op_5xy2(x as usize, y as usize),
+            Op::LoadRange(x, y) => chip8.op_5xy3(x as usize, y as usize),
+            Op::BranchBack(nn) => chip8.op_bbnn(nn),
+            Op::BranchForward(nn) => chip8.op_bfnn(nn),
+            Op::SkipBytes(x) => chip8.op_fx1b(x as usize),
+            Op::DelayWait(x) => chip8.op_fx4f(x as usize),
+            Op::MegaMode(enabled) => chip8.op_001n(enabled),
+            Op::LongIndex(nn) => chip8.op_01nn(nn),
+            Op::LoadPalette(nn) => chip8.op_02nn(nn),
+            Op::SpriteWidth(nn) => chip8.op_03nn(nn),
+            Op::SpriteHeight(nn) => chip8.op_04nn(nn),
+            Op::ScreenAlpha(nn) => chip8.op_05nn(nn),
+            Op::PlaySound(n) => chip8.op_060n(n),
+            Op::StopSound => chip8.digital_sound = None,
+            Op::Blend(n) => chip8.op_080n(n),
+            Op::Unsupported(instruction) => panic!("Unsupported opcode: {instruction:#06x}"),
+        }
+    }
+}
+
+/** Registers VX to VY, which CHIP-8E 5XY2 and 5XY3 store and load */
+fn range_len(x: u8, y: u8) -> usize {
+    (y as usize + 1).saturating_sub(x as usize)
+}
+
+/**
+ * Executes instructions from a cache of decoded ops, one entry per RAM address.
+ * Entries are decoded the first time they run and invalidated when FX55 or FX33
+ * write over them, so self-modifying code behaves exactly like `Chip8::cycle`.
+ */
+pub struct PredecodedEngine {
+    /** Covers the first 4KB and grows when MEGA-CHIP runs code past it */
+    cache: Vec<Option<Op>>,
+}
+impl Default for PredecodedEngine {
+    fn default() -> Self {
+        PredecodedEngine::new()
+    }
+}
+impl PredecodedEngine {
+    pub fn new() -> PredecodedEngine {
+        PredecodedEngine {
+            cache: vec![None; 4096],
+        }
+    }
+
+    /** Drops cached entries overlapping the given byte range of RAM */
+    pub fn invalidate(&mut self, start: usize, end: usize) {
+        // an entry at address A also covers the byte at A + 1
+        let first = start.saturating_sub(1).min(self.cache.len());
+        let last = end.min(self.cache.len());
+        self.cache[first..last].fill(None);
+    }
+}
+impl Engine for PredecodedEngine {
+    fn step(&mut self, chip8: &mut Chip8) {
+        let pc = chip8.pc as usize;
+        if pc >= self.cache.len() {
+            self.cache.resize(pc + 1, None);
+        }
+        let op = match self.cache[pc] {
+            Some(op) => op,
+            None => {
+                let instruction = (chip8.ram[pc] as u16) << 8 | chip8.ram[pc + 1] as u16;
+                let op = Op::decode_for(instruction, chip8.platform);
+                self.cache[pc] = Some(op);
+                op
+            }
+        };
+
+        let write_range = op.write_range(chip8);
+        chip8.count_instruction();
+        chip8.pc += 2;
+        op.execute(chip8);
+        if let Some((start, end)) = write_range {
+            self.invalidate(start, end);
+        }
+    }
+
+    fn invalidate_all(&mut self) {
+        self.cache.fill(None);
+    }
+}
@@ -0,0 +1,162 @@
+/** Levels of nested calls the COSMAC VIP's stack holds */
+pub const VIP_STACK_DEPTH: u8 = 12;
+/** Levels of nested calls the SUPER-CHIP stack holds, also the default limit */
+pub const SCHIP_STACK_DEPTH: u8 = 16;
+/**
+ * With `stack_in_ram`, return addresses are stored big-endian just below this address and grow
+ * downwards, where the VIP interpreter keeps them
+ */
+pub const VIP_STACK_TOP: usize = 0xED0;
+
+/**
+ * Behaviours that differ between CHIP-8 platforms. Everything off matches the
+ * interpreter's original behaviour, with calls limited to 16 levels; the presets match the
+ * named platforms.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /** 8XY6/8XYE shift VX in place instead of shifting VY into VX */
+    pub shift_in_place: bool,
+    /** FX55/FX65 leave I pointing just past the last register accessed */
+    pub memory_increments_i: bool,
+    /** BNNN jumps to NNN plus VX, where X is the highest nibble of NNN, instead of V0 */
+    pub jump_uses_vx: bool,
+    /** DXYN wraps sprites around the screen edges instead of clipping them */
+    pub wrap_sprites: bool,
+    /** 8XY1, 8XY2 and 8XY3 reset VF to 0 */
+    pub logic_resets_vf: bool,
+    /** Return addresses also live in RAM below `VIP_STACK_TOP`, and 00EE reads them from there */
+    pub stack_in_ram: bool,
+    /** 0NNN runs the machine code routine at NNN on an emulated RCA 1802 */
+    pub machine_code: bool,
+    /** Calls that can nest before 2NNN overflows the stack */
+    pub stack_depth: u8,
+}
+impl Default for Quirks {
+    fn default() -> Quirks {
+        Quirks {
+            shift_in_place: false,
+            memory_increments_i: false,
+            jump_uses_vx: false,
+            wrap_sprites: false,
+            logic_resets_vf: false,
+            stack_in_ram: false,
+            machine_code: false,
+            stack_depth: SCHIP_STACK_DEPTH,
+        }
+    }
+}
+
+/** Names used when writing quirks as text, in field order */
+const FLAG_NAMES: [&str; 7] = [
+    "shift_in_place",
+    "memory_increments_i",
+    "jump_uses_vx",
+    "wrap_sprites",
+    "logic_resets_vf",
+    "stack_in_ram",
+    "machine_code",
+];
+
+impl Quirks {
+    /** The original COSMAC VIP interpreter */
+    pub fn vip() -> Quirks {
+        Quirks {
+            memory_increments_i: true,
+            logic_resets_vf: true,
+            stack_in_ram: true,
+            machine_code: true,
+            stack_depth: VIP_STACK_DEPTH,
+            ..Quirks::default()
+        }
+    }
+
+    /** SUPER-CHIP on the HP48 */
+    pub fn schip() -> Quirks {
+        Quirks {
+            shift_in_place: true,
+            jump_uses_vx: true,
+            ..Quirks::default()
+        }
+    }
+
+    /** XO-CHIP as implemented by Octo */
+    pub fn xochip() -> Quirks {
+        Quirks {
+            memory_increments_i: true,
+            wrap_sprites: true,
+            ..Quirks::default()
+        }
+    }
+
+    /** Looks up a preset by name: "vip", "schip", "xochip" or "default" */
+    pub fn preset(name: &str) -> Option<Quirks> {
+        match name.to_ascii_lowercase().as_str() {
+            "default" => Some(Quirks::default()),
+            "vip" => Some(Quirks::vip()),
+            "schip" => Some(Quirks::schip()),
+            "xochip" => Some(Quirks::xochip()),
+            _ => None,
+        }
+    }
+
+    fn flags(&self) -> [bool; 7] {
+        [
+            self.shift_in_place,
+            self.memory_increments_i,
+            self.jump_uses_vx,
+            self.wrap_sprites,
+            self.logic_resets_vf,
+            self.stack_in_ram,
+            self.machine_code,
+        ]
+    }
+
+    /**
+     * Writes the enabled quirks as a comma separated list, e.g. "memory_increments_i,logic_resets_vf".
+     * A stack depth other than the default is written as "stack_depth=12"
+     */
+    pub fn to_flags(&self) -> String {
+        let mut enabled: Vec<String> = FLAG_NAMES
+            .iter()
+            .zip(self.flags())
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| name.to_string())
+            .collect();
+        if self.stack_depth != SCHIP_STACK_DEPTH {
+            enabled.push(format!("stack_depth={}", self.stack_depth));
+        }
+        enabled.join(",")
+    }
+
+    /** Reads a list written by `to_flags` */
+    pub fn from_flags(flags: &str) -> Result<Quirks, String> {
+        let mut quirks = Quirks::default();
+        for name in flags
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            match name {
+                "shift_in_place" => quirks.shift_in_place = true,
+                "memory_increments_i" => quirks.memory_increments_i = true,
+                "jump_uses_vx" => quirks.jump_uses_vx = true,
+                "wrap_sprites" => quirks.wrap_sprites = true,
+                "logic_resets_vf" => quirks.logic_resets_vf = true,
+                "stack_in_ram" => quirks.stack_in_ram = true,
+                "machine_code" => quirks.machine_code = true,
+                _ => match name.strip_prefix("stack_depth=") {
+                    Some(depth) => {
+                        quirks.stack_depth = depth
+                            .parse()
+                            .ok()
+                            .filter(|depth| *depth > 0)
+                            .ok_or_else(|| format!("Invalid stack depth: {depth}"))?
+                    }
+                    None => return Err(format!("Unknown quirk: {name}")),
+                },
+            }
+        }
+        Ok(quirks)
+    }
+}
@@ -0,0 +1,95 @@
+use crate::display::{Display, MAX_HEIGHT, WIDTH};
+
+/** Decides when the renderer samples a new frame from the display */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresentMode {
+    /** Sample the display on every update */
+    Always,
+    /** Only sample the display after a 00E0 or DXYN has completed */
+    OnDraw,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RendererConfig {
+    /** Intensity a pixel loses per update once it is turned off (1.0 turns it off instantly) */
+    pub fade: f32,
+    /** Weight of the previous frame when blending with the new one (0.0 disables blending) */
+    pub blend: f32,
+    pub present_mode: PresentMode,
+}
+impl Default for RendererConfig {
+    /** Draws exactly what is on the display, with no persistence */
+    fn default() -> Self {
+        RendererConfig {
+            fade: 1.0,
+            blend: 0.0,
+            present_mode: PresentMode::Always,
+        }
+    }
+}
+impl RendererConfig {
+    /** Mimics the slow decay of a CRT phosphor to hide XOR sprite flicker */
+    pub fn phosphor() -> RendererConfig {
+        RendererConfig {
+            fade: 0.2,
+            blend: 0.0,
+            present_mode: PresentMode::OnDraw,
+        }
+    }
+}
+
+/** Keeps a per-pixel intensity buffer on top of a Display */
+pub struct Renderer {
+    pub config: RendererConfig,
+    presented: [[bool; WIDTH]; MAX_HEIGHT],
+    intensity: [[f32; WIDTH]; MAX_HEIGHT],
+}
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer::new(RendererConfig::default())
+    }
+}
+impl Renderer {
+    pub fn new(config: RendererConfig) -> Renderer {
+        Renderer {
+            config,
+            presented: [[false; WIDTH]; MAX_HEIGHT],
+            intensity: [[0.0; WIDTH]; MAX_HEIGHT],
+        }
+    }
+
+    /** Advances the intensity buffer by one frame. `drawn` is whether the display was drawn to since the last update */
+    pub fn update(&mut self, display: &Display, drawn: bool) {
+        if drawn || self.config.present_mode == PresentMode::Always {
+            for (y, row) in self.presented.iter_mut().enumerate() {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    *pixel = display.get_pixel(y, x);
+                }
+            }
+        }
+
+        let blend = self.config.blend.clamp(0.0, 1.0);
+        let fade = self.config.fade.clamp(0.0, 1.0);
+        for (y, row) in self.intensity.iter_mut().enumerate() {
+            for (x, intensity) in row.iter_mut().enumerate() {
+                let target = if self.presented[y][x] { 1.0 } else { 0.0 };
+                let blended = *intensity * blend + target * (1.0 - blend);
+                *intensity = match self.presented[y][x] {
+                    true => blended,
+                    false => blended.max(*intensity - fade).max(0.0),
+                };
+            }
+        }
+    }
+
+    /** Returns how lit a pixel should be drawn, from 0.0 (off) to 1.0 (fully on) */
+    pub fn intensity(&self, y: usize, x: usize) -> f32 {
+        self.intensity[y][x]
+    }
+
+    /** Drops all persisted intensity, e.g. when a new ROM is loaded */
+    pub fn reset(&mut self) {
+        self.presented = [[false; WIDTH]; MAX_HEIGHT];
+        self.intensity = [[0.0; WIDTH]; MAX_HEIGHT];
+    }
+}
@@ -0,0 +1,144 @@
+/** Where CXNN gets its random numbers from. Implement this to inject a custom generator */
+pub trait RandomSource: Send {
+    /** Returns the next random byte */
+    fn next_byte(&mut self) -> u8;
+
+    /** The complete internal state, so it can be stored in a snapshot */
+    fn state(&self) -> u64;
+
+    /** Restores a state previously returned by `state` */
+    fn set_state(&mut self, state: u64);
+
+    fn box_clone(&self) -> Box<dyn RandomSource>;
+
+    /** Which built-in generator this is, so snapshots can recreate it. None for custom sources */
+    fn generator(&self) -> Option<Generator> {
+        None
+    }
+}
+
+impl Clone for Box<dyn RandomSource> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/** The built-in random sources, selectable by name */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Generator {
+    #[default]
+    Xorshift,
+    Vip,
+}
+impl Generator {
+    pub const ALL: [Generator; 2] = [Generator::Xorshift, Generator::Vip];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Generator::Xorshift => "xorshift",
+            Generator::Vip => "vip",
+        }
+    }
+
+    /** Looks a generator up by the name returned from `Generator::name` */
+    pub fn from_name(name: &str) -> Option<Generator> {
+        Generator::ALL
+            .into_iter()
+            .find(|generator| generator.name().eq_ignore_ascii_case(name))
+    }
+
+    /** Creates the generator with a seed, as passed to `RandomSource::set_state` */
+    pub fn source(&self, seed: u64) -> Box<dyn RandomSource> {
+        match self {
+            Generator::Xorshift => Box::new(Xorshift::new(seed)),
+            Generator::Vip => Box::new(VipRandom::new(seed)),
+        }
+    }
+}
+
+/** Fast seedable generator (xorshift64*) used by default */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Xorshift {
+    state: u64,
+}
+impl Xorshift {
+    pub fn new(seed: u64) -> Xorshift {
+        let mut rng = Xorshift { state: 0 };
+        rng.set_state(seed);
+        rng
+    }
+}
+impl RandomSource for Xorshift {
+    fn next_byte(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+
+    fn state(&self) -> u64 {
+        self.state
+    }
+
+    fn set_state(&mut self, state: u64) {
+        // xorshift gets stuck on a zero state, so scramble the seed into a non-zero one
+        self.state = match state {
+            0 => 0x9E37_79B9_7F4A_7C15,
+            _ => state,
+        };
+    }
+
+    fn box_clone(&self) -> Box<dyn RandomSource> {
+        Box::new(self.clone())
+    }
+
+    fn generator(&self) -> Option<Generator> {
+        Some(Generator::Xorshift)
+    }
+}
+
+/** How far R.0 moves between two CXNN calls in `VipRandom`. Odd, so every low byte comes up */
+const R0_STEP: u8 = 0x2B;
+
+/**
+ * The COSMAC VIP interpreter's CXNN routine. It adds the low byte of R.0, the display DMA
+ * pointer that keeps moving while a program runs, to its previous result and rotates it. R.0
+ * depends on exact timing, so here it steps by `R0_STEP` between calls instead
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VipRandom {
+    r0: u8,
+    last: u8,
+}
+impl VipRandom {
+    /** Seeds R.0 from the low byte of the seed and the previous result from the next */
+    pub fn new(seed: u64) -> VipRandom {
+        let mut rng = VipRandom { r0: 0, last: 0 };
+        rng.set_state(seed);
+        rng
+    }
+}
+impl RandomSource for VipRandom {
+    fn next_byte(&mut self) -> u8 {
+        self.r0 = self.r0.wrapping_add(R0_STEP);
+        self.last = self.last.wrapping_add(self.r0).rotate_right(1);
+        self.last
+    }
+
+    fn state(&self) -> u64 {
+        (self.last as u64) << 8 | self.r0 as u64
+    }
+
+    fn set_state(&mut self, state: u64) {
+        self.r0 = state as u8;
+        self.last = (state >> 8) as u8;
+    }
+
+    fn box_clone(&self) -> Box<dyn RandomSource> {
+        Box::new(self.clone())
+    }
+
+    fn generator(&self) -> Option<Generator> {
+        Some(Generator::Vip)
+    }
+}
@@ -1,15 +1,48 @@
+use crate::megachip::MEGA_MEMORY;
+use sha1::{Digest, Sha1};
+#[cfg(not(target_arch = "wasm32"))]
 use std::fs::File;
+#[cfg(not(target_arch = "wasm32"))]
 use std::io::Read;
 
 pub struct ROM {
-    pub data: [u8; 3584],
+    /** The ROM contents, padded with zeroes to fill the 3584 bytes after 0x200 */
+    pub data: Vec<u8>,
+    /** Number of bytes actually read from the ROM file */
+    pub size: usize,
 }
 impl ROM {
+    /** Reads a ROM file. Not available on wasm, where ROMs come from `from_bytes` */
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn new(rom_path: &str) -> ROM {
         let mut file = File::open(rom_path).expect("Unable to open ROM file");
-        let mut buffer: [u8; 3584] = [0; 3584];
-        file.read(&mut buffer).expect("Unable to read ROM file");
+        let mut contents: Vec<u8> = Vec::new();
+        file.read_to_end(&mut contents)
+            .expect("Unable to read ROM file");
 
-        ROM { data: buffer }
+        ROM::from_bytes(&contents)
+    }
+
+    /**
+     * Creates a ROM from its contents, truncated to what fits in MEGA-CHIP's 16MB. Only MEGA-CHIP
+     * loads more than the 3584 bytes that fit in 4KB
+     */
+    pub fn from_bytes(contents: &[u8]) -> ROM {
+        let len = contents.len().min(MEGA_MEMORY - 0x200);
+        let mut buffer = contents[..len].to_vec();
+        buffer.resize(len.max(3584), 0);
+
+        ROM {
+            data: buffer,
+            size: len,
+        }
+    }
+
+    /** SHA-1 of the ROM contents as lowercase hex, the same hash ROM databases use */
+    pub fn sha1(&self) -> String {
+        Sha1::digest(&self.data[..self.size])
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
     }
 }
@@ -0,0 +1,214 @@
+use crate::Chip8;
+use crate::engine::Engine;
+use crate::font::Font;
+use crate::palette::Palette;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+/** Width of one labelled cell of a sprite sheet, in unscaled pixels */
+pub const CELL_WIDTH: usize = 18;
+/** A 5 pixel label, a gap, up to 15 sprite rows and padding */
+pub const CELL_HEIGHT: usize = 24;
+const LABEL_HEIGHT: usize = 5;
+const SPRITE_TOP: usize = LABEL_HEIGHT + 2;
+
+/** A sprite drawn by DXYN: `height` bytes starting at `addr` */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sprite {
+    pub addr: u16,
+    pub height: u8,
+}
+
+/**
+ * Finds sprites statically by sweeping a ROM's instructions from 0x200 and pairing each DXYN
+ * with the last ANNN before it. I is forgotten after instructions that move it by an unknown amount
+ */
+pub fn scan(rom: &[u8]) -> Vec<Sprite> {
+    let mut found = BTreeSet::new();
+    let mut index: Option<u16> = None;
+    for pair in rom.chunks_exact(2) {
+        let instruction = u16::from_be_bytes([pair[0], pair[1]]);
+        match (instruction >> 12, instruction & 0xFF) {
+            (0xA, _) => index = Some(instruction & 0xFFF),
+            (0xD, _) => {
+                let height = (instruction & 0xF) as u8;
+                if let Some(addr) = index
+                    && height > 0
+                    && addr as usize + height as usize <= 4096
+                {
+                    found.insert(Sprite { addr, height });
+                }
+            }
+            (0xF, 0x1E | 0x29 | 0x33 | 0x55 | 0x65) => index = None,
+            _ => {}
+        }
+    }
+    found.into_iter().collect()
+}
+
+/**
+ * Records the sprites a running program actually draws. Runs as an engine so the frontends can
+ * use it in place of `Chip8::run_frame`
+ */
+#[derive(Clone, Debug, Default)]
+pub struct SpriteTracker {
+    sprites: BTreeSet<Sprite>,
+}
+impl SpriteTracker {
+    pub fn new() -> SpriteTracker {
+        SpriteTracker::default()
+    }
+
+    /** Records the instruction at the PC if it is a DXYN. Call before it runs */
+    pub fn observe(&mut self, chip8: &Chip8) {
+        let pc = chip8.pc as usize;
+        if pc + 1 >= chip8.ram.len() || chip8.ram[pc] >> 4 != 0xD {
+            return;
+        }
+        let height = chip8.ram[pc + 1] & 0xF;
+        if height > 0 {
+            self.sprites.insert(Sprite {
+                addr: chip8.idx_reg as u16,
+                height,
+            });
+        }
+    }
+
+    /** Adds sprites found elsewhere, e.g. by `scan` */
+    pub fn extend(&mut self, sprites: impl IntoIterator<Item = Sprite>) {
+        self.sprites.extend(sprites);
+    }
+
+    /** Everything recorded so far, ordered by address */
+    pub fn sprites(&self) -> Vec<Sprite> {
+        self.sprites.iter().copied().collect()
+    }
+}
+impl Engine for SpriteTracker {
+    fn step(&mut self, chip8: &mut Chip8) {
+        self.observe(chip8);
+        chip8.cycle();
+    }
+}
+
+/** One cell of a sheet: a short hex label above the sprite's rows */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SheetEntry {
+    pub label: String,
+    pub rows: Vec<u8>,
+}
+
+/** Sprites laid out in a grid of labelled cells, for the debugger or a PNG export */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpriteSheet {
+    pub entries: Vec<SheetEntry>,
+    pub columns: usize,
+}
+impl SpriteSheet {
+    /** Reads each sprite's bytes from memory, labelled with its address */
+    pub fn from_sprites(ram: &[u8], sprites: &[Sprite], columns: usize) -> SpriteSheet {
+        let entries = sprites
+            .iter()
+            .map(|sprite| SheetEntry {
+                label: format!("{:03X}", sprite.addr),
+                rows: read(ram, sprite.addr as usize, sprite.height as usize),
+            })
+            .collect();
+        SpriteSheet {
+            entries,
+            columns: columns.max(1),
+        }
+    }
+
+    /** Reads the 16 hexadecimal glyphs at an address, labelled with their digit */
+    pub fn font(ram: &[u8], base: usize) -> SpriteSheet {
+        let entries = (0..16)
+            .map(|digit| SheetEntry {
+                label: format!("{digit:X}"),
+                rows: read(ram, base + digit * 5, 5),
+            })
+            .collect();
+        SpriteSheet {
+            entries,
+            columns: 8,
+        }
+    }
+
+    /** The font at the machine's font address, or whatever a ROM has written over it */
+    pub fn loaded_font(chip8: &Chip8) -> SpriteSheet {
+        SpriteSheet::font(&chip8.ram, chip8.font_addr as usize)
+    }
+
+    pub fn width(&self) -> usize {
+        self.columns.min(self.entries.len()).max(1) * CELL_WIDTH
+    }
+
+    pub fn height(&self) -> usize {
+        self.entries.len().div_ceil(self.columns).max(1) * CELL_HEIGHT
+    }
+
+    /** Unscaled pixels row by row, true where lit. Labels are drawn with the default font */
+    pub fn pixels(&self) -> Vec<bool> {
+        let label_font = Font::default();
+        let width = self.width();
+        let mut pixels = vec![false; width * self.height()];
+        let mut plot = |x: usize, y: usize, row: u8, bits: usize| {
+            for bit in 0..bits {
+                if row & (0x80 >> bit) != 0 {
+                    pixels[y * width + x + bit] = true;
+                }
+            }
+        };
+        for (index, entry) in self.entries.iter().enumerate() {
+            let left = (index % self.columns) * CELL_WIDTH;
+            let top = (index / self.columns) * CELL_HEIGHT;
+            for (char_index, digit) in entry.label.chars().take(3).enumerate() {
+                let Some(digit) = digit.to_digit(16) else {
+                    continue;
+                };
+                for (y, row) in label_font.glyph(digit as u8).iter().enumerate() {
+                    plot(left + char_index * 5, top + y, *row, 4);
+                }
+            }
+            for (y, row) in entry.rows.iter().take(CELL_HEIGHT - SPRITE_TOP).enumerate() {
+                plot(left, top + SPRITE_TOP + y, *row, 8);
+            }
+        }
+        pixels
+    }
+
+    /** Saves the sheet as a two-colour PNG using the palette's background and foreground */
+    pub fn save_png(&self, path: &Path, palette: &Palette, scale: usize) -> io::Result<()> {
+        let scale = scale.max(1);
+        let (width, height) = (self.width() * scale, self.height() * scale);
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette([palette.background(), palette.foreground()].concat());
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+
+        let pixels = self.pixels();
+        let mut scaled = Vec::with_capacity(width * height);
+        for row in pixels.chunks_exact(self.width()) {
+            let scaled_row: Vec<u8> = row
+                .iter()
+                .flat_map(|&lit| std::iter::repeat_n(lit as u8, scale))
+                .collect();
+            for _ in 0..scale {
+                scaled.extend_from_slice(&scaled_row);
+            }
+        }
+        writer.write_image_data(&scaled).map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+}
+
+/** Reads bytes from memory, wrapping around the end */
+fn read(ram: &[u8], start: usize, len: usize) -> Vec<u8> {
+    (0..len)
+        .map(|offset| ram[(start + offset) % ram.len()])
+        .collect()
+}
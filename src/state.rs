@@ -0,0 +1,313 @@
+use crate::display::{BACKGROUND_CYCLE, ColorMap, HEIGHT, MAX_HEIGHT, WIDTH};
+use crate::megachip::{BlendMode, DigitalSound, Framebuffer, MEGA_HEIGHT, MEGA_MEMORY, MEGA_WIDTH};
+use crate::platform::Platform;
+use crate::rng::Generator;
+use crate::timing::{Timing, VipClock};
+
+/** Identifies serialized snapshots, followed by a format version byte */
+const MAGIC: &[u8; 4] = b"C8SS";
+const VERSION: u8 = 1;
+
+/** Everything needed to put a Chip8 back into an earlier state */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub ram: Vec<u8>,
+    /** Display rows packed like `Display::rows`, 32 or 64 of them */
+    pub display: Vec<u64>,
+    pub colors: Option<ColorMap>,
+    pub mega: Option<Framebuffer>,
+    pub pc: u16,
+    pub idx_reg: u32,
+    pub var_reg: [u8; 16],
+    pub call_stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub curr_input_key: Option<u8>,
+    pub rng_state: u64,
+    /** The built-in generator the state belongs to. None for custom random sources */
+    pub rng_generator: Option<Generator>,
+    pub digital_sound: Option<DigitalSound>,
+    pub platform: Platform,
+    pub timing: Timing,
+    pub vip_clock: VipClock,
+    pub font_addr: u16,
+    /** Bytes of the font at `font_addr`, so a later font load wipes the right region */
+    pub font_size: usize,
+    pub waiting_for_delay: bool,
+    pub port_in: u8,
+    pub port_out: u8,
+}
+impl Snapshot {
+    /** Serializes the snapshot into a compact little-endian byte format */
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.ram.len() + self.display.len() * 8 + 64);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(self.ram.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.ram);
+        bytes.push(self.display.len() as u8);
+        for row in &self.display {
+            bytes.extend_from_slice(&row.to_le_bytes());
+        }
+        match &self.colors {
+            Some(colors) => {
+                bytes.push(1);
+                bytes.extend(colors.foreground.iter().flatten());
+                bytes.push(colors.background);
+            }
+            None => bytes.push(0),
+        }
+        match &self.mega {
+            Some(mega) => {
+                bytes.extend_from_slice(&[1, mega.enabled as u8]);
+                bytes.extend(mega.palette.iter().flatten());
+                bytes.extend_from_slice(&(mega.sprite_width as u16).to_le_bytes());
+                bytes.extend_from_slice(&(mega.sprite_height as u16).to_le_bytes());
+                bytes.push(mega.alpha);
+                bytes.push(
+                    BlendMode::ALL
+                        .iter()
+                        .position(|b| *b == mega.blend)
+                        .unwrap() as u8,
+                );
+                bytes.extend(mega.pixels.iter().flatten());
+                bytes.extend(mega.frame.iter().flatten());
+            }
+            None => bytes.push(0),
+        }
+        bytes.extend_from_slice(&self.pc.to_le_bytes());
+        bytes.extend_from_slice(&self.idx_reg.to_le_bytes());
+        bytes.extend_from_slice(&self.var_reg);
+        bytes.extend_from_slice(&(self.call_stack.len() as u16).to_le_bytes());
+        for addr in &self.call_stack {
+            bytes.extend_from_slice(&addr.to_le_bytes());
+        }
+        bytes.push(self.delay_timer);
+        bytes.push(self.sound_timer);
+        match self.curr_input_key {
+            Some(key) => bytes.extend_from_slice(&[1, key]),
+            None => bytes.extend_from_slice(&[0, 0]),
+        }
+        bytes.extend_from_slice(&self.rng_state.to_le_bytes());
+        match self.rng_generator {
+            Some(generator) => {
+                bytes.push(1 + Generator::ALL.iter().position(|g| *g == generator).unwrap() as u8)
+            }
+            None => bytes.push(0),
+        }
+        match &self.digital_sound {
+            Some(sound) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&sound.addr.to_le_bytes());
+                bytes.extend_from_slice(&sound.sample_rate.to_le_bytes());
+                bytes.extend_from_slice(&sound.len.to_le_bytes());
+                bytes.push(sound.looping as u8);
+            }
+            None => bytes.push(0),
+        }
+        bytes.push(
+            Platform::ALL
+                .iter()
+                .position(|p| *p == self.platform)
+                .unwrap() as u8,
+        );
+        bytes.push(Timing::ALL.iter().position(|t| *t == self.timing).unwrap() as u8);
+        bytes.extend_from_slice(&self.vip_clock.overrun.to_le_bytes());
+        bytes.push(self.vip_clock.waiting_for_interrupt as u8);
+        bytes.extend_from_slice(&self.vip_clock.elapsed.to_le_bytes());
+        bytes.extend_from_slice(&self.font_addr.to_le_bytes());
+        bytes.extend_from_slice(&(self.font_size as u16).to_le_bytes());
+        bytes.extend_from_slice(&[self.waiting_for_delay as u8, self.port_in, self.port_out]);
+        bytes
+    }
+
+    /** Reads a snapshot written by `to_bytes` */
+    pub fn from_bytes(bytes: &[u8]) -> Result<Snapshot, String> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err("Not a Chip8 snapshot".to_owned());
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(format!("Unsupported snapshot version {version}"));
+        }
+
+        let ram_len = reader.u32()? as usize;
+        if !(4096..=MEGA_MEMORY).contains(&ram_len) {
+            return Err(format!("Invalid memory size {ram_len}"));
+        }
+        let ram = reader.take(ram_len)?.to_vec();
+        let rows = reader.u8()? as usize;
+        if rows != HEIGHT && rows != MAX_HEIGHT {
+            return Err(format!("Invalid display height {rows}"));
+        }
+        let display = (0..rows).map(|_| reader.u64()).collect::<Result<_, _>>()?;
+        let colors = match reader.u8()? {
+            0 => None,
+            _ => {
+                let mut colors = ColorMap::default();
+                for (row, bytes) in colors
+                    .foreground
+                    .iter_mut()
+                    .zip(reader.take(HEIGHT * WIDTH / 8)?.chunks(WIDTH / 8))
+                {
+                    row.copy_from_slice(bytes);
+                }
+                colors.background = reader.u8()?;
+                let in_range = colors.foreground.iter().flatten().all(|color| *color < 8);
+                if !in_range || colors.background as usize >= BACKGROUND_CYCLE.len() {
+                    return Err("Invalid CHIP-8X colours".to_owned());
+                }
+                Some(colors)
+            }
+        };
+        let mega = match reader.u8()? {
+            0 => None,
+            _ => Some(read_framebuffer(&mut reader)?),
+        };
+        let pc = reader.u16()?;
+        let idx_reg = reader.u32()?;
+        let mut var_reg = [0; 16];
+        var_reg.copy_from_slice(reader.take(16)?);
+        let stack_len = reader.u16()?;
+        let call_stack = (0..stack_len)
+            .map(|_| reader.u16())
+            .collect::<Result<_, _>>()?;
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let curr_input_key = match (reader.u8()?, reader.u8()?) {
+            (0, _) => None,
+            (_, key) => Some(key),
+        };
+        let rng_state = reader.u64()?;
+        let rng_generator = match reader.u8()? {
+            0 => None,
+            index => Some(
+                *Generator::ALL
+                    .get(index as usize - 1)
+                    .ok_or("Invalid random generator")?,
+            ),
+        };
+        let digital_sound = match reader.u8()? {
+            0 => None,
+            _ => Some(DigitalSound {
+                addr: reader.u32()?,
+                sample_rate: reader.u16()?,
+                len: reader.u32()?,
+                looping: reader.u8()? != 0,
+            }),
+        };
+        let platform = *Platform::ALL
+            .get(reader.u8()? as usize)
+            .ok_or("Invalid platform")?;
+        let timing = *Timing::ALL
+            .get(reader.u8()? as usize)
+            .ok_or("Invalid timing mode")?;
+        let vip_clock = VipClock {
+            overrun: reader.u32()?,
+            waiting_for_interrupt: reader.u8()? != 0,
+            elapsed: reader.u64()?,
+        };
+        let font_addr = reader.u16()?;
+        let font_size = reader.u16()? as usize;
+        if font_addr as usize + font_size > 4096 {
+            return Err("Invalid font location".to_owned());
+        }
+        let waiting_for_delay = reader.u8()? != 0;
+        let port_in = reader.u8()?;
+        let port_out = reader.u8()?;
+        if reader.pos != bytes.len() {
+            return Err("Unexpected data after snapshot".to_owned());
+        }
+
+        Ok(Snapshot {
+            ram,
+            display,
+            colors,
+            mega,
+            pc,
+            idx_reg,
+            var_reg,
+            call_stack,
+            delay_timer,
+            sound_timer,
+            curr_input_key,
+            rng_state,
+            rng_generator,
+            digital_sound,
+            platform,
+            timing,
+            vip_clock,
+            font_addr,
+            font_size,
+            waiting_for_delay,
+            port_in,
+            port_out,
+        })
+    }
+}
+
+fn read_framebuffer(reader: &mut Reader) -> Result<Framebuffer, String> {
+    let enabled = reader.u8()? != 0;
+    let mut palette = [[0; 4]; 256];
+    for (entry, bytes) in palette.iter_mut().zip(reader.take(256 * 4)?.chunks(4)) {
+        entry.copy_from_slice(bytes);
+    }
+    let sprite_width = reader.u16()? as usize;
+    let sprite_height = reader.u16()? as usize;
+    let alpha = reader.u8()?;
+    let blend = BlendMode::from_nibble(reader.u8()?).ok_or("Invalid MEGA-CHIP blend mode")?;
+    if !(1..=256).contains(&sprite_width) || !(1..=256).contains(&sprite_height) {
+        return Err("Invalid MEGA-CHIP sprite size".to_owned());
+    }
+    let mut pixels = || -> Result<Vec<[u8; 4]>, String> {
+        Ok(reader
+            .take(MEGA_WIDTH * MEGA_HEIGHT * 4)?
+            .chunks(4)
+            .map(|pixel| pixel.try_into().unwrap())
+            .collect())
+    };
+    Ok(Framebuffer {
+        enabled,
+        palette,
+        sprite_width,
+        sprite_height,
+        alpha,
+        blend,
+        pixels: pixels()?,
+        frame: pixels()?,
+    })
+}
+
+/** Cursor over serialized bytes that reports truncation as an error */
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos + len;
+        if end > self.bytes.len() {
+            return Err("Snapshot is truncated".to_owned());
+        }
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
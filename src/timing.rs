@@ -0,0 +1,198 @@
+use crate::Chip8;
+use crate::predecode::Op;
+
+/** 1802 machine cycles per 60Hz frame: a 1.7609MHz clock, 8 clock pulses per machine cycle */
+pub const CYCLES_PER_FRAME: u32 = 3668;
+/** Cycles the 1861 steals each frame to fetch 128 scanlines of 8 bytes by DMA */
+pub const DMA_CYCLES: u32 = 128 * 8;
+/** Cycles spent in the display interrupt routine, which also ticks the timers */
+pub const INTERRUPT_CYCLES: u32 = 62;
+/** What is left of each frame for the CHIP-8 interpreter */
+pub const INTERPRETER_CYCLES: u32 = CYCLES_PER_FRAME - DMA_CYCLES - INTERRUPT_CYCLES;
+/** Cycles the interpreter spends fetching an instruction and dispatching to its routine */
+pub const FETCH_CYCLES: u32 = 40;
+/** Extra cycles when a skip instruction skips */
+const SKIP_CYCLES: u32 = 4;
+
+/** How many instructions run per frame */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Timing {
+    /** A fixed number of instructions per frame, given to `run_frame` */
+    #[default]
+    Fast,
+    /**
+     * Instructions cost what they took on the COSMAC VIP, DXYN waits for the display
+     * interrupt and the timers tick from that interrupt. `cycles_per_frame` is ignored
+     */
+    Vip,
+}
+impl Timing {
+    pub const ALL: [Timing; 2] = [Timing::Fast, Timing::Vip];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Timing::Fast => "fast",
+            Timing::Vip => "vip",
+        }
+    }
+
+    /** Looks up a mode by name: "fast" or "vip" */
+    pub fn from_name(name: &str) -> Option<Timing> {
+        Timing::ALL
+            .into_iter()
+            .find(|timing| timing.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/** Where the emulated VIP is between frames */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VipClock {
+    /** Cycles the last instruction of the previous frame ran past the interrupt */
+    pub overrun: u32,
+    /** A DXYN at the PC is waiting for the next interrupt before it draws */
+    pub waiting_for_interrupt: bool,
+    /** Machine cycles spent by the interpreter since the machine was created */
+    pub elapsed: u64,
+}
+
+/**
+ * The 1802 machine cycles an instruction takes on the VIP, including fetching it, given the
+ * machine state before it runs. Skips cost `SKIP_CYCLES` more when they skip. CHIP-8X and
+ * CHIP-8E instructions cost about what the closest original instruction does
+ */
+pub fn instruction_cycles(chip8: &Chip8, instruction: u16) -> u32 {
+    let v = |x: u8| chip8.var_reg[x as usize];
+    let execute = match Op::decode_for(instruction, chip8.platform) {
+        // clearing the 256 bytes of display memory, 12 cycles per byte
+        Op::ClearScreen => 24 + 12 * 256,
+        Op::Return => 10,
+        // the jump into the routine; the routine's own cycles aren't counted
+        Op::MachineCall(_) => 12,
+        Op::Jump(_) => 12,
+        Op::Call(_) => 26,
+        Op::SkipEqImm(..) | Op::SkipNeImm(..) => 10,
+        Op::SkipEq(..) | Op::SkipNe(..) => 14,
+        Op::SetImm(..) => 6,
+        Op::AddImm(..) => 10,
+        Op::Set(..)
+        | Op::Or(..)
+        | Op::And(..)
+        | Op::Xor(..)
+        | Op::Add(..)
+        | Op::Sub(..)
+        | Op::ShiftRight(..)
+        | Op::SubReverse(..)
+        | Op::ShiftLeft(..) => 44,
+        Op::SetIndex(_) => 12,
+        // crossing into the next page takes an extra branch
+        Op::JumpOffset(nnn) => match (nnn & 0xFF) + v(0) as u16 > 0xFF {
+            true => 24,
+            false => 22,
+        },
+        Op::Random(..) => 36,
+        // unaligned sprites are shifted bit by bit into two bytes per row
+        Op::Draw(x, _, n) => match v(x) % 8 {
+            0 => 26 + 34 * n as u32,
+            shift => 26 + (50 + 8 * shift as u32) * n as u32,
+        },
+        Op::SkipKey(_) | Op::SkipNotKey(_) => 14,
+        Op::GetDelay(_) | Op::SetDelay(_) => 10,
+        Op::SetSound(_) => 12,
+        // polls the keypad once per attempt
+        Op::WaitKey(_) => 18,
+        Op::AddIndex(_) => 16,
+        // a multiply by 5 and a lookup; FX30 never ran on a VIP, so it's costed the same
+        Op::FontChar(_) | Op::BigFontChar(_) => 20,
+        // counts hundreds and then tens by repeated subtraction
+        Op::Bcd(x) => 84 + 16 * (v(x) / 100 + v(x) / 10 % 10) as u32,
+        Op::Store(x) | Op::Load(x) => 14 + 14 * (x as u32 + 1),
+        Op::StoreRange(x, y) | Op::LoadRange(x, y) => {
+            14 + 14 * (y as u32 + 1).saturating_sub(x as u32)
+        }
+        Op::Nop | Op::Stop | Op::Skip | Op::WaitDelay => 10,
+        Op::CycleBackground
+        | Op::BranchBack(_)
+        | Op::BranchForward(_)
+        | Op::SkipBytes(_)
+        | Op::Output(_)
+        | Op::Input(_)
+        | Op::DelayWait(_) => 12,
+        Op::AddNibbles(..) => 44,
+        Op::SkipGreater(..) | Op::SkipKey2(_) | Op::SkipNotKey2(_) => 14,
+        // one colour RAM byte per row or zone
+        Op::Color(_, _, n) => 26 + 8 * n.max(4) as u32,
+        // MEGA-CHIP never ran on a VIP, so these are rough guesses
+        Op::MegaMode(_)
+        | Op::SpriteWidth(_)
+        | Op::SpriteHeight(_)
+        | Op::ScreenAlpha(_)
+        | Op::PlaySound(_)
+        | Op::StopSound
+        | Op::Blend(_) => 12,
+        Op::LongIndex(_) => 20,
+        Op::LoadPalette(nn) => 14 + 14 * nn as u32,
+        Op::Unsupported(_) => 0,
+    };
+    FETCH_CYCLES + execute
+}
+
+/**
+ * Runs the interpreter for one frame of VIP time: instructions until the cycles left after the
+ * display DMA and interrupt are spent, then a timer tick. An instruction that runs past the
+ * interrupt takes its excess from the next frame. Stops at the first error without ticking
+ */
+pub(crate) fn run_vip_frame<E>(
+    chip8: &mut Chip8,
+    mut step: impl FnMut(&mut Chip8) -> Result<(), E>,
+) -> Result<(), E> {
+    // the interrupt that starts this frame releases a waiting DXYN
+    let mut may_draw = chip8.vip_clock.waiting_for_interrupt;
+    chip8.vip_clock.waiting_for_interrupt = false;
+    let mut spent = chip8.vip_clock.overrun;
+    chip8.vip_clock.overrun = 0;
+
+    while spent < INTERPRETER_CYCLES {
+        let pc = chip8.pc as usize;
+        let instruction = match pc + 1 < chip8.ram.len() {
+            true => u16::from_be_bytes([chip8.ram[pc], chip8.ram[pc + 1]]),
+            // let the step report or panic on the bad PC
+            false => 0,
+        };
+        let op = Op::decode_for(instruction, chip8.platform);
+        if let Op::Draw(..) = op {
+            if !may_draw {
+                chip8.vip_clock.waiting_for_interrupt = true;
+                spent = INTERPRETER_CYCLES;
+                break;
+            }
+            may_draw = false;
+        }
+
+        let mut cycles = instruction_cycles(chip8, instruction);
+        let skippable = matches!(
+            op,
+            Op::SkipEqImm(..)
+                | Op::SkipNeImm(..)
+                | Op::SkipEq(..)
+                | Op::SkipNe(..)
+                | Op::SkipKey(_)
+                | Op::SkipNotKey(_)
+                | Op::SkipGreater(..)
+                | Op::SkipKey2(_)
+                | Op::SkipNotKey2(_)
+        );
+        step(chip8)?;
+        if skippable && chip8.pc as usize == pc + 4 {
+            cycles += SKIP_CYCLES;
+        }
+        spent += cycles;
+        chip8.vip_clock.elapsed += cycles as u64;
+    }
+    // an overrun can use up a whole frame before the waiting DXYN gets to run
+    if may_draw {
+        chip8.vip_clock.waiting_for_interrupt = true;
+    }
+    chip8.vip_clock.overrun = spent - INTERPRETER_CYCLES;
+    chip8.decrement_timers(1);
+    Ok(())
+}
@@ -0,0 +1,73 @@
+mod tests {
+    use chip8::Chip8;
+    use chip8::batch::Batch;
+    use chip8::error::Chip8Error;
+    use chip8::quirks::Quirks;
+
+    /** Draws the "0" glyph at a random position every loop */
+    const RANDOM_DRAW: [u8; 10] = [0xA0, 0x50, 0xC0, 0x3F, 0xC1, 0x1F, 0xD0, 0x15, 0x12, 0x02];
+
+    #[test]
+    fn batch_matches_sequential_runs() {
+        let mut batch = Batch::from_rom(&RANDOM_DRAW, 8, 100, Quirks::default()).with_threads(3);
+        let frame = batch.run(20, 10, None);
+
+        for (i, framebuffer) in frame.framebuffers.iter().enumerate() {
+            let mut chip8 = Chip8::with_seed(100 + i as u64);
+            chip8.load_memory(0x200, &RANDOM_DRAW);
+            for _ in 0..20 {
+                chip8.run_frame(10);
+            }
+            assert_eq!(*framebuffer, chip8.display.as_bitmask_rows());
+            assert_eq!(frame.pcs[i], chip8.pc);
+        }
+        assert_eq!(batch.stats().frames, vec![20; 8]);
+        assert_eq!(batch.stats().draw_frames, vec![20; 8]);
+    }
+
+    #[test]
+    fn inputs_are_independent() {
+        // loops back to the start while key 5 is held, otherwise parks at 0x206
+        let rom = [0x60, 0x05, 0xE0, 0xA1, 0x12, 0x00, 0x12, 0x06];
+        let mut batch = Batch::from_rom(&rom, 2, 0, Quirks::default());
+        let frame = batch.step(&[Some(0x5), None], 3);
+        assert_eq!(frame.pcs, vec![0x200, 0x206]);
+    }
+
+    #[test]
+    fn failing_instances_stop_without_stopping_the_batch() {
+        let mut crashing = Chip8::with_seed(0);
+        crashing.load_memory(0x200, &[0xFF, 0xFF]);
+        let mut running = Chip8::with_seed(0);
+        running.load_memory(0x200, &RANDOM_DRAW);
+
+        let mut batch = Batch::new(vec![crashing, running]);
+        batch.run(3, 5, None);
+        assert_eq!(
+            batch.stats().errors,
+            vec![Some(Chip8Error::UnsupportedOpcode(0xFFFF)), None]
+        );
+        assert_eq!(batch.stats().frames, vec![0, 3]);
+        // the failing instruction didn't run
+        assert_eq!(batch.machines()[0].pc, 0x200);
+    }
+
+    #[test]
+    fn oversized_roms_are_cut_to_fit_memory() {
+        let mut rom = vec![0x12, 0x00];
+        rom.resize(5000, 0xAA);
+        let batch = Batch::from_rom(&rom, 2, 0, Quirks::default());
+        for chip8 in batch.machines() {
+            assert_eq!(chip8.ram.len(), 4096);
+            assert_eq!(chip8.ram[0x200..0x202], [0x12, 0x00]);
+            assert_eq!(chip8.ram[0xFFF], 0xAA);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "one input per machine")]
+    fn step_needs_an_input_per_machine() {
+        let mut batch = Batch::from_rom(&RANDOM_DRAW, 2, 0, Quirks::default());
+        batch.step(&[None], 1);
+    }
+}
@@ -0,0 +1,18 @@
+mod tests {
+    use chip8::Chip8;
+    use chip8::blocks::BlockEngine;
+    use chip8::engine::Engine;
+
+    #[test]
+    fn invalidate_all_picks_up_external_writes() {
+        let mut chip8 = Chip8::with_seed(7);
+        chip8.load_memory(0x200, &[0x60, 0x01, 0x61, 0x01, 0x12, 0x00]);
+        let mut engine = BlockEngine::new();
+        engine.run_frame(&mut chip8, 3);
+
+        chip8.load_memory(0x202, &[0x61, 0x02]);
+        engine.invalidate_all();
+        engine.run_frame(&mut chip8, 3);
+        assert_eq!(chip8.snapshot().var_reg[1], 0x02);
+    }
+}
@@ -0,0 +1,117 @@
+mod tests {
+    use chip8::capture::{Capture, Recorder, RecordingFormat};
+    use chip8::display::Display;
+    use chip8::palette::Palette;
+    use chip8::platform::Platform;
+    use std::fs::File;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn recording_format_from_extension() {
+        assert_eq!(
+            RecordingFormat::from_path(Path::new("clip.GIF")),
+            Some(RecordingFormat::Gif)
+        );
+        assert_eq!(
+            RecordingFormat::from_path(Path::new("clip.png")),
+            Some(RecordingFormat::Apng)
+        );
+        assert_eq!(RecordingFormat::from_path(Path::new("clip.mp4")), None);
+    }
+
+    #[test]
+    fn screenshot_is_scaled_with_palette() {
+        let mut display = Display::new();
+        display.flip_pixel(0, 1);
+        let palette = Palette::from_hex("#102030,#A0B0C0").unwrap();
+        let path = temp_path("screenshot.png");
+        Capture::new(palette, 2).save_png(&display, &path).unwrap();
+
+        let decoder = png::Decoder::new(std::io::BufReader::new(File::open(&path).unwrap()));
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!((info.width, info.height), (128, 64));
+        assert_eq!(
+            &info.palette.as_ref().unwrap()[..6],
+            &[0x10, 0x20, 0x30, 0xA0, 0xB0, 0xC0]
+        );
+    }
+
+    #[test]
+    fn screenshots_keep_chip8x_and_mega_chip_colours() {
+        let palette = Palette::default();
+        let mut chip8x = Display::for_platform(Platform::Chip8X);
+        chip8x.colors.as_mut().unwrap().fill(0, 3, 0, 3, 5);
+        chip8x.flip_pixel(0, 0);
+        chip8x.flip_pixel(20, 40);
+        let path = temp_path("chip8x.png");
+        Capture::new(palette, 1).save_png(&chip8x, &path).unwrap();
+        let (size, pixels) = decode_png(&path);
+        assert_eq!(size, (64, 32));
+        let expected: Vec<u8> = chip8x
+            .to_rgba(&palette)
+            .chunks(4)
+            .flat_map(|pixel| pixel[..3].to_vec())
+            .collect();
+        assert_eq!(pixels, expected);
+
+        // the 4:3 screen is centred on the background, as the window draws it
+        let mut mega = Display::for_platform(Platform::MegaChip);
+        mega.set_mega_mode(true);
+        mega.mega
+            .as_mut()
+            .unwrap()
+            .frame
+            .fill([0x12, 0x34, 0x56, 0xFF]);
+        let path = temp_path("mega.png");
+        Capture::new(palette, 3).save_png(&mega, &path).unwrap();
+        let (size, pixels) = decode_png(&path);
+        assert_eq!(size, (192, 96));
+        let at = |x: usize, y: usize| &pixels[(y * 192 + x) * 3..(y * 192 + x) * 3 + 3];
+        assert_eq!(at(96, 48), [0x12, 0x34, 0x56]);
+        assert_eq!(at(0, 48), palette.background());
+        assert_eq!(at(191, 0), palette.background());
+    }
+
+    #[test]
+    fn recorder_merges_unchanged_frames() {
+        let mut display = Display::new();
+        let mut recorder = Recorder::new(Capture::new(Palette::default(), 1), RecordingFormat::Gif);
+        recorder.push_frame(&display);
+        recorder.push_frame(&display);
+        display.flip_pixel(5, 5);
+        recorder.push_frame(&display);
+        assert_eq!(recorder.ticks(), 3);
+
+        let path = temp_path("recording.gif");
+        recorder.save(&path).unwrap();
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(File::open(&path).unwrap())
+            .unwrap();
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+        assert_eq!(delays, vec![3, 2]);
+    }
+
+    #[test]
+    fn empty_recording_is_an_error() {
+        let recorder = Recorder::new(Capture::new(Palette::default(), 1), RecordingFormat::Apng);
+        assert!(recorder.save(&temp_path("empty.png")).is_err());
+    }
+
+    /** Reads a PNG as RGB, returning its size and pixels */
+    fn decode_png(path: &Path) -> ((u32, u32), Vec<u8>) {
+        let mut decoder = png::Decoder::new(std::io::BufReader::new(File::open(path).unwrap()));
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        ((info.width, info.height), pixels)
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("chip8-{}-{name}", std::process::id()))
+    }
+}
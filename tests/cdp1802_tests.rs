@@ -0,0 +1,145 @@
+mod common;
+
+mod tests {
+    use crate::common::{MachineBuilder, step_on_every_engine};
+    use chip8::Chip8;
+    use chip8::cdp1802::{Cdp1802, DISPLAY_ADDR, MachineCodeError};
+    use chip8::error::Chip8Error;
+    use chip8::quirks::Quirks;
+
+    /** A VIP machine that calls the routine at 0x210 with V2 as VX */
+    fn vip_machine(routine: &[u8]) -> Chip8 {
+        let mut chip8 = MachineBuilder::default()
+            .with_quirks(Quirks::vip())
+            .load(&[0x02, 0x10, 0x12, 0x02]);
+        chip8.load_memory(0x210, routine);
+        chip8
+    }
+
+    #[test]
+    fn routines_write_variables_through_r6_and_return_with_sep_r4() {
+        let mut chip8 = vip_machine(&[
+            0xF8, 0x2A, // LDI 0x2A
+            0x56, // STR R6: V2 = 0x2A
+            0x8A, // GLO RA
+            0xFC, 0x10, // ADI 0x10
+            0xAA, // PLO RA: I += 0x10
+            0xD4, // SEP R4
+        ]);
+        chip8.idx_reg = 0x300;
+        step_on_every_engine(&mut chip8);
+        assert_eq!(chip8.var_reg[2], 0x2A);
+        assert_eq!(chip8.idx_reg, 0x310);
+        assert_eq!(chip8.pc, 0x202);
+    }
+
+    #[test]
+    fn loops_and_arithmetic_run_on_the_1802() {
+        // adds V2 to itself 3 times, using RC as a counter and RD as the total
+        let mut chip8 = vip_machine(&[
+            0xE6, // 0x210: SEX R6
+            0xF8, 0x03, // 0x211: LDI 3
+            0xAC, // 0x213: PLO RC
+            0xF8, 0x00, // 0x214: LDI 0
+            0xAD, // 0x216: PLO RD
+            0x8D, // 0x217: GLO RD
+            0xF4, // 0x218: ADD
+            0xAD, // 0x219: PLO RD
+            0x2C, // 0x21A: DEC RC
+            0x8C, // 0x21B: GLO RC
+            0x3A, 0x17, // 0x21C: BNZ 0x217
+            0x8D, // 0x21E: GLO RD
+            0x56, // 0x21F: STR R6
+            0xE2, // 0x220: SEX R2
+            0xD4, // 0x221: SEP R4
+        ]);
+        chip8.var_reg[2] = 7;
+        step_on_every_engine(&mut chip8);
+        assert_eq!(chip8.var_reg[2], 21);
+
+        let mut cpu = Cdp1802::new();
+        let mut ram = [0; 4096];
+        // LDI 0x90, ADI 0x80 carries, SMI 0x20 then doesn't borrow
+        ram[..6].copy_from_slice(&[0xF8, 0x90, 0xFC, 0x80, 0xFF, 0x20]);
+        for _ in 0..2 {
+            cpu.step(&mut ram).unwrap();
+        }
+        assert_eq!((cpu.d, cpu.df), (0x10, true));
+        cpu.step(&mut ram).unwrap();
+        assert_eq!((cpu.d, cpu.df), (0xF0, false));
+        assert_eq!(cpu.cycles, 6);
+    }
+
+    #[test]
+    fn the_display_is_shared_with_machine_code() {
+        let mut chip8 = vip_machine(&[
+            0x0B, // LDN RB: the first byte of the display
+            0xFB, 0xFF, // XRI 0xFF
+            0x5B, // STR RB
+            0xD4, // SEP R4
+        ]);
+        let mut rows = [0; 32];
+        rows[0] = 1 << 63;
+        chip8.display.load_bitmask_rows(&rows);
+        step_on_every_engine(&mut chip8);
+        assert!(!chip8.display.get_pixel(0, 0));
+        assert!((1..8).all(|x| chip8.display.get_pixel(0, x)));
+        assert!(!chip8.display.get_pixel(0, 8));
+        assert!(chip8.draw_flag);
+        assert_eq!(chip8.ram[DISPLAY_ADDR], 0x7F);
+    }
+
+    #[test]
+    fn unsupported_instructions_leave_the_machine_unchanged() {
+        // OUT 4 isn't wired to anything on the VIP
+        let mut chip8 = vip_machine(&[0xF8, 0x2A, 0x56, 0x64, 0xD4]);
+        let before = chip8.snapshot();
+        assert_eq!(
+            chip8.try_cycle(),
+            Err(Chip8Error::MachineCode(MachineCodeError::Unsupported {
+                addr: 0x213,
+                opcode: 0x64
+            }))
+        );
+        assert_eq!(chip8.snapshot(), before);
+
+        // a routine that never hands control back
+        let mut chip8 = vip_machine(&[0x30, 0x10]);
+        assert_eq!(
+            chip8.try_cycle(),
+            Err(Chip8Error::MachineCode(MachineCodeError::NoReturn(0x210)))
+        );
+
+        // without the quirk 0NNN stays unsupported
+        let mut chip8 = vip_machine(&[0xD4]);
+        chip8.quirks.machine_code = false;
+        assert_eq!(
+            chip8.try_cycle(),
+            Err(Chip8Error::UnsupportedOpcode(0x0210))
+        );
+    }
+
+    #[test]
+    fn ef3_reports_the_key_latched_by_out_2() {
+        let mut chip8 = vip_machine(&[
+            0xE2, // SEX R2
+            0xF8, 0x05, // LDI 5
+            0x52, // STR R2
+            0x62, // OUT 2: latch key 5, R2 += 1
+            0x22, // DEC R2
+            0xF8, 0x00, // LDI 0
+            0x3E, 0x1C, // BN3 0x21C
+            0xF8, 0x01, // LDI 1
+            0x56, // 0x21C: STR R6
+            0xD4, // SEP R4
+        ]);
+        chip8.curr_input_key = Some(5);
+        let mut released = chip8.clone();
+        released.curr_input_key = None;
+
+        step_on_every_engine(&mut chip8);
+        step_on_every_engine(&mut released);
+        assert_eq!(chip8.var_reg[2], 1);
+        assert_eq!(released.var_reg[2], 0);
+    }
+}
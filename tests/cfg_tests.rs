@@ -0,0 +1,100 @@
+mod tests {
+    use chip8::cfg::{ControlFlowGraph, Edge, EdgeKind, Exit, Region, RegionKind};
+
+    const IBM_LOGO: &[u8] = include_bytes!("../ROMs/IBM Logo.ch8");
+
+    /** Calls a subroutine, skips over one of two halts, then dead code and sprite data */
+    const ROM: [u8; 19] = [
+        0xA2, 0x10, // 0x200: I = 0x210
+        0x22, 0x0A, // 0x202: call 0x20A
+        0x30, 0x01, // 0x204: skip if V0 == 1
+        0x12, 0x06, // 0x206: halt
+        0x12, 0x08, // 0x208: halt
+        0x60, 0x01, // 0x20A: V0 = 1
+        0x00, 0xEE, // 0x20C: return
+        0x60, 0x02, // 0x20E: never runs
+        0xF0, 0x90, 0xF0, // 0x210: sprite
+    ];
+
+    #[test]
+    fn follows_calls_skips_and_jumps() {
+        let graph = ControlFlowGraph::build(&ROM);
+        let starts: Vec<u16> = graph.blocks.keys().copied().collect();
+        assert_eq!(starts, [0x200, 0x204, 0x206, 0x208, 0x20A]);
+        assert_eq!(
+            graph.subroutines.iter().copied().collect::<Vec<_>>(),
+            [0x20A]
+        );
+
+        let entry = &graph.blocks[&0x200];
+        assert_eq!((entry.end, entry.exit), (0x204, Exit::Call));
+        assert_eq!(
+            entry.successors,
+            [
+                Edge {
+                    target: 0x20A,
+                    kind: EdgeKind::Call
+                },
+                Edge {
+                    target: 0x204,
+                    kind: EdgeKind::Fallthrough
+                },
+            ]
+        );
+        assert_eq!(graph.blocks[&0x204].exit, Exit::Skip);
+        assert_eq!(graph.blocks[&0x206].exit, Exit::Halt);
+        assert_eq!(graph.blocks[&0x20A].exit, Exit::Return);
+        assert!(graph.is_code(0x20D));
+        assert!(!graph.is_code(0x20E));
+    }
+
+    #[test]
+    fn splits_unreachable_code_from_referenced_data() {
+        let graph = ControlFlowGraph::build(&ROM);
+        assert_eq!(
+            graph.regions(),
+            [
+                Region {
+                    range: 0x20E..0x210,
+                    kind: RegionKind::Unreachable
+                },
+                Region {
+                    range: 0x210..0x213,
+                    kind: RegionKind::Data
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_indirect_jumps_and_machine_code_calls() {
+        let graph = ControlFlowGraph::build(&[0x01, 0x23, 0xB3, 0x00]);
+        assert_eq!(graph.machine_calls, [0x200]);
+        assert_eq!(graph.indirect_jumps, [0x202]);
+        let block = &graph.blocks[&0x200];
+        assert_eq!((block.end, block.exit), (0x204, Exit::IndirectJump));
+        assert!(block.successors.is_empty());
+        assert!(graph.to_dot().contains("\"0x200\" -> \"BNNN\""));
+    }
+
+    #[test]
+    fn ibm_logo_is_code_followed_by_sprite_data() {
+        let graph = ControlFlowGraph::build(IBM_LOGO);
+        // one data region per sprite, back to back up to the end of the ROM
+        let regions = graph.regions();
+        assert_eq!(regions.len(), 6);
+        assert_eq!(regions[0].range.start, 0x22A);
+        assert_eq!(regions[5].range.end, 0x200 + IBM_LOGO.len() as u16);
+        assert!(
+            regions
+                .windows(2)
+                .all(|pair| pair[0].range.end == pair[1].range.start)
+        );
+        assert!(regions.iter().all(|region| region.kind == RegionKind::Data));
+        assert_eq!(graph.blocks.values().last().unwrap().exit, Exit::Halt);
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph chip8 {"));
+        assert!(dot.contains("0x200: 00E0"));
+    }
+}
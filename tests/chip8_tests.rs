@@ -1,5 +1,13 @@
+// the baseline tests predate clippy and are kept as written
+#![allow(clippy::bool_assert_comparison, clippy::needless_borrow)]
+
+mod common;
+
 mod tests {
+    use crate::common::step_on_every_engine;
     use chip8::Chip8;
+    use chip8::error::Chip8Error;
+    use chip8::quirks::{Quirks, VIP_STACK_TOP};
 
     #[test]
     fn initialized_correctly() {
@@ -145,9 +153,75 @@ mod tests {
         assert_eq!(chip8.ram[chip8.idx_reg as usize + 2], 3);
     }
 
-    /** Loads an instruction and runs a single cycle */
+    #[test]
+    fn try_cycle_reports_errors_without_running() {
+        let mut chip8 = Chip8::new();
+        chip8.load_memory(0x200, &[0x00, 0xEE]);
+        assert_eq!(chip8.try_cycle(), Err(Chip8Error::StackUnderflow));
+        assert_eq!(chip8.pc, 0x200);
+
+        chip8.load_memory(0x200, &[0xFF, 0xFF]);
+        assert_eq!(
+            chip8.try_cycle(),
+            Err(Chip8Error::UnsupportedOpcode(0xFFFF))
+        );
+
+        chip8.idx_reg = 0xFFE;
+        chip8.load_memory(0x200, &[0xF3, 0x33]);
+        assert_eq!(chip8.try_cycle(), Err(Chip8Error::MemoryOutOfBounds(0xFFE)));
+
+        chip8.pc = 0xFFF;
+        assert_eq!(chip8.try_cycle(), Err(Chip8Error::PcOutOfBounds(0xFFF)));
+    }
+
+    #[test]
+    fn try_cycle_matches_cycle() {
+        let mut chip8 = Chip8::with_seed(3);
+        chip8.load_memory(0x200, &[0x22, 0x04, 0x63, 0x07, 0x73, 0x01, 0x00, 0xEE]);
+        let mut checked = chip8.clone();
+        for _ in 0..4 {
+            chip8.cycle();
+            checked.try_cycle().unwrap();
+        }
+        assert_eq!(chip8.snapshot(), checked.snapshot());
+    }
+
+    #[test]
+    fn calls_overflow_at_the_quirks_stack_depth() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks = Quirks::vip();
+        // a subroutine that calls itself
+        chip8.load_memory(0x200, &[0x22, 0x00]);
+        for _ in 0..12 {
+            chip8.try_cycle().unwrap();
+        }
+        assert_eq!(chip8.call_stack.len(), 12);
+        assert_eq!(chip8.try_cycle(), Err(Chip8Error::StackOverflow));
+        assert_eq!(chip8.call_stack.len(), 12);
+
+        chip8.quirks.stack_depth = 16;
+        assert_eq!(chip8.try_cycle(), Ok(()));
+    }
+
+    #[test]
+    fn vip_stack_lives_in_ram() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks = Quirks::vip();
+        chip8.load_memory(0x200, &[0x22, 0x10]);
+        chip8.load_memory(0x210, &[0x00, 0xEE]);
+        step_on_every_engine(&mut chip8);
+        assert_eq!(chip8.ram[VIP_STACK_TOP - 2..VIP_STACK_TOP], [0x02, 0x02]);
+
+        // programs can rewrite the return address
+        chip8.ram[VIP_STACK_TOP - 1] = 0x40;
+        step_on_every_engine(&mut chip8);
+        assert_eq!(chip8.pc, 0x240);
+        assert!(chip8.call_stack.is_empty());
+    }
+
+    /** Loads an instruction and runs a single cycle on every engine */
     fn load_run_instruction(chip8: &mut Chip8, instruction: &[u8]) {
         chip8.load_memory(chip8.pc, &instruction);
-        chip8.cycle();
+        step_on_every_engine(chip8);
     }
 }
@@ -0,0 +1,81 @@
+//! Machine setup shared by the integration tests. Each test crate only uses part of it
+#![allow(dead_code)]
+
+use chip8::Chip8;
+use chip8::blocks::BlockEngine;
+use chip8::engine::{Engine, Interpreter};
+use chip8::platform::Platform;
+use chip8::predecode::PredecodedEngine;
+use chip8::quirks::Quirks;
+use chip8::rom::ROM;
+use chip8::timing::Timing;
+
+/** Describes a test machine; `load` creates it with a program at the platform's load address */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MachineBuilder {
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub timing: Timing,
+}
+impl MachineBuilder {
+    pub fn with_platform(mut self, platform: Platform) -> MachineBuilder {
+        self.platform = platform;
+        self
+    }
+
+    pub fn with_quirks(mut self, quirks: Quirks) -> MachineBuilder {
+        self.quirks = quirks;
+        self
+    }
+
+    pub fn with_timing(mut self, timing: Timing) -> MachineBuilder {
+        self.timing = timing;
+        self
+    }
+
+    /** A machine seeded with 0, so CXNN rolls the same numbers in every test run */
+    pub fn load(&self, program: &[u8]) -> Chip8 {
+        let mut chip8 = Chip8::with_seed(0);
+        chip8.set_platform(self.platform);
+        chip8.quirks = self.quirks;
+        chip8.timing = self.timing;
+        chip8.load_rom(ROM::from_bytes(program));
+        chip8
+    }
+}
+
+/** A default CHIP-8 machine running `program` */
+pub fn machine(program: &[u8]) -> Chip8 {
+    MachineBuilder::default().load(program)
+}
+
+/** One of each engine, for tests that must pass whichever one runs the program */
+pub fn engines() -> Vec<Box<dyn Engine>> {
+    vec![
+        Box::new(Interpreter),
+        Box::new(PredecodedEngine::new()),
+        Box::new(BlockEngine::new()),
+    ]
+}
+
+/** Runs one instruction with `try_cycle` and on every engine, asserting they all agree */
+pub fn step_on_every_engine(chip8: &mut Chip8) {
+    let start = chip8.clone();
+    chip8.try_cycle().unwrap();
+    for mut engine in engines() {
+        let mut other = start.clone();
+        engine.step(&mut other);
+        assert_eq!(other.snapshot(), chip8.snapshot());
+    }
+}
+
+/** Runs a frame with `try_run_frame` and on every engine, asserting they all agree */
+pub fn frame_on_every_engine(chip8: &mut Chip8, cycles_per_frame: usize) {
+    let start = chip8.clone();
+    chip8.try_run_frame(cycles_per_frame).unwrap();
+    for mut engine in engines() {
+        let mut other = start.clone();
+        engine.run_frame(&mut other, cycles_per_frame);
+        assert_eq!(other.snapshot(), chip8.snapshot());
+    }
+}
@@ -0,0 +1,60 @@
+#![cfg(feature = "instrumentation")]
+
+mod common;
+
+mod tests {
+    use crate::common::machine;
+    use chip8::blocks::BlockEngine;
+    use chip8::engine::Engine;
+    use chip8::predecode::PredecodedEngine;
+
+    /** Draws the "0" glyph twice in the same place, sets both timers, then loops */
+    const ROM: [u8; 14] = [
+        0xA0, 0x50, // I = 0x50
+        0xD0, 0x05, // draw
+        0xD0, 0x05, // draw again, colliding
+        0x60, 0x05, // V0 = 5
+        0xF0, 0x15, // delay timer = V0
+        0xF0, 0x18, // sound timer = V0
+        0x12, 0x0C, // loop forever
+    ];
+
+    #[test]
+    fn counts_instructions_draws_and_timers() {
+        let mut chip8 = machine(&ROM);
+        for _ in 0..10 {
+            chip8.cycle();
+        }
+
+        let counters = chip8.counters();
+        assert_eq!(counters.instructions[0xA], 1);
+        assert_eq!(counters.instructions[0xD], 2);
+        assert_eq!(counters.instructions[0x1], 4);
+        assert_eq!(counters.total_instructions(), 10);
+        assert_eq!(counters.draws, 2);
+        assert_eq!(counters.collisions, 1);
+        assert_eq!(counters.timer_writes, 2);
+    }
+
+    #[test]
+    fn engines_count_like_the_interpreter() {
+        let mut plain = machine(&ROM);
+        plain.run_frame(25);
+
+        let mut predecoded = machine(&ROM);
+        PredecodedEngine::new().run_frame(&mut predecoded, 25);
+        let mut compiled = machine(&ROM);
+        BlockEngine::new().run_frame(&mut compiled, 25);
+
+        assert_eq!(plain.counters(), predecoded.counters());
+        assert_eq!(plain.counters(), compiled.counters());
+    }
+
+    #[test]
+    fn reset_clears_counters() {
+        let mut chip8 = machine(&ROM);
+        chip8.run_frame(5);
+        chip8.reset_counters();
+        assert_eq!(chip8.counters().total_instructions(), 0);
+    }
+}
@@ -0,0 +1,76 @@
+mod tests {
+    use chip8::Chip8;
+    use chip8::display::{DirtyRect, Display};
+    use chip8::palette::Palette;
+
+    #[test]
+    fn frame_id_changes_with_display() {
+        let mut display = Display::new();
+        let start = display.frame_id();
+        display.flip_pixel(0, 0);
+        assert_ne!(display.frame_id(), start);
+
+        let drawn = display.frame_id();
+        display.get_pixel(0, 0);
+        assert_eq!(display.frame_id(), drawn);
+    }
+
+    #[test]
+    fn op_dxyn_reports_dirty_rect() {
+        let mut chip8 = Chip8::new();
+        assert!(!chip8.display.is_dirty());
+
+        // draw the "0" font glyph (4x5 pixels) at (10, 3)
+        chip8.var_reg[0x1] = 10;
+        chip8.var_reg[0x2] = 3;
+        chip8.idx_reg = 0x50;
+        chip8.load_memory(chip8.pc, &[0xD1, 0x25]);
+        chip8.cycle();
+
+        assert_eq!(
+            chip8.display.dirty_rect(),
+            Some(DirtyRect {
+                x: 10,
+                y: 3,
+                width: 4,
+                height: 5
+            })
+        );
+        chip8.display.clear_dirty();
+        assert!(!chip8.display.is_dirty());
+    }
+
+    #[test]
+    fn bitmask_rows_put_leftmost_pixel_in_high_bit() {
+        let mut display = Display::new();
+        display.flip_pixel(0, 0);
+        display.flip_pixel(31, 63);
+        let rows = display.as_bitmask_rows();
+        assert_eq!(rows[0], 1 << 63);
+        assert_eq!(rows[31], 1);
+        assert_eq!(rows[1..31], [0; 30]);
+    }
+
+    #[test]
+    fn to_rgba_uses_palette() {
+        let mut display = Display::new();
+        display.flip_pixel(0, 1);
+        let palette = Palette::from_hex("#010203,#A0B0C0").unwrap();
+        let rgba = display.to_rgba(&palette);
+        assert_eq!(rgba.len(), chip8::WIDTH * chip8::HEIGHT * 4);
+        assert_eq!(rgba[0..8], [0x01, 0x02, 0x03, 0xFF, 0xA0, 0xB0, 0xC0, 0xFF]);
+    }
+
+    #[test]
+    fn to_ascii_draws_font_glyph() {
+        let mut chip8 = Chip8::new();
+        chip8.idx_reg = 0x50;
+        chip8.load_memory(chip8.pc, &[0xD0, 0x05]);
+        chip8.cycle();
+
+        let ascii = chip8.display.to_ascii();
+        let rows: Vec<&str> = ascii.lines().take(5).map(|row| &row[..4]).collect();
+        assert_eq!(rows, vec!["####", "#..#", "#..#", "#..#", "####"]);
+        assert_eq!(ascii.lines().count(), chip8::HEIGHT);
+    }
+}
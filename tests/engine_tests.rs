@@ -0,0 +1,183 @@
+mod common;
+
+mod tests {
+    use crate::common::{MachineBuilder, machine};
+    use chip8::Chip8;
+    use chip8::blocks::BlockEngine;
+    use chip8::engine::{Engine, Interpreter};
+    use chip8::platform::Platform;
+    use chip8::predecode::PredecodedEngine;
+    use chip8::quirks::Quirks;
+
+    /**
+     * Runs a machine on every engine and through `try_run_frame`, checking they all match the
+     * interpreter after each frame. Odd frame sizes make frames end partway through blocks.
+     * Returns the interpreter's machine after the run with the longest frames
+     */
+    fn assert_engines_agree(start: Chip8, frames: usize) -> Chip8 {
+        let mut plain = start.clone();
+        for cycles_per_frame in [1, 7, 100] {
+            plain = start.clone();
+            let mut checked = plain.clone();
+            let mut engines: Vec<(Box<dyn Engine>, Chip8)> = vec![
+                (Box::new(PredecodedEngine::new()), plain.clone()),
+                (Box::new(BlockEngine::new()), plain.clone()),
+            ];
+            for _ in 0..frames {
+                Interpreter.run_frame(&mut plain, cycles_per_frame);
+                checked.try_run_frame(cycles_per_frame).unwrap();
+                assert_eq!(checked.snapshot(), plain.snapshot());
+                for (engine, chip8) in engines.iter_mut() {
+                    engine.run_frame(chip8, cycles_per_frame);
+                    assert_eq!(chip8.snapshot(), plain.snapshot());
+                }
+            }
+        }
+        plain
+    }
+
+    #[test]
+    fn test_rom_matches_on_every_engine() {
+        let rom = std::fs::read("ROMs/test_opcode.ch8").unwrap();
+        assert_engines_agree(machine(&rom), 60);
+        for quirks in [Quirks::vip(), Quirks::schip()] {
+            assert_engines_agree(MachineBuilder::default().with_quirks(quirks).load(&rom), 60);
+        }
+    }
+
+    #[test]
+    fn ibm_logo_matches_on_every_engine() {
+        let rom = std::fs::read("ROMs/IBM Logo.ch8").unwrap();
+        assert_engines_agree(machine(&rom), 30);
+    }
+
+    #[test]
+    fn long_blocks_of_arithmetic_match_on_every_engine() {
+        let rom = [
+            0x60, 0xF0, // 0x200: V0 = 0xF0
+            0x80, 0x11, // 0x202: V0 |= V1
+            0x82, 0x02, // 0x204: V2 &= V0
+            0x83, 0x23, // 0x206: V3 ^= V2
+            0x84, 0x14, // 0x208: V4 += V1
+            0x85, 0x45, // 0x20A: V5 -= V4
+            0x86, 0x16, // 0x20C: V6 = V1 >> 1
+            0x87, 0x57, // 0x20E: V5 = V5 - V7
+            0x88, 0x1E, // 0x210: V8 = V1 << 1
+            0x72, 0x3C, // 0x212: V2 += 0x3C
+            0xA3, 0x00, // 0x214: I = 0x300
+            0xF5, 0x33, // 0x216: BCD of V5
+            0xF8, 0x55, // 0x218: store V0..V8
+            0xF2, 0x65, // 0x21A: load V0..V2
+            0x71, 0x01, // 0x21C: V1 += 1
+            0x12, 0x02, // 0x21E: loop
+        ];
+        for quirks in [Quirks::default(), Quirks::vip(), Quirks::schip()] {
+            let chip8 =
+                assert_engines_agree(MachineBuilder::default().with_quirks(quirks).load(&rom), 20);
+            assert_ne!(chip8.var_reg[0x1], 0);
+        }
+    }
+
+    #[test]
+    fn writes_over_cached_code_match_on_every_engine() {
+        // the block at 0x20C runs once with "V2 += 1", then FX55 rewrites that to "V2 += 0x10"
+        let rom = [
+            0x60, 0x72, // 0x200: V0 = 0x72
+            0x61, 0x10, // 0x202: V1 = 0x10
+            0x12, 0x0C, // 0x204: jump to 0x20C
+            0xA2, 0x0E, // 0x206: I = 0x20E
+            0xF1, 0x55, // 0x208: store V0..V1 at I
+            0x00, 0xE0, // 0x20A: clear screen
+            0x73, 0x01, // 0x20C: V3 += 1
+            0x72, 0x01, // 0x20E: V2 += 1
+            0x33, 0x02, // 0x210: skip if V3 == 2
+            0x12, 0x06, // 0x212: jump to 0x206
+            0x12, 0x14, // 0x214: loop forever
+        ];
+        let chip8 = assert_engines_agree(machine(&rom), 10);
+        assert_eq!(chip8.var_reg[2], 0x11);
+
+        // V0/V1 hold "V2 = 0x22", which FX55 writes over the "V2 = 0x11" at 0x20C
+        // after it has already run once, so the second pass must see the new code
+        let rom = [
+            0x60, 0x62, // 0x200: V0 = 0x62
+            0x61, 0x22, // 0x202: V1 = 0x22
+            0xA2, 0x0C, // 0x204: I = 0x20C
+            0x33, 0x00, // 0x206: skip if V3 == 0
+            0xF1, 0x55, // 0x208: store V0..V1 at I
+            0x73, 0x01, // 0x20A: V3 += 1
+            0x62, 0x11, // 0x20C: V2 = 0x11
+            0x33, 0x02, // 0x20E: skip if V3 == 2
+            0x12, 0x06, // 0x210: jump to 0x206
+            0x12, 0x12, // 0x212: loop forever
+        ];
+        let chip8 = assert_engines_agree(machine(&rom), 20);
+        assert_eq!(chip8.var_reg[2], 0x22);
+    }
+
+    #[test]
+    fn fx33_over_cached_code_matches_on_every_engine() {
+        // BCD of 200 writes 2, 0, 0 from 0x205, turning "jump to 0x208" into "jump to 0x202"
+        let rom = [
+            0x60, 0xC8, // 0x200: V0 = 200
+            0x71, 0x01, // 0x202: V1 += 1
+            0x12, 0x08, // 0x204: jump to 0x208
+            0x00, 0xE0, // 0x206: overwritten, never run
+            0xA2, 0x05, // 0x208: I = 0x205
+            0xF0, 0x33, // 0x20A: BCD of V0 at I
+            0x12, 0x02, // 0x20C: jump to 0x202
+        ];
+        let chip8 = assert_engines_agree(machine(&rom), 12);
+        assert_eq!(chip8.ram[0x204..0x206], [0x12, 0x02]);
+    }
+
+    #[test]
+    fn calls_pushing_onto_cached_code_match_on_every_engine() {
+        // with the stack in RAM, the call from 0x0DE stores its return address 0x0E0 at 0xECE,
+        // turning the already run "V2 += 1" there into "00E0"
+        let mut start = MachineBuilder::default()
+            .with_quirks(Quirks::vip())
+            .load(&[0x1E, 0xCC]); // 0x200: jump to 0xECC
+        start.load_memory(
+            0xECC,
+            &[
+                0x73, 0x01, // 0xECC: V3 += 1
+                0x72, 0x01, // 0xECE: V2 += 1, then the return address
+                0x33, 0x02, // 0xED0: skip if V3 == 2
+                0x10, 0xDE, // 0xED2: jump to 0x0DE
+                0x1E, 0xD4, // 0xED4: loop forever
+            ],
+        );
+        start.load_memory(0x0DE, &[0x2E, 0xCC]); // 0x0DE: call 0xECC
+
+        let chip8 = assert_engines_agree(start, 10);
+        assert_eq!(chip8.ram[0xECE..0xED0], [0x00, 0xE0]);
+        assert_eq!((chip8.var_reg[2], chip8.var_reg[3]), (1, 2));
+    }
+
+    #[test]
+    fn mega_chip_code_past_4kb_matches_on_every_engine() {
+        // runs off the end of the first 4KB into code at 0x1000 that would alias 0x000, and
+        // on to 0x1200 that would alias 0x200
+        let mut rom = vec![0; 0x1204 - 0x200];
+        let mut put = |addr: usize, bytes: [u8; 2]| {
+            rom[addr - 0x200..addr - 0x200 + 2].copy_from_slice(&bytes);
+        };
+        put(0x200, [0x60, 0x01]); // V0 = 1
+        put(0x202, [0x1F, 0xFC]); // jump to 0xFFC
+        put(0xFFC, [0x71, 0x01]); // V1 += 1
+        put(0xFFE, [0x72, 0x01]); // V2 += 1
+        for addr in (0x1000..0x1200).step_by(2) {
+            put(addr, [0x73, 0x01]); // V3 += 1
+        }
+        put(0x1200, [0x65, 0x55]); // V5 = 0x55
+        put(0x1202, [0x12, 0x00]); // jump to 0x200
+        let start = MachineBuilder::default()
+            .with_platform(Platform::MegaChip)
+            .load(&rom);
+
+        let chip8 = assert_engines_agree(start, 10);
+        assert_eq!(chip8.var_reg[0x5], 0x55);
+        assert_ne!(chip8.var_reg[0x3], 0);
+    }
+}
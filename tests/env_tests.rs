@@ -0,0 +1,159 @@
+mod tests {
+    use chip8::env::{Chip8Env, EnvConfig, RamScore, ScoreEncoding};
+
+    /** Counts up in V0 forever, storing it at 0x300 as the "score" */
+    const COUNTER: [u8; 8] = [0xA3, 0x00, 0x70, 0x01, 0xF0, 0x55, 0x12, 0x02];
+
+    /** Draws the "0" glyph at a random position every loop */
+    const RANDOM_DRAW: [u8; 10] = [0xA0, 0x50, 0xC0, 0x3F, 0xC1, 0x1F, 0xD0, 0x15, 0x12, 0x02];
+
+    fn counter_env(frame_skip: usize) -> Chip8Env {
+        let config = EnvConfig {
+            cycles_per_frame: 3,
+            frame_skip,
+            ..EnvConfig::default()
+        };
+        let score = RamScore::new(0x300, 1, ScoreEncoding::Binary).unwrap();
+        Chip8Env::new(&COUNTER, config, Box::new(score)).unwrap()
+    }
+
+    #[test]
+    fn reward_is_score_increase() {
+        let mut env = counter_env(1);
+        let (_, first, done) = env.step(None);
+        assert_eq!(first, 1.0);
+        assert!(!done);
+        let (_, second, _) = env.step(None);
+        assert_eq!(second, 1.0);
+    }
+
+    #[test]
+    fn frame_skip_sums_rewards() {
+        let mut env = counter_env(4);
+        let (_, reward, _) = env.step(None);
+        assert_eq!(reward, 4.0);
+    }
+
+    #[test]
+    fn episode_ends_at_target_or_max_steps() {
+        let mut score = RamScore::new(0x300, 1, ScoreEncoding::Binary).unwrap();
+        score.target = Some(3);
+        let mut env = Chip8Env::new(
+            &COUNTER,
+            EnvConfig {
+                cycles_per_frame: 3,
+                frame_skip: 1,
+                ..EnvConfig::default()
+            },
+            Box::new(score),
+        )
+        .unwrap();
+        let dones: Vec<bool> = (0..3).map(|_| env.step(None).2).collect();
+        assert_eq!(dones, vec![false, false, true]);
+
+        let mut env = counter_env(1);
+        env.config.max_steps = Some(2);
+        assert!(!env.step(None).2);
+        assert!(env.step(None).2);
+        env.reset(0);
+        assert_eq!(env.steps(), 0);
+    }
+
+    #[test]
+    fn bcd_scores_are_decoded() {
+        let mut env = counter_env(1);
+        env.reset(0);
+        let score = RamScore::new(0x300, 3, ScoreEncoding::Bcd).unwrap();
+        let mut chip8 = env.chip8().clone();
+        chip8.load_memory(0x300, &[1, 2, 3]);
+        assert_eq!(score.score(&chip8), 123);
+    }
+
+    #[test]
+    fn cloned_envs_roll_out_identically() {
+        let score = RamScore::new(0x300, 1, ScoreEncoding::Binary).unwrap();
+        let mut env = Chip8Env::new(&RANDOM_DRAW, EnvConfig::default(), Box::new(score)).unwrap();
+        let first_obs = env.reset(1234);
+        env.step(Some(0x5));
+
+        let mut branch = env.clone();
+        for _ in 0..10 {
+            assert_eq!(env.step(None), branch.step(None));
+        }
+
+        assert_eq!(env.reset(1234), first_obs);
+        let mut other = env.clone();
+        other.reset(4321);
+        assert_ne!(env.step(None).0, other.step(None).0);
+    }
+
+    #[test]
+    fn roms_too_big_for_memory_are_rejected() {
+        let score = RamScore::new(0x300, 1, ScoreEncoding::Binary).unwrap();
+        let rom = vec![0x12; 3585];
+        let result = Chip8Env::new(&rom, EnvConfig::default(), Box::new(score.clone()));
+        assert_eq!(
+            result.err(),
+            Some("ROMs are at most 3584 bytes, not 3585".to_string())
+        );
+        assert!(Chip8Env::new(&rom[..3584], EnvConfig::default(), Box::new(score)).is_ok());
+    }
+
+    #[test]
+    fn pong_style_bcd_scores_are_rewarded() {
+        // like Pong, keep the score in V0 and write its digits with FX33 before drawing them
+        let rom = [
+            0x70, 0x01, // 0x200: V0 += 1
+            0xA3, 0x00, // 0x202: I = 0x300
+            0xF0, 0x33, // 0x204: BCD of V0 at I
+            0x12, 0x00, // 0x206: loop
+        ];
+        let config = EnvConfig {
+            cycles_per_frame: 4,
+            frame_skip: 1,
+            ..EnvConfig::default()
+        };
+        let mut score = RamScore::new(0x300, 3, ScoreEncoding::Bcd).unwrap();
+        score.target = Some(12);
+        let mut env = Chip8Env::new(&rom, config, Box::new(score)).unwrap();
+        let steps: Vec<(f32, bool)> = (0..12)
+            .map(|_| {
+                let (_, reward, done) = env.step(None);
+                (reward, done)
+            })
+            .collect();
+        assert!(steps[..11].iter().all(|step| *step == (1.0, false)));
+        assert_eq!(steps[11], (1.0, true));
+        assert_eq!(env.chip8().ram[0x300..0x303], [0, 1, 2]);
+    }
+
+    #[test]
+    fn scores_outside_memory_are_rejected() {
+        assert!(RamScore::new(0xFFE, 2, ScoreEncoding::Binary).is_ok());
+        assert_eq!(
+            RamScore::new(0xFFE, 3, ScoreEncoding::Binary).err(),
+            Some("Scores must fit in memory, not 3 bytes at 0xffe".to_string())
+        );
+        assert!(RamScore::new(0x300, 0, ScoreEncoding::Bcd).is_err());
+    }
+
+    #[test]
+    fn long_scores_saturate() {
+        let env = counter_env(1);
+        let mut chip8 = env.chip8().clone();
+        chip8.load_memory(0x300, &[9; 12]);
+        let bcd = RamScore::new(0x300, 12, ScoreEncoding::Bcd).unwrap();
+        assert_eq!(bcd.score(&chip8), u32::MAX);
+        let binary = RamScore::new(0x300, 5, ScoreEncoding::Binary).unwrap();
+        assert_eq!(binary.score(&chip8), u32::MAX);
+    }
+
+    #[test]
+    fn rom_errors_end_the_episode() {
+        let score = RamScore::new(0x300, 1, ScoreEncoding::Binary).unwrap();
+        let mut env = Chip8Env::new(&[0xFF, 0xFF], EnvConfig::default(), Box::new(score)).unwrap();
+        let (_, reward, done) = env.step(None);
+        assert_eq!(reward, 0.0);
+        assert!(done);
+    }
+}
@@ -0,0 +1,170 @@
+mod common;
+
+mod tests {
+    use crate::common::{engines, machine, step_on_every_engine};
+    use chip8::engine::Engine;
+    use chip8::error::Chip8Error;
+    use chip8::font::{Font, FontSet};
+    use chip8::lint;
+    use chip8::{Chip8, FONT_START};
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn fx29_points_i_at_the_glyph_for_the_low_nibble() {
+        let mut chip8 = machine(&[
+            0x60, 0x1A, // 0x200: V0 = 0x1A
+            0xF0, 0x29, // 0x202: I = the "A" glyph
+            0x61, 0x00, // 0x204: V1 = 0
+            0xD1, 0x15, // 0x206: draw it at (0, 0)
+        ]);
+        for _ in 0..4 {
+            step_on_every_engine(&mut chip8);
+        }
+        assert_eq!(chip8.idx_reg as usize, FONT_START + 0xA * 5);
+        // the top row of "A" is 0xF0 and its last row 0x90
+        assert!((0..4).all(|x| chip8.display.get_pixel(0, x)));
+        assert!(chip8.display.get_pixel(4, 0) && chip8.display.get_pixel(4, 3));
+        assert!(!chip8.display.get_pixel(4, 1));
+    }
+
+    #[test]
+    fn font_sets_move_to_their_address_and_fx30_finds_big_digits() {
+        let mut chip8 = machine(&[
+            0x60, 0x07, // 0x200: V0 = 7
+            0xF0, 0x29, // 0x202: I = the small "7"
+            0xF0, 0x30, // 0x204: I = the big "7"
+        ]);
+        let schip = FontSet::Schip.font();
+        chip8.load_font(&schip, 0x100).unwrap();
+        assert_eq!(chip8.font_addr, 0x100);
+        assert_eq!(chip8.font_region(), 0x100..0x100 + 180);
+        assert!(
+            chip8.ram[FONT_START..FONT_START + 80]
+                .iter()
+                .all(|b| *b == 0)
+        );
+        assert_eq!(chip8.ram[0x100..0x150], schip.small);
+        assert_eq!(chip8.ram[0x150..0x1B4], schip.big[..]);
+
+        step_on_every_engine(&mut chip8);
+        step_on_every_engine(&mut chip8);
+        assert_eq!(chip8.idx_reg, 0x100 + 7 * 5);
+        step_on_every_engine(&mut chip8);
+        assert_eq!(chip8.idx_reg, 0x150 + 7 * 10);
+        assert_eq!(chip8.ram[0x196..0x1A0], schip.big[70..80]);
+
+        // sets differ where the interpreters did
+        assert_ne!(FontSet::Vip.font().glyph(1), Font::default().glyph(1));
+        assert_ne!(
+            FontSet::Dream6800.font().glyph(4),
+            FontSet::Eti660.font().glyph(4)
+        );
+        assert_eq!(FontSet::Octo.font().big.len(), 160);
+        assert!(FontSet::Vip.font().big.is_empty());
+        assert_eq!(FontSet::from_name("ETI660"), Some(FontSet::Eti660));
+        assert_eq!(FontSet::from_name("chip48"), None);
+    }
+
+    #[test]
+    fn fx30_without_a_big_glyph_is_unsupported() {
+        let program = [
+            0x61, 0x09, // 0x200: V1 = 9
+            0xF1, 0x30, // 0x202: I = the big "9"
+            0x61, 0x0A, // 0x204: V1 = 0xA
+            0xF1, 0x30, // 0x206: I = the big "A"
+        ];
+        let mut chip8 = machine(&program);
+        step_on_every_engine(&mut chip8);
+        assert_eq!(
+            chip8.try_cycle(),
+            Err(Chip8Error::UnsupportedOpcode(0xF130))
+        );
+        assert_eq!(chip8.pc, 0x202);
+
+        // SUPER-CHIP's big font stops at 9
+        chip8
+            .load_font(&FontSet::Schip.font(), FONT_START as u16)
+            .unwrap();
+        for _ in 0..2 {
+            step_on_every_engine(&mut chip8);
+        }
+        assert_eq!(chip8.idx_reg as usize, FONT_START + 80 + 9 * 10);
+        assert_eq!(
+            chip8.try_cycle(),
+            Err(Chip8Error::UnsupportedOpcode(0xF130))
+        );
+
+        chip8
+            .load_font(&FontSet::Octo.font(), FONT_START as u16)
+            .unwrap();
+        step_on_every_engine(&mut chip8);
+        assert_eq!(chip8.idx_reg as usize, FONT_START + 80 + 0xA * 10);
+    }
+
+    #[test]
+    fn custom_font_files_are_checked_and_must_fit_in_memory() {
+        let path = env::temp_dir().join("chip8_font_tests_custom.bin");
+        let mut bytes: Vec<u8> = (0..80).collect();
+        fs::write(&path, &bytes).unwrap();
+        let font = Font::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(font.glyph(2), [10, 11, 12, 13, 14]);
+        assert_eq!(font.size(), 80);
+
+        bytes.extend([0xFF; 100]);
+        assert_eq!(Font::from_bytes(&bytes).unwrap().big, [0xFF; 100]);
+        assert_eq!(
+            Font::from_bytes(&bytes[..79]),
+            Err("Font files are 80, 180 or 240 bytes, not 79".to_string())
+        );
+
+        let mut chip8 = Chip8::with_seed(0);
+        assert!(chip8.load_font(&font, 0xFB0).is_ok());
+        assert!(chip8.load_font(&font, 0xFB1).is_err());
+        // a failed load leaves the previous font where it was
+        assert_eq!(chip8.font_addr, 0xFB0);
+    }
+
+    #[test]
+    fn every_engine_agrees_on_font_lookups() {
+        let program = [
+            0x60, 0x03, // 0x200: V0 = 3
+            0xF0, 0x29, // 0x202: I = the small "3"
+            0xD0, 0x05, // 0x204: draw it
+            0xF0, 0x30, // 0x206: I = the big "3"
+            0xD0, 0x0A, // 0x208: draw it
+            0x70, 0x01, // 0x20A: next digit
+            0x12, 0x02, // 0x20C: loop
+        ];
+        let mut expected = machine(&program);
+        expected.load_font(&FontSet::Octo.font(), 0).unwrap();
+        let mut checked = expected.clone();
+        let mut engines: Vec<(Box<dyn Engine>, Chip8)> = engines()
+            .into_iter()
+            .map(|engine| (engine, expected.clone()))
+            .collect();
+        for _ in 0..5 {
+            expected.run_frame(9);
+            checked.try_run_frame(9).unwrap();
+            for (engine, chip8) in engines.iter_mut() {
+                engine.run_frame(chip8, 9);
+            }
+        }
+        assert_eq!(checked.snapshot(), expected.snapshot());
+        for (_, chip8) in &engines {
+            assert_eq!(chip8.snapshot(), expected.snapshot());
+        }
+    }
+
+    #[test]
+    fn fx29_sets_i_for_the_linter() {
+        let rom = [
+            0xA3, 0x00, // 0x200: I = 0x300
+            0xF1, 0x55, // 0x202: store V0..V1
+            0xF0, 0x29, // 0x204: I = a glyph, so the increment no longer matters
+            0xD0, 0x05, // 0x206: draw it
+        ];
+        assert_eq!(lint::check(&rom), []);
+    }
+}
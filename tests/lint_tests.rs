@@ -0,0 +1,153 @@
+mod tests {
+    use chip8::Chip8;
+    use chip8::error::Chip8Error;
+    use chip8::lint::{self, Lint, Linter};
+    use chip8::quirks::Quirks;
+    use chip8::rom::ROM;
+
+    const IBM_LOGO: &[u8] = include_bytes!("../ROMs/IBM Logo.ch8");
+
+    #[test]
+    fn flags_platform_specific_instructions_statically() {
+        let rom = [
+            0x81, 0x26, // 0x200: V1 >>= V2
+            0xA3, 0x00, // 0x202: I = 0x300
+            0xF1, 0x55, // 0x204: store V0..V1
+            0xF1, 0x55, // 0x206: store again, after I moved on the VIP only
+            0x01, 0x23, // 0x208: machine code
+            0xB2, 0x10, // 0x20A: jump to 0x210 + V0
+        ];
+        assert_eq!(
+            lint::check(&rom),
+            [
+                Lint::Shift {
+                    addr: 0x200,
+                    x: 1,
+                    y: 2
+                },
+                Lint::IndexAfterMemory {
+                    addr: 0x204,
+                    used_at: 0x206
+                },
+                Lint::JumpOffset { addr: 0x20A },
+                Lint::MachineCall { addr: 0x208 },
+            ]
+        );
+        let recommendation = lint::recommend(&lint::check(&rom));
+        assert_eq!(recommendation.preset, "vip");
+        assert_eq!(recommendation.reasons.len(), 3);
+    }
+
+    #[test]
+    fn tracks_constants_for_edge_draws_and_writes_over_code() {
+        let rom = [
+            0x60, 0x3C, // 0x200: V0 = 60
+            0x61, 0x00, // 0x202: V1 = 0
+            0xA2, 0x0C, // 0x204: I = 0x20C
+            0xD0, 0x11, // 0x206: draw 0xF0 at x = 60, inside the screen
+            0x70, 0x01, // 0x208: V0 = 61
+            0xD0, 0x11, // 0x20A: now the last lit pixel is past the edge
+            0xF0, 0x55, // 0x20C: stores V0 over itself
+            0x12, 0x0E, // 0x20E: halt
+        ];
+        assert_eq!(
+            lint::check(&rom),
+            [
+                Lint::EdgeDraw { addr: 0x20A },
+                Lint::SelfModifying {
+                    addr: 0x20C,
+                    target: 0x20C
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_deep_and_recursive_calls() {
+        // a chain of 13 subroutines, each calling the next
+        let mut rom = vec![0x22, 0x04, 0x12, 0x02];
+        for level in 0..13u16 {
+            let next = 0x208 + level * 4;
+            match level {
+                12 => rom.extend([0x00, 0xEE, 0x00, 0xEE]),
+                _ => rom.extend([0x20 | (next >> 8) as u8, next as u8, 0x00, 0xEE]),
+            }
+        }
+        assert_eq!(lint::check(&rom), [Lint::StackDepth { depth: 13 }]);
+        assert_eq!(lint::recommend(&lint::check(&rom)).preset, "schip");
+
+        let recursive = [0x22, 0x02, 0x22, 0x02];
+        assert_eq!(
+            lint::check(&recursive),
+            [Lint::Recursion { subroutine: 0x202 }]
+        );
+    }
+
+    #[test]
+    fn running_finds_computed_draws_and_stops_at_machine_code() {
+        let rom = [
+            0xC0, 0x00, // 0x200: V0 = random & 0, unknown statically
+            0x70, 0x3F, // 0x202: V0 = 63
+            0xA2, 0x0C, // 0x204: I = 0x20C
+            0xD0, 0x01, // 0x206: draw 0xF0 at x = 63
+            0x01, 0x23, // 0x208: machine code
+            0x12, 0x0A, // 0x20A: never runs
+            0xF0, // 0x20C: sprite
+        ];
+        assert!(
+            lint::check(&rom)
+                .iter()
+                .all(|lint| !matches!(lint, Lint::EdgeDraw { .. }))
+        );
+
+        let mut chip8 = Chip8::with_seed(0);
+        chip8.load_rom(ROM::from_bytes(&rom));
+        let mut linter = Linter::new();
+        linter.check_rom(&rom);
+        assert_eq!(
+            linter.run(&mut chip8, 1, 10),
+            Err(Chip8Error::UnsupportedOpcode(0x0123))
+        );
+        assert_eq!(
+            linter.lints(),
+            [
+                Lint::EdgeDraw { addr: 0x206 },
+                Lint::MachineCall { addr: 0x208 },
+            ]
+        );
+        assert_eq!(chip8.pc, 0x208);
+    }
+
+    #[test]
+    fn running_finds_calls_pushing_onto_code() {
+        // with the stack in RAM, the call stores its return address over the code at 0xECE
+        let mut chip8 = Chip8::with_seed(0);
+        chip8.quirks = Quirks::vip();
+        chip8.load_memory(0x200, &[0x1E, 0xCE]); // 0x200: jump to 0xECE
+        chip8.load_memory(0xECE, &[0x12, 0x04]); // 0xECE: jump to 0x204
+        chip8.load_memory(0x204, &[0x22, 0x08, 0x12, 0x06]); // call 0x208, then loop
+        chip8.load_memory(0x208, &[0x00, 0xEE]); // return
+        let mut linter = Linter::new();
+        assert_eq!(linter.run(&mut chip8, 1, 6), Ok(()));
+        assert_eq!(
+            linter.lints(),
+            [Lint::SelfModifying {
+                addr: 0x204,
+                target: 0xECE
+            }]
+        );
+    }
+
+    #[test]
+    fn ibm_logo_needs_no_quirks() {
+        let mut chip8 = Chip8::with_seed(0);
+        chip8.load_rom(ROM::from_bytes(IBM_LOGO));
+        let mut linter = Linter::new();
+        linter.check_rom(IBM_LOGO);
+        assert_eq!(linter.run(&mut chip8, 20, 10), Ok(()));
+        assert!(linter.lints().is_empty());
+        let recommendation = linter.recommend();
+        assert_eq!(recommendation.preset, "default");
+        assert!(recommendation.reasons.is_empty());
+    }
+}
@@ -0,0 +1,200 @@
+mod common;
+
+mod tests {
+    use crate::common::{MachineBuilder, engines, step_on_every_engine};
+    use chip8::Chip8;
+    use chip8::error::Chip8Error;
+    use chip8::megachip::{BlendMode, DigitalSound, Framebuffer, MEGA_HEIGHT, MEGA_WIDTH};
+    use chip8::palette::Palette;
+    use chip8::platform::Platform;
+    use chip8::state::Snapshot;
+
+    const RED: [u8; 4] = [0xFF, 0x00, 0x00, 0xFF];
+    const BLUE: [u8; 4] = [0x00, 0x00, 0xFF, 0xFF];
+
+    /** Draws a 2x2 sprite whose palette and pixels are stored past the first 4KB */
+    fn sprite_rom() -> Vec<u8> {
+        let mut rom = vec![
+            0x00, 0x11, // 0x200: MEGA mode on
+            0x01, 0x00, 0x10, 0x00, // 0x202: I = 0x001000
+            0x02, 0x02, // 0x206: load two colours
+            0x03, 0x02, // 0x208: sprites are 2 wide
+            0x04, 0x02, // 0x20A: and 2 tall
+            0x01, 0x00, 0x10, 0x08, // 0x20C: I = 0x001008
+            0x60, 0x0A, // 0x210: V0 = 10
+            0x61, 0x05, // 0x212: V1 = 5
+            0xD0, 0x10, // 0x214: draw at (10, 5)
+            0x00, 0xE0, // 0x216: present the frame
+        ];
+        rom.resize(0x1000 - 0x200, 0);
+        // ARGB red and blue, then the sprite's palette indices
+        rom.extend([0xFF, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF]);
+        rom.extend([0x01, 0x00, 0x02, 0x01]);
+        rom
+    }
+
+    fn pixel(chip8: &Chip8, y: usize, x: usize) -> [u8; 4] {
+        let rgba = chip8.display.to_rgba(&Palette::default());
+        let start = (y * MEGA_WIDTH + x) * 4;
+        rgba[start..start + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn mode_switches_change_the_screen() {
+        let mut chip8 = MachineBuilder::default()
+            .with_platform(Platform::MegaChip)
+            .load(&[0x00, 0x11, 0x00, 0x10]);
+        assert!(chip8.display.mega_mode().is_none());
+        assert_eq!(
+            chip8.display.to_rgba(&Palette::default()).len(),
+            64 * 32 * 4
+        );
+
+        step_on_every_engine(&mut chip8);
+        assert!(chip8.display.mega_mode().is_some());
+        let rgba = chip8.display.to_rgba(&Palette::default());
+        assert_eq!(rgba.len(), MEGA_WIDTH * MEGA_HEIGHT * 4);
+        assert_eq!(chip8.display.to_ascii().lines().count(), MEGA_HEIGHT);
+
+        step_on_every_engine(&mut chip8);
+        assert!(chip8.display.mega_mode().is_none());
+        assert_eq!(chip8.display.to_ascii().lines().count(), 32);
+    }
+
+    #[test]
+    fn sprites_use_the_palette_and_show_after_00e0() {
+        let mut chip8 = MachineBuilder::default()
+            .with_platform(Platform::MegaChip)
+            .load(&sprite_rom());
+        assert_eq!(chip8.ram.len(), 0x100C);
+        for _ in 0..9 {
+            step_on_every_engine(&mut chip8);
+        }
+        assert_eq!(chip8.idx_reg, 0x1008);
+        assert_eq!(chip8.var_reg[0xF], 0);
+        // nothing is shown until the frame is presented
+        assert_eq!(pixel(&chip8, 5, 10), [0, 0, 0, 0xFF]);
+
+        step_on_every_engine(&mut chip8);
+        assert_eq!(pixel(&chip8, 5, 10), RED);
+        assert_eq!(pixel(&chip8, 5, 11), [0, 0, 0, 0xFF]);
+        assert_eq!(pixel(&chip8, 6, 10), BLUE);
+        assert_eq!(pixel(&chip8, 6, 11), RED);
+
+        // 00E0 started a blank frame, so only the second draw collides
+        chip8.pc = 0x214;
+        step_on_every_engine(&mut chip8);
+        assert_eq!(chip8.var_reg[0xF], 0);
+        chip8.pc = 0x214;
+        step_on_every_engine(&mut chip8);
+        assert_eq!(chip8.var_reg[0xF], 1);
+    }
+
+    #[test]
+    fn blend_modes_and_alpha_mix_colours() {
+        let screen = [0x80, 0x40, 0x00, 0xFF];
+        let sprite = [0x80, 0x80, 0x80, 0xFF];
+        assert_eq!(BlendMode::Normal.blend(sprite, screen), sprite);
+        assert_eq!(
+            BlendMode::Alpha50.blend(sprite, screen),
+            [0x80, 0x60, 0x40, 0xFF]
+        );
+        assert_eq!(
+            BlendMode::Add.blend(sprite, screen),
+            [0xFF, 0xC0, 0x80, 0xFF]
+        );
+        assert_eq!(
+            BlendMode::Multiply.blend(sprite, screen),
+            [0x40, 0x20, 0x00, 0xFF]
+        );
+        assert_eq!(BlendMode::from_nibble(5), Some(BlendMode::Multiply));
+        assert_eq!(BlendMode::from_nibble(6), None);
+
+        let mut framebuffer = Framebuffer::default();
+        framebuffer.palette[1] = [0xFF, 0xFF, 0xFF, 0xFF];
+        framebuffer.set_sprite_width(1);
+        framebuffer.set_sprite_height(1);
+        framebuffer.draw_sprite(0, 0, &[1]);
+        framebuffer.present();
+        framebuffer.alpha = 0x80;
+        assert_eq!(framebuffer.to_rgba()[..4], [0x80, 0x80, 0x80, 0xFF]);
+    }
+
+    #[test]
+    fn digitised_sound_starts_and_stops() {
+        let mut chip8 = MachineBuilder::default()
+            .with_platform(Platform::MegaChip)
+            .load(&[
+                0xA2, 0x0A, // 0x200: I = the header
+                0x06, 0x01, // 0x202: play once
+                0x07, 0x00, // 0x204: stop
+                0x06, 0x00, // 0x206: play looping
+                0x00, 0x00, // 0x208: padding
+                0x1F, 0x40, 0x00, 0x00, 0x03, 0x00, // 0x20A: 8000Hz, 3 samples
+                0x80, 0xFF, 0x00, // 0x210: samples
+            ]);
+        step_on_every_engine(&mut chip8);
+        step_on_every_engine(&mut chip8);
+        let sound = chip8.digital_sound.unwrap();
+        assert_eq!(
+            sound,
+            DigitalSound {
+                addr: 0x20A,
+                sample_rate: 8000,
+                len: 3,
+                looping: false,
+            }
+        );
+        assert_eq!(sound.samples(&chip8.ram), [0x80, 0xFF, 0x00]);
+        let wav = sound.to_wav(&chip8.ram);
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(wav.len(), 44 + 3);
+        assert_eq!(wav[24..28], 8000u32.to_le_bytes());
+
+        step_on_every_engine(&mut chip8);
+        assert_eq!(chip8.digital_sound, None);
+        step_on_every_engine(&mut chip8);
+        assert!(chip8.digital_sound.unwrap().looping);
+    }
+
+    #[test]
+    fn engines_and_snapshots_keep_megachip_state() {
+        let expected = {
+            let mut chip8 = MachineBuilder::default()
+                .with_platform(Platform::MegaChip)
+                .load(&sprite_rom());
+            chip8.run_frame(10);
+            chip8
+        };
+        for mut engine in engines() {
+            let mut chip8 = MachineBuilder::default()
+                .with_platform(Platform::MegaChip)
+                .load(&sprite_rom());
+            engine.run_frame(&mut chip8, 10);
+            assert_eq!(chip8.snapshot(), expected.snapshot());
+        }
+        let mut checked = MachineBuilder::default()
+            .with_platform(Platform::MegaChip)
+            .load(&sprite_rom());
+        checked.try_run_frame(10).unwrap();
+        assert_eq!(checked.snapshot(), expected.snapshot());
+
+        let snapshot = Snapshot::from_bytes(&expected.snapshot().to_bytes()).unwrap();
+        assert_eq!(snapshot, expected.snapshot());
+        let mut restored = Chip8::with_seed(0);
+        restored.restore(&snapshot);
+        assert_eq!(restored.ram.len(), 0x100C);
+        assert_eq!(restored.idx_reg, 0x1008);
+        assert_eq!(pixel(&restored, 6, 10), BLUE);
+
+        // palettes past the end of memory are reported rather than read
+        let mut chip8 = MachineBuilder::default()
+            .with_platform(Platform::MegaChip)
+            .load(&[0x01, 0x00, 0x10, 0x00, 0x02, 0x01]);
+        step_on_every_engine(&mut chip8);
+        assert_eq!(
+            chip8.try_cycle(),
+            Err(Chip8Error::MemoryOutOfBounds(0x1000))
+        );
+    }
+}
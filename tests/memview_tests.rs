@@ -0,0 +1,81 @@
+mod tests {
+    use chip8::Chip8;
+    use chip8::memview::{EditTarget, Highlight, MemoryView, Register};
+
+    #[test]
+    fn highlights_pc_then_i_then_regions() {
+        let mut chip8 = Chip8::with_seed(0);
+        chip8.idx_reg = 0x50;
+        let view = MemoryView::new(0x10, 16);
+
+        assert_eq!(view.highlight(&chip8, 0x200), Highlight::Pc);
+        assert_eq!(view.highlight(&chip8, 0x201), Highlight::Pc);
+        assert_eq!(view.highlight(&chip8, 0x202), Highlight::Rom);
+        assert_eq!(view.highlight(&chip8, 0x210), Highlight::None);
+        assert_eq!(view.highlight(&chip8, 0x50), Highlight::Index);
+        assert_eq!(view.highlight(&chip8, 0x9F), Highlight::Font);
+        assert_eq!(view.highlight(&chip8, 0xA0), Highlight::None);
+    }
+
+    #[test]
+    fn typing_edits_bytes_and_moves_on() {
+        let mut chip8 = Chip8::with_seed(0);
+        let mut view = MemoryView::new(0, 16);
+        view.select(Some(EditTarget::Byte(0x300)));
+        for digit in [0xA, 0xB, 0xC] {
+            view.type_digit(&mut chip8, digit);
+        }
+
+        assert_eq!(chip8.ram[0x300], 0xAB);
+        assert_eq!(chip8.ram[0x301], 0x0C);
+        assert_eq!(view.editing(), Some(EditTarget::Byte(0x301)));
+    }
+
+    #[test]
+    fn typing_edits_registers_to_their_width() {
+        let mut chip8 = Chip8::with_seed(0);
+        let mut view = MemoryView::new(0, 16);
+        view.select(Some(EditTarget::Register(Register::I)));
+        for digit in [0x1, 0x2, 0x3] {
+            view.type_digit(&mut chip8, digit);
+        }
+        view.type_digit(&mut chip8, 0x4);
+
+        assert_eq!(chip8.idx_reg, 0x123);
+        assert_eq!(chip8.pc, 0x004);
+        assert_eq!(view.editing(), Some(EditTarget::Register(Register::Pc)));
+
+        view.select(Some(EditTarget::Register(Register::V(0xF))));
+        view.move_selection(-1);
+        view.type_digit(&mut chip8, 0x7);
+        assert_eq!(chip8.var_reg[0xE], 0x07);
+    }
+
+    #[test]
+    fn follows_i_until_scrolled() {
+        let mut chip8 = Chip8::with_seed(0);
+        let mut view = MemoryView::new(0, 16);
+        view.follow_index = true;
+        chip8.idx_reg = 0xE00;
+        view.update(&chip8);
+        assert!(view.rows().any(|addr| addr == 0xE00));
+
+        view.scroll(-4);
+        assert!(!view.follow_index);
+        view.scroll(1000);
+        assert_eq!(view.rows().last(), Some(0xFF0));
+    }
+
+    #[test]
+    fn sprite_preview_reads_at_i_and_wraps() {
+        let mut chip8 = Chip8::with_seed(0);
+        let mut view = MemoryView::new(0, 16);
+        chip8.idx_reg = 0x50;
+        assert_eq!(view.sprite(&chip8), [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+
+        chip8.idx_reg = 0xFFF;
+        chip8.ram[0xFFF] = 0x81;
+        view.sprite_height = 2;
+        assert_eq!(view.sprite(&chip8), [0x81, 0x00]);
+    }
+}
@@ -0,0 +1,137 @@
+mod tests {
+    use chip8::FONT_START;
+    use chip8::font::{Font, FontSet};
+    use chip8::movie::{Movie, MovieHeader, MovieMode, key_mask, mask_key};
+    use chip8::platform::Platform;
+    use chip8::quirks::Quirks;
+    use chip8::rng::Generator;
+    use chip8::rom::ROM;
+    use chip8::timing::Timing;
+
+    /** Waits for a key with FX0A, rolls V1 with CXNN and draws a glyph at that height, forever */
+    const KEY_ROLL: [u8; 12] = [
+        0xF0, 0x0A, 0xC1, 0xFF, 0xA0, 0x50, 0xD2, 0x15, 0x72, 0x05, 0x12, 0x00,
+    ];
+
+    fn header() -> MovieHeader {
+        MovieHeader {
+            rom_sha1: "da39a3ee5e6b4b0d3255bfef95601890afd80709".to_owned(),
+            quirks: Quirks::vip(),
+            seed: 99,
+            rng: Generator::Xorshift,
+            cycles_per_frame: 4,
+            timing: Timing::Fast,
+            platform: Platform::Chip8,
+            font: Font::default(),
+            font_addr: FONT_START as u16,
+        }
+    }
+
+    #[test]
+    fn key_masks() {
+        assert_eq!(key_mask(None), 0);
+        assert_eq!(key_mask(Some(0xA)), 0x0400);
+        assert_eq!(mask_key(0x0400), Some(0xA));
+        assert_eq!(mask_key(0), None);
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let mut movie = Movie::new(header());
+        movie.next_input(Some(0x3));
+        movie.next_input(None);
+        let loaded = Movie::from_text(&movie.to_text()).unwrap();
+        assert_eq!(loaded.header, movie.header);
+        assert_eq!(loaded.inputs(), &[0x0008, 0x0000]);
+        assert_eq!(loaded.mode, MovieMode::ReadOnly);
+    }
+
+    #[test]
+    fn headers_carry_the_machine_configuration() {
+        let header = MovieHeader {
+            rng: Generator::Vip,
+            timing: Timing::Vip,
+            platform: Platform::HiRes,
+            font: FontSet::Octo.font(),
+            font_addr: 0x100,
+            ..header()
+        };
+        let movie = Movie::from_text(&Movie::new(header.clone()).to_text()).unwrap();
+        assert_eq!(movie.header, header);
+
+        let chip8 = movie.create_machine().unwrap();
+        assert_eq!(chip8.rng.generator(), Some(Generator::Vip));
+        assert_eq!(chip8.timing, Timing::Vip);
+        assert_eq!(chip8.platform, Platform::HiRes);
+        assert_eq!(chip8.font_region(), 0x100..0x100 + 240);
+        assert_eq!(chip8.ram[0x150..0x1F0], FontSet::Octo.font().big[..]);
+
+        let too_high = MovieHeader {
+            font_addr: 0xFF0,
+            ..header
+        };
+        assert!(Movie::new(too_high).create_machine().is_err());
+    }
+
+    #[test]
+    fn from_text_rejects_bad_movies() {
+        assert!(Movie::from_text("NOTAMOVIE 1\n").is_err());
+        let text = Movie::new(header()).to_text() + "zzzz\n";
+        assert!(Movie::from_text(&text).is_err());
+    }
+
+    #[test]
+    fn playback_reproduces_recorded_run() {
+        let live_inputs = [
+            None,
+            Some(0x5),
+            Some(0x5),
+            None,
+            Some(0xC),
+            None,
+            None,
+            Some(0x1),
+        ];
+        let mut movie = Movie::new(header());
+        let mut recorded = movie.create_machine().unwrap();
+        recorded.load_memory(0x200, &KEY_ROLL);
+        for input in live_inputs {
+            movie.run_frame(&mut recorded, input);
+        }
+
+        let mut playback = Movie::from_text(&movie.to_text()).unwrap();
+        let mut replayed = playback.create_machine().unwrap();
+        replayed.load_memory(0x200, &KEY_ROLL);
+        while !playback.is_finished() {
+            playback.run_frame(&mut replayed, Some(0xF));
+        }
+        assert_eq!(replayed.snapshot(), recorded.snapshot());
+    }
+
+    #[test]
+    fn seek_branches_read_write_movies() {
+        let mut movie = Movie::new(header());
+        for key in 0..5 {
+            movie.next_input(Some(key));
+        }
+
+        movie.mode = MovieMode::ReadOnly;
+        movie.seek(2);
+        assert_eq!(movie.len(), 5);
+        assert_eq!(movie.next_input(None), Some(2));
+
+        movie.mode = MovieMode::ReadWrite;
+        movie.seek(2);
+        movie.next_input(Some(0xE));
+        assert_eq!(movie.inputs(), &[0x0001, 0x0002, 0x4000]);
+    }
+
+    #[test]
+    fn rom_hash_ignores_padding() {
+        let path = std::env::temp_dir().join(format!("chip8-{}-empty.ch8", std::process::id()));
+        std::fs::write(&path, []).unwrap();
+        let rom = ROM::new(path.to_str().unwrap());
+        assert_eq!(rom.size, 0);
+        assert_eq!(rom.sha1(), header().rom_sha1);
+    }
+}
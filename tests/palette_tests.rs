@@ -0,0 +1,56 @@
+mod tests {
+    use chip8::palette::{Palette, PaletteDatabase, Theme};
+
+    #[test]
+    fn theme_lookup_by_name() {
+        for theme in Theme::ALL {
+            assert_eq!(Theme::from_name(theme.name()), Some(theme));
+        }
+        assert_eq!(Theme::from_name("AMBER"), Some(Theme::Amber));
+        assert_eq!(Theme::from_name("sepia"), None);
+    }
+
+    #[test]
+    fn from_hex_reads_background_and_foreground() {
+        let palette = Palette::from_hex("#000000, 33ff33").unwrap();
+        assert_eq!(palette.background(), [0x00, 0x00, 0x00]);
+        assert_eq!(palette.foreground(), [0x33, 0xFF, 0x33]);
+    }
+
+    #[test]
+    fn from_hex_rejects_bad_input() {
+        assert!(Palette::from_hex("#000000").is_err());
+        assert!(Palette::from_hex("#000000,#GGGGGG").is_err());
+        assert!(Palette::from_hex("#000,#FFF").is_err());
+        assert!(Palette::from_hex("#000000,#FFFFFF,#AAAAAA").is_err());
+    }
+
+    #[test]
+    fn database_picks_palettes_by_rom_hash() {
+        let db = PaletteDatabase::from_text(
+            "# comment\n\n1BA58656810B67FD131EB9AF3E3987863BF26C90 amber\nabc #102030,#405060\n",
+        )
+        .unwrap();
+        assert_eq!(
+            db.lookup("1ba58656810b67fd131eb9af3e3987863bf26c90"),
+            Some(Theme::Amber.palette())
+        );
+        assert_eq!(db.lookup("abc"), Palette::from_hex("#102030,#405060").ok());
+        assert_eq!(db.lookup("def"), None);
+        assert!(PaletteDatabase::from_text("abc").is_err());
+        assert!(PaletteDatabase::from_text("abc sepia").is_err());
+
+        // the shipped database covers the bundled IBM logo
+        let db = PaletteDatabase::load(std::path::Path::new("resources/palettes.txt")).unwrap();
+        let rom = chip8::rom::ROM::new("ROMs/IBM Logo.ch8");
+        assert!(db.lookup(&rom.sha1()).is_some());
+    }
+
+    #[test]
+    fn shade_mixes_background_and_foreground() {
+        let palette = Palette::default();
+        assert_eq!(palette.shade(0.0), palette.background());
+        assert_eq!(palette.shade(1.0), palette.foreground());
+        assert_eq!(palette.shade(0.5), [0x80, 0x80, 0x80]);
+    }
+}
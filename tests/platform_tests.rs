@@ -0,0 +1,194 @@
+mod common;
+
+mod tests {
+    use crate::common::{MachineBuilder, engines, frame_on_every_engine, step_on_every_engine};
+    use chip8::Chip8;
+    use chip8::display::VP590_COLORS;
+    use chip8::engine::Engine;
+    use chip8::platform::{HIRES_START, Platform};
+    use chip8::state::Snapshot;
+
+    #[test]
+    fn chip8x_loads_at_0x300_and_colours_the_screen() {
+        let mut chip8 = MachineBuilder::default()
+            .with_platform(Platform::Chip8X)
+            .load(&[
+                0x02, 0xA0, // 0x300: background goes from blue to black
+                0x60, 0x11, // 0x302: V0 = 0x11, zones 1 and 2 across
+                0x61, 0x00, // 0x304: V1 = 0, just the top zone row
+                0x62, 0x04, // 0x306: V2 = green
+                0xB0, 0x20, // 0x308: colour the zones
+                0x63, 0x75, // 0x30A: V3 = 0x75
+                0x64, 0x13, // 0x30C: V4 = 0x13
+                0x53, 0x41, // 0x30E: V3 = 0x00, each nibble wrapping at 8
+                0xE0, 0xF5, // 0x310: the second keypad is never pressed, so this skips
+                0x00, 0x00, // 0x312: skipped
+            ]);
+        assert_eq!(chip8.pc, 0x300);
+        assert_eq!(chip8.display.height(), 32);
+        for _ in 0..9 {
+            step_on_every_engine(&mut chip8);
+        }
+        let colors = chip8.display.colors.unwrap();
+        assert_eq!(colors.background_rgb(), VP590_COLORS[0]);
+        assert_eq!(colors.foreground_rgb(0, 8), VP590_COLORS[4]);
+        assert_eq!(colors.foreground_rgb(3, 23), VP590_COLORS[4]);
+        assert_eq!(colors.foreground_rgb(4, 8), VP590_COLORS[1]);
+        assert_eq!(colors.foreground_rgb(0, 7), VP590_COLORS[1]);
+        assert_eq!(colors.foreground_rgb(0, 24), VP590_COLORS[1]);
+        assert_eq!(chip8.var_reg[3], 0x00);
+        assert_eq!(chip8.pc, 0x314);
+
+        // BXYN colours N rows of one column
+        chip8.var_reg[0] = 40;
+        chip8.var_reg[1] = 10;
+        chip8.load_memory(0x314, &[0xB0, 0x23]);
+        step_on_every_engine(&mut chip8);
+        let colors = chip8.display.colors.unwrap();
+        assert!((10..13).all(|y| colors.foreground_rgb(y, 40) == VP590_COLORS[4]));
+        assert_eq!(colors.foreground_rgb(13, 40), VP590_COLORS[1]);
+    }
+
+    #[test]
+    fn hires_programs_jump_to_0x2c0_and_draw_64_rows() {
+        let mut program = vec![0x12, 0x60];
+        program.resize((HIRES_START - 0x200) as usize, 0);
+        program.extend([
+            0x60, 0x3E, // 0x2C0: V0 = 62
+            0xA0, 0x50, // 0x2C2: I = the "0" glyph
+            0xD0, 0x05, // 0x2C4: drawn on rows 62 to 66, clipped at 64
+            0x02, 0x30, // 0x2C6: clear both pages
+        ]);
+        let mut chip8 = MachineBuilder::default()
+            .with_platform(Platform::HiRes)
+            .load(&program);
+        step_on_every_engine(&mut chip8);
+        assert_eq!(chip8.pc, HIRES_START);
+
+        for _ in 0..3 {
+            step_on_every_engine(&mut chip8);
+        }
+        assert_eq!(chip8.display.height(), 64);
+        assert_eq!(chip8.display.rows().len(), 64);
+        assert!(chip8.display.get_pixel(62, 62) && chip8.display.get_pixel(63, 62));
+        assert_eq!(chip8.display.rows()[..62], [0; 62]);
+        assert_eq!(chip8.display.to_ascii().lines().count(), 64);
+
+        step_on_every_engine(&mut chip8);
+        assert!(chip8.display.rows().iter().all(|row| *row == 0));
+
+        // the same jump elsewhere, or on another platform, goes where it says
+        let mut chip8 = MachineBuilder::default()
+            .with_platform(Platform::Chip8)
+            .load(&[0x12, 0x60]);
+        step_on_every_engine(&mut chip8);
+        assert_eq!(chip8.pc, 0x260);
+    }
+
+    #[test]
+    fn chip8e_adds_ranges_branches_and_waits() {
+        let mut chip8 = MachineBuilder::default()
+            .with_platform(Platform::Chip8E)
+            .load(&[
+                0x61, 0x0A, // 0x200: V1 = 10
+                0x62, 0x0B, // 0x202: V2 = 11
+                0xA3, 0x00, // 0x204: I = 0x300
+                0x51, 0x22, // 0x206: store V1..V2
+                0x53, 0x43, // 0x208: load V3..V4
+                0x52, 0x11, // 0x20A: V2 > V1, so skip
+                0x00, 0x00, // 0x20C: skipped
+                0xBF, 0x06, // 0x20E: forward to 0x214
+                0x00, 0x00, // 0x210: skipped
+                0x00, 0x00, // 0x212: skipped
+                0x01, 0x88, // 0x214: skip
+                0x00, 0x00, // 0x216: skipped
+                0x65, 0x02, // 0x218: V5 = 2
+                0xF5, 0x1B, // 0x21A: skip 2 bytes
+                0x00, 0x00, // 0x21C: skipped
+                0xF5, 0x4F, // 0x21E: delay = 2, then wait for it
+                0x00, 0xED, // 0x220: stop
+            ]);
+        for _ in 0..10 {
+            step_on_every_engine(&mut chip8);
+        }
+        assert_eq!(chip8.ram[0x300..0x302], [10, 11]);
+        assert_eq!(chip8.var_reg[3..5], [10, 11]);
+        assert_eq!(chip8.pc, 0x21E);
+
+        for _ in 0..5 {
+            frame_on_every_engine(&mut chip8, 1);
+        }
+        assert_eq!(chip8.pc, 0x220);
+        frame_on_every_engine(&mut chip8, 10);
+        assert_eq!(chip8.pc, 0x220);
+
+        // branches back are relative to the branch itself
+        let mut chip8 = MachineBuilder::default()
+            .with_platform(Platform::Chip8E)
+            .load(&[0x00, 0xF2, 0xBB, 0x02]);
+        step_on_every_engine(&mut chip8);
+        step_on_every_engine(&mut chip8);
+        assert_eq!(chip8.pc, 0x200);
+    }
+
+    #[test]
+    fn every_engine_runs_platform_opcodes() {
+        let program = [
+            0x60, 0x05, // 0x300: V0 = 5
+            0x61, 0x13, // 0x302: V1 = 0x13
+            0x50, 0x11, // 0x304: add nibbles
+            0x62, 0x06, // 0x306: V2 = cyan
+            0xB0, 0x22, // 0x308: colour two rows
+            0x02, 0xA0, // 0x30A: next background
+            0xD0, 0x15, // 0x30C: draw
+            0x13, 0x00, // 0x30E: loop
+        ];
+        let mut expected = MachineBuilder::default()
+            .with_platform(Platform::Chip8X)
+            .load(&program);
+        let mut checked = expected.clone();
+        let mut engines: Vec<(Box<dyn Engine>, Chip8)> = engines()
+            .into_iter()
+            .map(|engine| (engine, expected.clone()))
+            .collect();
+        for _ in 0..5 {
+            expected.run_frame(7);
+            checked.try_run_frame(7).unwrap();
+            for (engine, chip8) in engines.iter_mut() {
+                engine.run_frame(chip8, 7);
+            }
+        }
+        assert_eq!(expected.var_reg[0], 0x10);
+        assert_eq!(checked.snapshot(), expected.snapshot());
+        for (_, chip8) in &engines {
+            assert_eq!(chip8.snapshot(), expected.snapshot());
+        }
+        assert_eq!(Platform::from_name("CHIP8X"), Some(Platform::Chip8X));
+    }
+
+    #[test]
+    fn snapshots_keep_both_pages_and_the_colours() {
+        let mut hires = MachineBuilder::default()
+            .with_platform(Platform::HiRes)
+            .load(&[]);
+        hires.display.flip_pixel(63, 0);
+        let snapshot = Snapshot::from_bytes(&hires.snapshot().to_bytes()).unwrap();
+        assert_eq!(snapshot.display.len(), 64);
+        let mut restored = Chip8::with_seed(0);
+        restored.restore(&snapshot);
+        assert_eq!(restored.display.height(), 64);
+        assert!(restored.display.get_pixel(63, 0));
+        // the platform comes back too, so HiRes opcodes keep working
+        assert_eq!(restored.platform, Platform::HiRes);
+        restored.load_memory(restored.pc, &[0x02, 0x30]);
+        step_on_every_engine(&mut restored);
+        assert!(!restored.display.get_pixel(63, 0));
+
+        let mut chip8x = MachineBuilder::default()
+            .with_platform(Platform::Chip8X)
+            .load(&[]);
+        chip8x.display.colors.as_mut().unwrap().fill(0, 0, 0, 0, 7);
+        let snapshot = Snapshot::from_bytes(&chip8x.snapshot().to_bytes()).unwrap();
+        assert_eq!(snapshot.colors, chip8x.display.colors);
+    }
+}
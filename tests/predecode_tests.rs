@@ -0,0 +1,19 @@
+mod tests {
+    use chip8::Chip8;
+    use chip8::engine::Engine;
+    use chip8::predecode::PredecodedEngine;
+
+    #[test]
+    fn invalidate_all_picks_up_external_writes() {
+        let mut chip8 = Chip8::with_seed(7);
+        chip8.load_memory(0x200, &[0x60, 0x01, 0x12, 0x00]);
+        let mut engine = PredecodedEngine::new();
+        engine.step(&mut chip8);
+        engine.step(&mut chip8);
+
+        chip8.load_memory(0x200, &[0x60, 0x02]);
+        engine.invalidate_all();
+        engine.step(&mut chip8);
+        assert_eq!(chip8.snapshot().var_reg[0], 0x02);
+    }
+}
@@ -0,0 +1,112 @@
+mod tests {
+    use chip8::Chip8;
+    use chip8::blocks::BlockEngine;
+    use chip8::engine::Engine;
+    use chip8::quirks::Quirks;
+
+    #[test]
+    fn flags_round_trip() {
+        for quirks in [
+            Quirks::default(),
+            Quirks::vip(),
+            Quirks::schip(),
+            Quirks::xochip(),
+        ] {
+            assert_eq!(Quirks::from_flags(&quirks.to_flags()), Ok(quirks));
+        }
+        assert_eq!(Quirks::default().to_flags(), "");
+        assert_eq!(
+            Quirks::vip().to_flags(),
+            "memory_increments_i,logic_resets_vf,stack_in_ram,machine_code,stack_depth=12"
+        );
+        assert!(Quirks::from_flags("stack_depth=0").is_err());
+        assert!(Quirks::from_flags("turbo").is_err());
+    }
+
+    #[test]
+    fn preset_lookup() {
+        assert_eq!(Quirks::preset("VIP"), Some(Quirks::vip()));
+        assert_eq!(Quirks::preset("schip"), Some(Quirks::schip()));
+        assert_eq!(Quirks::preset("octo"), None);
+    }
+
+    #[test]
+    fn shift_in_place_ignores_vy() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks.shift_in_place = true;
+        chip8.var_reg[0x3] = 0x81;
+        chip8.var_reg[0x4] = 0x02;
+        load_run_instruction(&mut chip8, &[0x83, 0x4E]);
+        assert_eq!(chip8.var_reg[0x3], 0x02);
+        assert_eq!(chip8.var_reg[0xF], 1);
+    }
+
+    #[test]
+    fn shifts_without_the_quirk_keep_the_original_behaviour() {
+        let mut chip8 = Chip8::new();
+        chip8.var_reg[0x3] = 0x81;
+        chip8.var_reg[0x4] = 0x83;
+        load_run_instruction(&mut chip8, &[0x83, 0x46]);
+        assert_eq!(chip8.var_reg[0x3], 0x41);
+        assert_eq!(chip8.var_reg[0xF], 1);
+
+        // 8XYE copies VY without shifting it, as it always has
+        chip8.var_reg[0x4] = 0x81;
+        load_run_instruction(&mut chip8, &[0x83, 0x4E]);
+        assert_eq!(chip8.var_reg[0x3], 0x81);
+        assert_eq!(chip8.var_reg[0xF], 1);
+    }
+
+    #[test]
+    fn memory_increments_i() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks = Quirks::vip();
+        chip8.idx_reg = 0x300;
+        load_run_instruction(&mut chip8, &[0xF2, 0x55]);
+        assert_eq!(chip8.idx_reg, 0x303);
+    }
+
+    #[test]
+    fn logic_resets_vf() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks = Quirks::vip();
+        chip8.var_reg[0xF] = 5;
+        load_run_instruction(&mut chip8, &[0x81, 0x21]);
+        assert_eq!(chip8.var_reg[0xF], 0);
+    }
+
+    #[test]
+    fn jump_uses_vx() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks = Quirks::schip();
+        chip8.var_reg[0x0] = 0x10;
+        chip8.var_reg[0x3] = 0x02;
+        load_run_instruction(&mut chip8, &[0xB3, 0x00]);
+        assert_eq!(chip8.pc, 0x302);
+    }
+
+    #[test]
+    fn sprites_clip_or_wrap_at_edges() {
+        for (wrap, expected) in [(false, false), (true, true)] {
+            let mut chip8 = Chip8::new();
+            chip8.quirks.wrap_sprites = wrap;
+            // the "0" glyph drawn 2 pixels from the bottom right corner
+            chip8.var_reg[0x0] = 62;
+            chip8.var_reg[0x1] = 30;
+            chip8.idx_reg = 0x50;
+            load_run_instruction(&mut chip8, &[0xD0, 0x15]);
+            assert!(chip8.display.get_pixel(30, 63));
+            assert_eq!(chip8.display.get_pixel(30, 0), expected);
+            assert_eq!(chip8.display.get_pixel(0, 62), expected);
+        }
+    }
+
+    /** Loads an instruction and runs a single cycle, checking the block engine agrees */
+    fn load_run_instruction(chip8: &mut Chip8, instruction: &[u8]) {
+        chip8.load_memory(chip8.pc, instruction);
+        let mut compiled = chip8.clone();
+        BlockEngine::new().step(&mut compiled);
+        chip8.cycle();
+        assert_eq!(chip8.snapshot(), compiled.snapshot());
+    }
+}
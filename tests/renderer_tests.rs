@@ -0,0 +1,59 @@
+mod tests {
+    use chip8::display::Display;
+    use chip8::renderer::{PresentMode, Renderer, RendererConfig};
+
+    #[test]
+    fn default_config_matches_display() {
+        let mut display = Display::new();
+        let mut renderer = Renderer::default();
+        display.flip_pixel(3, 4);
+        renderer.update(&display, false);
+        assert_eq!(renderer.intensity(3, 4), 1.0);
+
+        display.flip_pixel(3, 4);
+        renderer.update(&display, false);
+        assert_eq!(renderer.intensity(3, 4), 0.0);
+    }
+
+    #[test]
+    fn fade_leaves_ghost_of_turned_off_pixel() {
+        let mut display = Display::new();
+        let mut renderer = Renderer::new(RendererConfig {
+            fade: 0.5,
+            blend: 0.0,
+            present_mode: PresentMode::Always,
+        });
+        display.flip_pixel(0, 0);
+        renderer.update(&display, true);
+        display.flip_pixel(0, 0);
+
+        renderer.update(&display, true);
+        assert_eq!(renderer.intensity(0, 0), 0.5);
+        renderer.update(&display, true);
+        assert_eq!(renderer.intensity(0, 0), 0.0);
+    }
+
+    #[test]
+    fn on_draw_mode_ignores_undrawn_frames() {
+        let mut display = Display::new();
+        let mut renderer = Renderer::new(RendererConfig {
+            present_mode: PresentMode::OnDraw,
+            ..RendererConfig::default()
+        });
+        display.flip_pixel(1, 1);
+        renderer.update(&display, false);
+        assert_eq!(renderer.intensity(1, 1), 0.0);
+
+        renderer.update(&display, true);
+        assert_eq!(renderer.intensity(1, 1), 1.0);
+    }
+
+    #[test]
+    fn draw_instructions_set_draw_flag() {
+        let mut chip8 = chip8::Chip8::new();
+        assert!(!chip8.draw_flag);
+        chip8.load_memory(chip8.pc, &[0xD0, 0x05]);
+        chip8.cycle();
+        assert!(chip8.draw_flag);
+    }
+}
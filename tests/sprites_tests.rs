@@ -0,0 +1,98 @@
+mod tests {
+    use chip8::Chip8;
+    use chip8::engine::Engine;
+    use chip8::rom::ROM;
+    use chip8::sprites::{self, CELL_HEIGHT, CELL_WIDTH, Sprite, SpriteSheet, SpriteTracker};
+
+    const IBM_LOGO: &[u8] = include_bytes!("../ROMs/IBM Logo.ch8");
+
+    #[test]
+    fn scan_pairs_draws_with_the_last_annn() {
+        let rom = [
+            0xA2, 0x10, // I = 0x210
+            0xD0, 0x15, // draw 5 rows
+            0xD0, 0x15, // the same sprite again
+            0xF0, 0x1E, // I += V0, unknown statically
+            0xD0, 0x13, // ignored
+            0xA3, 0x00, // I = 0x300
+            0xD0, 0x18, // draw 8 rows
+            0xD0, 0x10, // 16x16 sprites are ignored
+        ];
+        assert_eq!(
+            sprites::scan(&rom),
+            [
+                Sprite {
+                    addr: 0x210,
+                    height: 5
+                },
+                Sprite {
+                    addr: 0x300,
+                    height: 8
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn tracker_records_the_same_sprites_as_the_scan_for_the_ibm_logo() {
+        let mut chip8 = Chip8::with_seed(0);
+        chip8.load_rom(ROM::from_bytes(IBM_LOGO));
+        let mut tracker = SpriteTracker::new();
+        for _ in 0..20 {
+            tracker.run_frame(&mut chip8, 10);
+        }
+
+        let drawn = tracker.sprites();
+        assert_eq!(drawn.len(), 6);
+        assert_eq!(drawn, sprites::scan(IBM_LOGO));
+        // the tracker still runs the machine like the interpreter
+        assert!(chip8.display.as_bitmask_rows().iter().any(|row| *row != 0));
+    }
+
+    #[test]
+    fn font_sheet_shows_glyphs_under_their_labels() {
+        let chip8 = Chip8::with_seed(0);
+        let sheet = SpriteSheet::loaded_font(&chip8);
+        assert_eq!(sheet.entries.len(), 16);
+        assert_eq!(sheet.entries[0xA].label, "A");
+        assert_eq!(sheet.entries[0].rows, [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+        assert_eq!(
+            (sheet.width(), sheet.height()),
+            (8 * CELL_WIDTH, 2 * CELL_HEIGHT)
+        );
+
+        // the label "0" and the glyph "0" below it are both drawn with the built-in font
+        let pixels = sheet.pixels();
+        let row = |y: usize| -> String {
+            (0..8)
+                .map(|x| {
+                    if pixels[y * sheet.width() + x] {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect()
+        };
+        assert_eq!(row(0), "####....");
+        assert_eq!(row(1), "#..#....");
+        assert_eq!(row(7), "####....");
+        assert_eq!(row(8), "#..#....");
+    }
+
+    #[test]
+    fn custom_fonts_in_memory_are_previewed() {
+        let mut chip8 = Chip8::with_seed(0);
+        chip8.ram[0x300..0x305].copy_from_slice(&[0x60, 0x90, 0x90, 0x90, 0x60]);
+        let sheet = SpriteSheet::font(&chip8.ram, 0x300);
+        assert_eq!(sheet.entries[0].rows, [0x60, 0x90, 0x90, 0x90, 0x60]);
+
+        let sprites = [Sprite {
+            addr: 0x300,
+            height: 2,
+        }];
+        let sheet = SpriteSheet::from_sprites(&chip8.ram, &sprites, 4);
+        assert_eq!(sheet.entries[0].label, "300");
+        assert_eq!(sheet.width(), CELL_WIDTH);
+    }
+}
@@ -0,0 +1,195 @@
+mod tests {
+    use chip8::Chip8;
+    use chip8::font::FontSet;
+    use chip8::platform::Platform;
+    use chip8::rng::{Generator, RandomSource, VipRandom, Xorshift};
+    use chip8::state::Snapshot;
+    use chip8::timing::Timing;
+
+    /** CXNN with NN = 0xFF, repeated so VX is rolled many times */
+    const RANDOM_LOOP: [u8; 4] = [0xC0, 0xFF, 0x12, 0x00];
+
+    #[test]
+    fn same_seed_gives_same_numbers() {
+        let mut first = Chip8::with_seed(42);
+        let mut second = Chip8::with_seed(42);
+        first.load_memory(0x200, &RANDOM_LOOP);
+        second.load_memory(0x200, &RANDOM_LOOP);
+        for _ in 0..20 {
+            first.cycle();
+            second.cycle();
+            assert_eq!(first.var_reg[0x0], second.var_reg[0x0]);
+        }
+    }
+
+    #[test]
+    fn zero_seed_still_generates_numbers() {
+        let mut rng = Xorshift::new(0);
+        let bytes: Vec<u8> = (0..8).map(|_| rng.next_byte()).collect();
+        assert!(bytes.iter().any(|&byte| byte != 0));
+    }
+
+    #[test]
+    fn vip_generator_mixes_r0_into_its_last_result() {
+        let mut rng = VipRandom::new(0);
+        // R.0 steps to 0x2B, 0x56, 0x81; each is added to the last result, then rotated right
+        let bytes: Vec<u8> = (0..3).map(|_| rng.next_byte()).collect();
+        assert_eq!(bytes, [0x95, 0xF5, 0x3B]);
+        assert_eq!(rng.state(), 0x3B81);
+
+        let mut first = Chip8::with_seed(42);
+        first.set_generator(Generator::Vip);
+        let mut second = first.clone();
+        first.load_memory(0x200, &RANDOM_LOOP);
+        second.load_memory(0x200, &RANDOM_LOOP);
+        let mut rolls = Vec::new();
+        for _ in 0..40 {
+            first.cycle();
+            second.cycle();
+            assert_eq!(first.var_reg[0x0], second.var_reg[0x0]);
+            rolls.push(first.var_reg[0x0]);
+        }
+        rolls.sort();
+        rolls.dedup();
+        assert!(rolls.len() > 10);
+    }
+
+    #[test]
+    fn snapshots_bring_back_the_generator() {
+        let mut chip8 = Chip8::with_seed(3);
+        chip8.set_generator(Generator::Vip);
+        chip8.load_memory(0x200, &RANDOM_LOOP);
+        let snapshot = Snapshot::from_bytes(&chip8.snapshot().to_bytes()).unwrap();
+        assert_eq!(snapshot.rng_generator, Some(Generator::Vip));
+        chip8.cycle();
+        let expected = chip8.var_reg[0x0];
+
+        let mut restored = Chip8::with_seed(99);
+        restored.restore(&snapshot);
+        assert_eq!(restored.rng.generator(), Some(Generator::Vip));
+        restored.cycle();
+        assert_eq!(restored.var_reg[0x0], expected);
+    }
+
+    /** Counts up from its state, so every number it gives is known */
+    #[derive(Clone)]
+    struct Scripted(u64);
+    impl RandomSource for Scripted {
+        fn next_byte(&mut self) -> u8 {
+            self.0 += 1;
+            self.0 as u8
+        }
+
+        fn state(&self) -> u64 {
+            self.0
+        }
+
+        fn set_state(&mut self, state: u64) {
+            self.0 = state;
+        }
+
+        fn box_clone(&self) -> Box<dyn RandomSource> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn custom_random_sources_feed_cxnn() {
+        let mut chip8 = Chip8::with_seed(0);
+        chip8.set_random_source(Box::new(Scripted(0x0F)));
+        chip8.load_memory(0x200, &RANDOM_LOOP);
+        chip8.cycle();
+        assert_eq!(chip8.var_reg[0x0], 0x10);
+
+        let snapshot = chip8.snapshot();
+        assert_eq!(snapshot.rng_state, 0x10);
+        chip8.cycle();
+        chip8.cycle();
+        chip8.restore(&snapshot);
+        chip8.pc = 0x200;
+        chip8.cycle();
+        assert_eq!(chip8.var_reg[0x0], 0x11);
+    }
+
+    #[test]
+    fn snapshot_round_trips_through_bytes() {
+        let mut chip8 = Chip8::with_seed(7);
+        chip8.load_memory(0x200, &[0xA0, 0x50, 0xD0, 0x05, 0x22, 0x00]);
+        chip8.delay_timer = 30;
+        for _ in 0..3 {
+            chip8.cycle();
+        }
+
+        let snapshot = chip8.snapshot();
+        let restored = Snapshot::from_bytes(&snapshot.to_bytes()).unwrap();
+        assert_eq!(restored, snapshot);
+        assert_eq!(restored.call_stack, vec![0x206]);
+    }
+
+    #[test]
+    fn snapshots_keep_timing_fonts_waits_and_ports() {
+        let mut chip8 = Chip8::with_seed(3);
+        chip8.set_platform(Platform::Chip8E);
+        chip8.timing = Timing::Vip;
+        chip8.load_font(&FontSet::Schip.font(), 0x100).unwrap();
+        chip8.port_in = 0x42;
+        chip8.load_memory(
+            0x200,
+            &[
+                0x60, 0x05, // 0x200: V0 = 5
+                0xF0, 0x4F, // 0x202: wait 5 ticks
+                0xF1, 0xE3, // 0x204: V1 = port
+                0xF1, 0x03, // 0x206: port = V1
+                0x12, 0x08, // 0x208: loop forever
+            ],
+        );
+        chip8.run_frame(0);
+
+        let snapshot = Snapshot::from_bytes(&chip8.snapshot().to_bytes()).unwrap();
+        assert!(snapshot.waiting_for_delay);
+        assert_ne!(snapshot.vip_clock.elapsed, 0);
+        let mut other = Chip8::with_seed(0);
+        other.restore(&snapshot);
+        assert_eq!(other.platform, Platform::Chip8E);
+        assert_eq!(other.timing, Timing::Vip);
+        assert_eq!(other.font_region(), 0x100..0x100 + 180);
+
+        for _ in 0..10 {
+            chip8.run_frame(0);
+            other.run_frame(0);
+        }
+        assert_eq!(other.snapshot(), chip8.snapshot());
+        assert_eq!(other.port_out, 0x42);
+    }
+
+    #[test]
+    fn from_bytes_rejects_bad_data() {
+        let bytes = Chip8::with_seed(1).snapshot().to_bytes();
+        assert!(Snapshot::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Snapshot::from_bytes(b"nope").is_err());
+    }
+
+    #[test]
+    fn restore_replays_random_numbers() {
+        let mut chip8 = Chip8::with_seed(1234);
+        chip8.load_memory(0x200, &RANDOM_LOOP);
+        let snapshot = chip8.snapshot();
+        let rolls: Vec<u8> = (0..10)
+            .map(|_| {
+                chip8.cycle();
+                chip8.var_reg[0x0]
+            })
+            .collect();
+
+        let mut other = Chip8::new();
+        other.restore(&snapshot);
+        for roll in rolls {
+            other.cycle();
+            assert_eq!(other.var_reg[0x0], roll);
+        }
+        assert_eq!(
+            other.display.as_bitmask_rows(),
+            chip8.display.as_bitmask_rows()
+        );
+    }
+}
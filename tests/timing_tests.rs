@@ -0,0 +1,109 @@
+mod common;
+
+mod tests {
+    use crate::common::MachineBuilder;
+    use chip8::Chip8;
+    use chip8::blocks::BlockEngine;
+    use chip8::engine::{Engine, Interpreter};
+    use chip8::predecode::PredecodedEngine;
+    use chip8::rom::ROM;
+    use chip8::timing::{self, INTERPRETER_CYCLES, Timing};
+
+    const IBM_LOGO: &[u8] = include_bytes!("../ROMs/IBM Logo.ch8");
+
+    #[test]
+    fn frames_last_as_many_machine_cycles_as_on_the_vip() {
+        // V0 += 1 then jump back: 50 + 52 cycles per loop
+        let mut chip8 = MachineBuilder::default()
+            .with_timing(Timing::Vip)
+            .load(&[0x70, 0x01, 0x12, 0x00]);
+        chip8.run_frame(1000);
+        // 25 loops take 2550 cycles, then one more add runs past the interrupt
+        assert_eq!(INTERPRETER_CYCLES, 2582);
+        assert_eq!(chip8.var_reg[0], 26);
+        assert_eq!(chip8.vip_clock.overrun, 18);
+        assert_eq!(chip8.vip_clock.elapsed, 2600);
+
+        chip8.run_frame(1000);
+        assert_eq!(chip8.var_reg[0], 51);
+    }
+
+    #[test]
+    fn draws_wait_for_the_display_interrupt() {
+        // draw, count the draw, jump back
+        let mut chip8 = MachineBuilder::default()
+            .with_timing(Timing::Vip)
+            .load(&[0xD0, 0x01, 0x71, 0x01, 0x12, 0x00]);
+        for _ in 0..10 {
+            chip8.run_frame(1000);
+        }
+        // the first frame only waits, then there is one draw per frame
+        assert_eq!(chip8.var_reg[1], 9);
+        assert!(chip8.vip_clock.waiting_for_interrupt);
+
+        chip8.timing = Timing::Fast;
+        chip8.run_frame(30);
+        assert_eq!(chip8.var_reg[1], 19);
+    }
+
+    #[test]
+    fn timers_tick_from_the_interrupt_and_slow_instructions_span_frames() {
+        // delay = 20, then clear the screen forever
+        let mut chip8 = MachineBuilder::default()
+            .with_timing(Timing::Vip)
+            .load(&[0x60, 0x14, 0xF0, 0x15, 0x00, 0xE0, 0x12, 0x04]);
+        assert_eq!(
+            timing::instruction_cycles(&chip8, 0x00E0),
+            3136,
+            "00E0 takes longer than a frame"
+        );
+        for _ in 0..5 {
+            chip8.run_frame(1000);
+        }
+        assert_eq!(chip8.delay_timer, 15);
+
+        // the first clear starts in frame 1 and the jump only runs in frame 2
+        let mut chip8 = MachineBuilder::default()
+            .with_timing(Timing::Vip)
+            .load(&[0x00, 0xE0, 0x12, 0x00]);
+        chip8.run_frame(1000);
+        assert_eq!(chip8.pc, 0x202);
+        assert_eq!(chip8.vip_clock.overrun, 3136 - INTERPRETER_CYCLES);
+    }
+
+    #[test]
+    fn every_engine_keeps_vip_time() {
+        let mut expected = Chip8::with_seed(0);
+        expected.timing = Timing::Vip;
+        expected.load_rom(ROM::from_bytes(IBM_LOGO));
+        let mut checked = expected.clone();
+        let mut engines: Vec<(Box<dyn Engine>, Chip8)> = vec![
+            (Box::new(Interpreter), expected.clone()),
+            (Box::new(PredecodedEngine::new()), expected.clone()),
+            (Box::new(BlockEngine::new()), expected.clone()),
+        ];
+        for _ in 0..30 {
+            expected.run_frame(10);
+            checked.try_run_frame(10).unwrap();
+            for (engine, chip8) in engines.iter_mut() {
+                engine.run_frame(chip8, 10);
+            }
+        }
+
+        assert_eq!(checked.snapshot(), expected.snapshot());
+        assert_eq!(checked.vip_clock, expected.vip_clock);
+        for (_, chip8) in &engines {
+            assert_eq!(chip8.snapshot(), expected.snapshot());
+            assert_eq!(chip8.vip_clock, expected.vip_clock);
+        }
+        // the logo is drawn one sprite per frame, so it is finished well within 30 frames
+        assert_eq!(Timing::from_name("VIP"), Some(Timing::Vip));
+        assert!(
+            expected
+                .display
+                .as_bitmask_rows()
+                .iter()
+                .any(|row| *row != 0)
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use chip8::quirks::Quirks;
+use chip8::rom::ROM;
+use chip8::{Chip8, HEIGHT, WIDTH};
+use wasm_bindgen::prelude::*;
+
+/** A Chip8 for the browser, driven one frame at a time from JavaScript */
+#[wasm_bindgen]
+pub struct Emulator {
+    chip8: Chip8,
+    seed: u64,
+    quirks: Quirks,
+    rom: Vec<u8>,
+    cycles_per_frame: usize,
+}
+
+#[wasm_bindgen]
+impl Emulator {
+    /** Creates an emulator with nothing loaded. JavaScript can pass e.g. `Math.random() * 2 ** 32` as the seed */
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: u32) -> Emulator {
+        Emulator {
+            chip8: Chip8::with_seed(seed as u64),
+            seed: seed as u64,
+            quirks: Quirks::default(),
+            rom: Vec::new(),
+            cycles_per_frame: 10,
+        }
+    }
+
+    /** Restarts the machine with the given ROM contents */
+    #[wasm_bindgen(js_name = loadRom)]
+    pub fn load_rom(&mut self, rom: &[u8]) {
+        self.rom = rom.to_vec();
+        self.reset();
+    }
+
+    /** Restarts the current ROM */
+    pub fn reset(&mut self) {
+        self.chip8 = Chip8::with_seed(self.seed);
+        self.chip8.quirks = self.quirks;
+        self.chip8.load_rom(ROM::from_bytes(&self.rom));
+    }
+
+    /** Selects a quirks preset by name ("vip", "schip", "xochip" or "default") and restarts */
+    #[wasm_bindgen(js_name = setQuirks)]
+    pub fn set_quirks(&mut self, name: &str) -> Result<(), JsError> {
+        self.quirks =
+            Quirks::preset(name).ok_or_else(|| JsError::new(&format!("Unknown quirks: {name}")))?;
+        self.reset();
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setCyclesPerFrame)]
+    pub fn set_cycles_per_frame(&mut self, cycles: usize) {
+        self.cycles_per_frame = cycles;
+    }
+
+    /**
+     * Runs one 60Hz frame. Returns whether the display changed, or throws when the ROM hits an
+     * unsupported opcode or a bad return
+     */
+    #[wasm_bindgen(js_name = stepFrame)]
+    pub fn step_frame(&mut self) -> Result<bool, JsError> {
+        self.chip8
+            .try_run_frame(self.cycles_per_frame)
+            .map_err(|err| JsError::new(&err.to_string()))?;
+        Ok(std::mem::take(&mut self.chip8.draw_flag))
+    }
+
+    /** Presses a key from 0x0 to 0xF. Only one key is held at a time */
+    #[wasm_bindgen(js_name = keyDown)]
+    pub fn key_down(&mut self, key: u8) {
+        if key <= 0xF {
+            self.chip8.set_input_key(Some(key));
+        }
+    }
+
+    /** Releases a key if it is the one held */
+    #[wasm_bindgen(js_name = keyUp)]
+    pub fn key_up(&mut self, key: u8) {
+        if self.chip8.curr_input_key == Some(key) {
+            self.chip8.set_input_key(None);
+        }
+    }
+
+    /** The display as a `Uint8Array` of `width * height` bytes, 1 for lit pixels and 0 otherwise */
+    pub fn framebuffer(&self) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(WIDTH * HEIGHT);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                pixels.push(self.chip8.display.get_pixel(y, x) as u8);
+            }
+        }
+        pixels
+    }
+
+    /** Whether the sound timer is running, i.e. the page should beep */
+    #[wasm_bindgen(js_name = soundActive)]
+    pub fn sound_active(&self) -> bool {
+        self.chip8.sound_timer > 0
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> usize {
+        WIDTH
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> usize {
+        HEIGHT
+    }
+}
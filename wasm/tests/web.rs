@@ -0,0 +1,60 @@
+//! Run under Node with `cargo test -p chip8-wasm --target wasm32-unknown-unknown`
+#![cfg(target_arch = "wasm32")]
+
+use chip8_wasm::Emulator;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const IBM_LOGO: &[u8] = include_bytes!("../../ROMs/IBM Logo.ch8");
+
+#[wasm_bindgen_test]
+fn ibm_logo_draws_to_the_framebuffer() {
+    let mut emulator = Emulator::new(0);
+    emulator.load_rom(IBM_LOGO);
+    let mut drawn = false;
+    for _ in 0..30 {
+        drawn |= emulator.step_frame().unwrap();
+    }
+
+    let framebuffer = emulator.framebuffer();
+    assert!(drawn);
+    assert_eq!(framebuffer.len(), emulator.width() * emulator.height());
+    assert!(framebuffer.iter().any(|&pixel| pixel == 1));
+}
+
+#[wasm_bindgen_test]
+fn reset_clears_the_display() {
+    let mut emulator = Emulator::new(0);
+    emulator.load_rom(IBM_LOGO);
+    emulator.step_frame().unwrap();
+    emulator.step_frame().unwrap();
+    emulator.reset();
+    assert!(emulator.framebuffer().iter().all(|&pixel| pixel == 0));
+}
+
+#[wasm_bindgen_test]
+fn drawing_waits_for_a_key() {
+    // wait for a key, draw the "0" glyph, then loop forever
+    let mut emulator = Emulator::new(0);
+    emulator.load_rom(&[0xF0, 0x0A, 0xA0, 0x50, 0xD0, 0x05, 0x12, 0x06]);
+    assert!(!emulator.step_frame().unwrap());
+
+    emulator.key_down(0x7);
+    emulator.key_up(0x3);
+    assert!(emulator.step_frame().unwrap());
+    emulator.key_up(0x7);
+    assert!(!emulator.step_frame().unwrap());
+}
+
+#[wasm_bindgen_test]
+fn unknown_quirks_are_rejected() {
+    let mut emulator = Emulator::new(0);
+    assert!(emulator.set_quirks("schip").is_ok());
+    assert!(emulator.set_quirks("nes").is_err());
+}
+
+#[wasm_bindgen_test]
+fn unsupported_opcodes_throw() {
+    let mut emulator = Emulator::new(0);
+    emulator.load_rom(&[0xFF, 0xFF]);
+    assert!(emulator.step_frame().is_err());
+}
@@ -0,0 +1,71 @@
+// Expects the output of `wasm-bindgen --target web` in ./pkg
+import init, { Emulator } from "./pkg/chip8_wasm.js";
+
+// same QWERTY layout as the desktop frontend
+const KEYS = {
+  "1": 0x1, "2": 0x2, "3": 0x3, "4": 0xC,
+  "q": 0x4, "w": 0x5, "e": 0x6, "r": 0xD,
+  "a": 0x7, "s": 0x8, "d": 0x9, "f": 0xE,
+  "z": 0xA, "x": 0x0, "c": 0xB, "v": 0xF,
+};
+const FOREGROUND = [0xff, 0xff, 0xff];
+const BACKGROUND = [0x00, 0x00, 0x00];
+
+await init();
+const emulator = new Emulator((Math.random() * 2 ** 32) >>> 0);
+const canvas = document.getElementById("screen");
+const context = canvas.getContext("2d");
+const image = context.createImageData(emulator.width, emulator.height);
+let running = false;
+
+document.getElementById("rom").addEventListener("change", async (event) => {
+  const file = event.target.files[0];
+  if (!file) return;
+  emulator.loadRom(new Uint8Array(await file.arrayBuffer()));
+  running = true;
+});
+document.getElementById("quirks").addEventListener("change", (event) => {
+  emulator.setQuirks(event.target.value);
+});
+document.addEventListener("keydown", (event) => {
+  const key = KEYS[event.key.toLowerCase()];
+  if (key !== undefined) emulator.keyDown(key);
+});
+document.addEventListener("keyup", (event) => {
+  const key = KEYS[event.key.toLowerCase()];
+  if (key !== undefined) emulator.keyUp(key);
+});
+
+function draw() {
+  const pixels = emulator.framebuffer();
+  for (let i = 0; i < pixels.length; i++) {
+    const color = pixels[i] ? FOREGROUND : BACKGROUND;
+    image.data.set([...color, 0xff], i * 4);
+  }
+  context.putImageData(image, 0, 0);
+}
+
+// step at 60Hz whatever the display's refresh rate
+const FRAME_MS = 1000 / 60;
+let last = performance.now();
+let pending = 0;
+function frame(now) {
+  pending = Math.min(pending + (now - last), FRAME_MS * 4);
+  last = now;
+  let drawn = false;
+  while (pending >= FRAME_MS) {
+    pending -= FRAME_MS;
+    if (!running) continue;
+    try {
+      drawn = emulator.stepFrame() || drawn;
+    } catch (err) {
+      // the ROM hit a bad opcode or return; stop until another one is loaded
+      running = false;
+      console.error(err);
+    }
+  }
+  if (drawn) draw();
+  requestAnimationFrame(frame);
+}
+draw();
+requestAnimationFrame(frame);